@@ -0,0 +1,44 @@
+//! Write every scene as an image, for looking at layout changes without hardware
+//!
+//! `cargo run --bin render -- [--pgm] [OUT_DIR]` writes `OUT_DIR/<scene>.png`
+//! (or `.pgm`), into `render/` by default.
+
+use std::path::PathBuf;
+use std::{env, fs, process};
+
+use display_host::{image, scenes};
+
+fn main() {
+    let mut pgm = false;
+    let mut out_dir = PathBuf::from("render");
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--pgm" => pgm = true,
+            "-h" | "--help" => {
+                eprintln!("usage: render [--pgm] [OUT_DIR]");
+                return;
+            }
+            _ => out_dir = PathBuf::from(arg),
+        }
+    }
+
+    if let Err(e) = fs::create_dir_all(&out_dir) {
+        eprintln!("cannot create {}: {}", out_dir.display(), e);
+        process::exit(1);
+    }
+
+    for scene in scenes::all() {
+        let frame = scene.render();
+        let (bytes, extension) = if pgm {
+            (image::encode_pgm(&frame), "pgm")
+        } else {
+            (image::encode_png(&frame), "png")
+        };
+        let path = out_dir.join(format!("{}.{}", scene.name, extension));
+        if let Err(e) = fs::write(&path, bytes) {
+            eprintln!("cannot write {}: {}", path.display(), e);
+            process::exit(1);
+        }
+        println!("{}", path.display());
+    }
+}
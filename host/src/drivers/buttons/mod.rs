@@ -0,0 +1,13 @@
+#[path = "../../../../src/drivers/buttons/debounce.rs"]
+mod debounce;
+#[path = "../../../../src/drivers/buttons/id.rs"]
+mod id;
+#[path = "../../../../src/drivers/buttons/gestures.rs"]
+pub mod gestures;
+#[path = "../../../../src/drivers/buttons/source.rs"]
+pub mod source;
+
+pub use debounce::{ButtonEvent, ButtonState, ButtonType, SETTLE_PERIOD};
+pub use gestures::GestureEvent;
+pub use id::{ButtonId, ButtonSet};
+pub use source::{ButtonSource, ScriptedButtons};
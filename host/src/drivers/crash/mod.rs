@@ -0,0 +1,4 @@
+#[path = "../../../../src/drivers/crash/record.rs"]
+mod record;
+
+pub use record::{fault_name, CrashKind, CrashRecord};
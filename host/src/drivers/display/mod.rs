@@ -0,0 +1,12 @@
+#[path = "../../../../src/drivers/display/display_write.rs"]
+pub mod display_write;
+#[path = "../../../../src/drivers/display/font16.rs"]
+pub mod font16;
+#[path = "../../../../src/drivers/display/framebuffer.rs"]
+pub mod framebuffer;
+#[path = "../../../../src/drivers/display/geometry.rs"]
+pub mod geometry;
+#[path = "../../../../src/drivers/display/layout.rs"]
+pub mod layout;
+#[path = "../../../../src/drivers/display/widget.rs"]
+pub mod widget;
@@ -0,0 +1,4 @@
+#[path = "../../../../src/drivers/leds/pattern.rs"]
+mod pattern;
+
+pub use pattern::{pwm_on, LedPattern, PWM_STEPS};
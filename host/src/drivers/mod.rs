@@ -0,0 +1,13 @@
+//! The firmware's `drivers` module, minus the hardware
+//!
+//! Each driver keeps only its pure files: the ones the drawing code needs,
+//! and the ones with host tests.
+
+pub mod buttons;
+pub mod crash;
+pub mod display;
+pub mod leds;
+pub mod network;
+pub mod pedals;
+#[path = "../../../src/drivers/storage/mod.rs"]
+pub mod storage;
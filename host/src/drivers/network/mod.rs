@@ -0,0 +1,7 @@
+#[path = "../../../../src/drivers/network/config.rs"]
+pub mod config;
+#[path = "../../../../src/drivers/network/udp.rs"]
+pub mod udp;
+
+pub use config::*;
+pub use udp::*;
@@ -0,0 +1,12 @@
+#[path = "../../../../src/drivers/pedals/calibration.rs"]
+pub mod calibration;
+#[path = "../../../../src/drivers/pedals/counts.rs"]
+mod counts;
+#[path = "../../../../src/drivers/pedals/filter.rs"]
+pub mod filter;
+#[path = "../../../../src/drivers/pedals/plausibility.rs"]
+pub mod plausibility;
+
+pub use calibration::{Calibrator, DeadZones, PedalCalibration};
+pub use counts::{PedalCounts, PedalRange};
+pub use plausibility::PedalFaults;
@@ -0,0 +1,211 @@
+//! PGM and PNG export of rendered frames
+//!
+//! Both formats store the panel's 16 shades as-is. The PNG is 4-bit
+//! grayscale, whose scanlines pack pixels exactly like the framebuffer does,
+//! so encoding is just framing: no filtering, and the image data sits in
+//! stored (uncompressed) deflate blocks. `decode_png` reads back what
+//! `encode_png` writes, which is all the snapshot tests need.
+
+use crate::drivers::display::framebuffer::{Framebuffer, ROW_BYTES};
+use crate::drivers::display::geometry::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// Largest payload of one stored deflate block
+const STORED_BLOCK_MAX: usize = 0xFFFF;
+
+/// Why an image could not be read back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// Not a PNG, or cut short
+    Malformed(&'static str),
+    /// Not a 256x64 4-bit grayscale image
+    WrongFormat,
+    /// Compressed data this decoder does not handle
+    Unsupported(&'static str),
+}
+
+/// Binary PGM (P5) with a maximum value of 15
+pub fn encode_pgm(frame: &Framebuffer) -> Vec<u8> {
+    let mut out = format!("P5\n{} {}\n15\n", DISPLAY_WIDTH, DISPLAY_HEIGHT).into_bytes();
+    for y in 0..DISPLAY_HEIGHT {
+        for x in 0..DISPLAY_WIDTH {
+            out.push(frame.pixel(x, y));
+        }
+    }
+    out
+}
+
+/// 4-bit grayscale PNG
+pub fn encode_png(frame: &Framebuffer) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(DISPLAY_WIDTH as u32).to_be_bytes());
+    header.extend_from_slice(&(DISPLAY_HEIGHT as u32).to_be_bytes());
+    // Bit depth 4, grayscale, deflate, no filtering, no interlace
+    header.extend_from_slice(&[4, 0, 0, 0, 0]);
+
+    // Every scanline starts with filter type 0 (none)
+    let mut raw = Vec::with_capacity(DISPLAY_HEIGHT * (ROW_BYTES + 1));
+    for row in frame.as_bytes().chunks(ROW_BYTES) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut out = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &header);
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Read a PNG written by `encode_png` back into a framebuffer
+pub fn decode_png(bytes: &[u8]) -> Result<Framebuffer, ImageError> {
+    let mut rest = bytes
+        .strip_prefix(&PNG_SIGNATURE[..])
+        .ok_or(ImageError::Malformed("missing PNG signature"))?;
+
+    let mut header = None;
+    let mut compressed = Vec::new();
+    while !rest.is_empty() {
+        if rest.len() < 12 {
+            return Err(ImageError::Malformed("truncated chunk"));
+        }
+        let len = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+        let kind = &rest[4..8];
+        let data = rest.get(8..8 + len).ok_or(ImageError::Malformed("truncated chunk"))?;
+        match kind {
+            b"IHDR" => header = Some(data),
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = &rest[(12 + len).min(rest.len())..];
+    }
+
+    let mut expected = (DISPLAY_WIDTH as u32).to_be_bytes().to_vec();
+    expected.extend_from_slice(&(DISPLAY_HEIGHT as u32).to_be_bytes());
+    expected.extend_from_slice(&[4, 0, 0, 0, 0]);
+    if header != Some(&expected[..]) {
+        return Err(ImageError::WrongFormat);
+    }
+
+    let raw = zlib_unstored(&compressed)?;
+    if raw.len() != DISPLAY_HEIGHT * (ROW_BYTES + 1) {
+        return Err(ImageError::Malformed("wrong amount of image data"));
+    }
+
+    let mut frame = Framebuffer::new();
+    for (y, line) in raw.chunks(ROW_BYTES + 1).enumerate() {
+        if line[0] != 0 {
+            return Err(ImageError::Unsupported("filtered scanline"));
+        }
+        for (i, byte) in line[1..].iter().enumerate() {
+            frame.set_pixel(2 * i, y, byte >> 4);
+            frame.set_pixel(2 * i + 1, y, byte & 0x0F);
+        }
+    }
+    Ok(frame)
+}
+
+/// Number of pixels that differ, plus an image of them
+///
+/// The diff image shows `expected` dimmed, with every differing pixel white.
+pub fn diff(expected: &Framebuffer, actual: &Framebuffer) -> (usize, Framebuffer) {
+    let mut image = Framebuffer::new();
+    let mut count = 0;
+    for y in 0..DISPLAY_HEIGHT {
+        for x in 0..DISPLAY_WIDTH {
+            let shade = if expected.pixel(x, y) == actual.pixel(x, y) {
+                expected.pixel(x, y) / 4
+            } else {
+                count += 1;
+                0xF
+            };
+            image.set_pixel(x, y, shade);
+        }
+    }
+    (count, image)
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// zlib stream made of stored deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Deflate, 32K window, no preset dictionary, header check bits
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(STORED_BLOCK_MAX).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Inverse of `zlib_stored`
+fn zlib_unstored(stream: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let truncated = ImageError::Malformed("truncated image data");
+    if stream.len() < 6 || stream[0] & 0x0F != 8 {
+        return Err(ImageError::Malformed("not a zlib stream"));
+    }
+
+    let mut out = Vec::new();
+    let mut pos = 2;
+    loop {
+        let flags = *stream.get(pos).ok_or(truncated.clone())?;
+        if flags & 0b110 != 0 {
+            return Err(ImageError::Unsupported("compressed deflate block"));
+        }
+        let len_bytes = stream.get(pos + 1..pos + 5).ok_or(truncated.clone())?;
+        let len = u16::from_le_bytes([len_bytes[0], len_bytes[1]]);
+        if !len != u16::from_le_bytes([len_bytes[2], len_bytes[3]]) {
+            return Err(ImageError::Malformed("bad stored block length"));
+        }
+        pos += 5;
+        let block = stream.get(pos..pos + len as usize).ok_or(truncated.clone())?;
+        out.extend_from_slice(block);
+        pos += len as usize;
+        if flags & 1 != 0 {
+            break;
+        }
+    }
+
+    let checksum = stream.get(pos..pos + 4).ok_or(truncated)?;
+    if u32::from_be_bytes(checksum.try_into().unwrap()) != adler32(&out) {
+        return Err(ImageError::Malformed("image data checksum mismatch"));
+    }
+    Ok(out)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
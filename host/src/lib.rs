@@ -0,0 +1,56 @@
+//! Host-side renderer for the steering wheel display
+//!
+//! Builds the firmware's drawing code (framebuffer, widgets, screens and the
+//! full-screen takeovers) for the build machine by including the firmware
+//! source files directly. The module tree mirrors the firmware crate so the
+//! `crate::` paths in those files resolve; the few hardware drivers they name
+//! are replaced by the pure parts of the driver or a small stand-in.
+//!
+//! Frames land in the same packed 256x64 Gray4 `Framebuffer` that is sent to
+//! the panel, and can be exported as PGM or PNG (`image`). `scenes` holds the
+//! fixed vehicle states each screen is rendered from, and
+//! `tests/snapshots.rs` compares the results against the golden images in
+//! `golden/`.
+//!
+//! # Usage
+//!
+//! ```text
+//! cd host
+//! cargo test                      # compare every screen against golden/
+//! UPDATE_GOLDEN=1 cargo test      # accept the current rendering
+//! cargo run --bin render -- out   # write every scene to out/*.png
+//! ```
+
+#[path = "../../src/control/mod.rs"]
+pub mod control;
+pub mod drivers;
+#[path = "../../src/protocol/mod.rs"]
+pub mod protocol;
+#[path = "../../src/screens/mod.rs"]
+pub mod screens;
+#[path = "../../src/state/mod.rs"]
+pub mod state;
+
+pub mod image;
+pub mod scenes;
+
+/// The firmware code logs and asserts through defmt; on the host its output is
+/// dropped and its panics become ordinary panics
+mod defmt_sink {
+    #[defmt::global_logger]
+    struct Discard;
+
+    unsafe impl defmt::Logger for Discard {
+        fn acquire() {}
+        unsafe fn flush() {}
+        unsafe fn release() {}
+        unsafe fn write(_bytes: &[u8]) {}
+    }
+
+    defmt::timestamp!("");
+
+    #[defmt::panic_handler]
+    fn panic() -> ! {
+        panic!("defmt panic")
+    }
+}
@@ -0,0 +1,271 @@
+//! Fixed vehicle states the snapshot tests render
+//!
+//! Each scene is one frame of the display task: the same clear, takeover,
+//! screen and pixel shift order, drawn from a hand-built `StateData` at a
+//! fixed uptime so the result never depends on the clock. The fixtures press
+//! a button just before that uptime and turn the pixel shift off, so only the
+//! burn-in scenes are dimmed, shifted or show the screensaver.
+
+use core::fmt::Write;
+use embassy_time::{Duration, Instant};
+
+use crate::control::{
+    BmsFlags, BurnIn, CruiseButton, DriveInputs, DriveState, Failsafe, FailsafeConfig, TurnSignal,
+};
+use crate::drivers::crash::{CrashKind, CrashRecord};
+use crate::drivers::display::framebuffer::Framebuffer;
+use crate::drivers::display::geometry::DISPLAY_BLACK;
+use crate::drivers::network::SendStats;
+use crate::drivers::pedals::PedalFaults;
+use crate::screens::{NetworkStatus, ScreenContext, ScreenId, ScreenManager};
+use crate::state::StateData;
+
+/// Uptime every scene is rendered at, unless it needs a particular blink phase
+const UPTIME_MS: u32 = 60_000;
+
+/// Age of the last VC/BMS message while both links are healthy
+const LINK_AGE_MS: u64 = 40;
+
+/// What takes up the display
+#[derive(Clone, Copy)]
+pub enum View {
+    Screen(ScreenId),
+    /// Unacknowledged BPS trip
+    BmsTrip,
+    Crash(CrashRecord),
+}
+
+pub struct Scene {
+    /// Golden image file stem
+    pub name: &'static str,
+    pub view: View,
+    pub data: StateData,
+    pub network: NetworkStatus,
+    /// Milliseconds since the display task started
+    pub uptime_ms: u32,
+}
+
+impl Scene {
+    fn new(name: &'static str, view: View, data: StateData) -> Self {
+        Self {
+            name,
+            view,
+            data,
+            network: NetworkStatus::default(),
+            uptime_ms: UPTIME_MS,
+        }
+    }
+
+    fn with_network(mut self, network: NetworkStatus) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn now(&self) -> Instant {
+        Instant::from_millis(self.uptime_ms as u64)
+    }
+
+    /// Draw the scene the way the display task would
+    pub fn render(&self) -> Framebuffer {
+        let now = self.now();
+        let mut frame = Framebuffer::new();
+        frame.fill(DISPLAY_BLACK);
+
+        match &self.view {
+            View::Crash(record) => frame.write_crash(record),
+            View::BmsTrip => {
+                let alert = self.data.steering.bms_alert;
+                let (mut flash, mut last_flash) = (false, 0);
+                frame.write_bms_flash(true, alert.reason(), &mut flash, &mut last_flash, self.uptime_ms);
+            }
+            View::Screen(id) => {
+                let steering = &self.data.steering;
+                let parked = steering.drive.mode() == DriveState::Neutral;
+                let idle = steering.dimming.idle_for(now);
+                let ctx = ScreenContext {
+                    data: &self.data,
+                    now,
+                    current_time: self.uptime_ms,
+                    time_since_vc: self.data.time_since_vc(now),
+                    time_since_bms: self.data.time_since_bms(now),
+                    network: self.network,
+                    dim_static: steering.burn_in.dim_static(idle),
+                };
+                let mut screens = ScreenManager::new();
+                if steering.burn_in.screensaver(idle, parked, steering.buttons.lock_on) {
+                    screens.screensaver().render(&mut frame, &ctx);
+                } else {
+                    screens.get(*id).render(&mut frame, &ctx);
+                }
+            }
+        }
+
+        // Only the horizontal step is in the frame; the panel does the vertical one
+        let (dx, _) = self.data.steering.burn_in.offset(now);
+        frame.shift_x(dx);
+        frame
+    }
+}
+
+/// Every scene, in a stable order
+pub fn all() -> Vec<Scene> {
+    vec![
+        Scene::new("main_parked", View::Screen(ScreenId::Main), parked()),
+        Scene::new("main_cruise", View::Screen(ScreenId::Main), cruising()),
+        Scene::new("main_links_lost", View::Screen(ScreenId::Main), links_lost()),
+        Scene::new("main_pedal_fault", View::Screen(ScreenId::Main), pedal_fault()),
+        Scene::new("main_bps_banner", View::Screen(ScreenId::Main), bps_tripped(true)),
+        Scene::new("debug", View::Screen(ScreenId::Debug), cruising()),
+        Scene::new("bms", View::Screen(ScreenId::Bms), parked()),
+        Scene::new("bms_no_data", View::Screen(ScreenId::Bms), links_lost()),
+        Scene::new("bms_tripped", View::Screen(ScreenId::Bms), bps_tripped(true)),
+        Scene::new("network", View::Screen(ScreenId::Network), parked()).with_network(busy_network()),
+        Scene::new("settings", View::Screen(ScreenId::Settings), parked()),
+        Scene::new("bps_trip", View::BmsTrip, bps_tripped(false)),
+        Scene::new("crash_panic", View::Crash(panic_record()), parked()),
+        Scene::new("crash_hardfault", View::Crash(hardfault_record()), parked()),
+        Scene::new("main_static_dimmed", View::Screen(ScreenId::Main), parked_idle(45)),
+        Scene::new("main_shifted", View::Screen(ScreenId::Main), shifted()),
+        Scene::new("screensaver", View::Screen(ScreenId::Main), parked_idle(60)),
+    ]
+}
+
+/// Link up with some traffic sent, a few BMS sends failed
+fn busy_network() -> NetworkStatus {
+    NetworkStatus {
+        link_up: true,
+        sent: [
+            SendStats { sent: 1200, errors: 0, dropped: 0 },
+            SendStats { sent: 1200, errors: 1, dropped: 2 },
+            SendStats { sent: 240, errors: 0, dropped: 0 },
+        ],
+    }
+}
+
+fn at(uptime_ms: u32) -> Instant {
+    Instant::from_millis(uptime_ms as u64)
+}
+
+/// Nothing set yet, but a button pressed just now and the pixel shift off
+fn attended() -> StateData {
+    let mut data = StateData::default();
+    data.steering.dimming.input(at(UPTIME_MS));
+    data.steering.burn_in.shift_period = None;
+    data
+}
+
+/// Both links healthy, parked in neutral with the lock on
+fn parked() -> StateData {
+    let now = at(UPTIME_MS);
+    let link = Duration::from_millis(LINK_AGE_MS);
+    let mut data = StateData {
+        last_vc_message: Some(now - link),
+        last_bms_message: Some(now - link),
+        ..attended()
+    };
+
+    data.vc.low_voltage = 12.6;
+    data.vc.regen_enabled = true;
+    data.vc.throttle_enabled = true;
+    data.bms.voltage = 118.4;
+    data.bms.current = -1.2;
+    data.bms.soc = 87.0;
+    data.bms.min_cell_voltage = 3.94;
+    data.bms.max_cell_voltage = 3.98;
+    data.bms.max_temperature = 31.5;
+
+    let steering = &mut data.steering;
+    steering.buttons.lock_on = true;
+    steering.failsafe = Failsafe::new(FailsafeConfig::default()).update(link, link, 0.0);
+    data
+}
+
+/// Cruising at 45 with the left signal lit
+fn cruising() -> StateData {
+    let now = at(UPTIME_MS);
+    let mut data = parked();
+    data.vc.speed = 45.0;
+    data.vc.left_motor_velocity = 45.0;
+    data.vc.right_motor_velocity = 44.6;
+    data.bms.current = 18.3;
+
+    let steering = &mut data.steering;
+    steering.buttons.lock_on = false;
+    steering.throttle = 0.42;
+    steering.raw_throttle = 1730;
+    steering.brake = 0.05;
+    steering.raw_brake = 310;
+
+    let stopped = DriveInputs {
+        brake: 1.0,
+        brake_input: 1.0,
+        ..DriveInputs::default()
+    };
+    let moving = DriveInputs {
+        speed: 45.0,
+        ..DriveInputs::default()
+    };
+    steering.drive.request(DriveState::Drive, stopped).unwrap();
+    steering.drive.request(DriveState::Cruise, moving).unwrap();
+    steering.cruise.engage(CruiseButton::Down, 45.0);
+    steering.turn.press(TurnSignal::Left, now - Duration::from_millis(100));
+    data
+}
+
+/// Nothing heard from the VC or BMS since boot
+fn links_lost() -> StateData {
+    attended()
+}
+
+/// `parked()` with no button pressed for `idle_s` seconds
+fn parked_idle(idle_s: u64) -> StateData {
+    let mut data = parked();
+    data.steering.dimming.input(at(UPTIME_MS) - Duration::from_secs(idle_s));
+    data
+}
+
+/// `parked()` with the default pixel shift, one step into its orbit
+fn shifted() -> StateData {
+    let mut data = parked();
+    data.steering.burn_in = BurnIn::default();
+    data
+}
+
+/// Brake channel shorted while parked
+fn pedal_fault() -> StateData {
+    let mut data = parked();
+    data.steering.pedal_faults = PedalFaults::BRAKE_RANGE;
+    data
+}
+
+/// BPS tripped on overtemperature, optionally acknowledged by the driver
+fn bps_tripped(acknowledged: bool) -> StateData {
+    let mut data = parked();
+    let flags = BmsFlags::from_bits(BmsFlags::BPS_TRIPPED.bits() | BmsFlags::OVERTEMP.bits());
+    data.bms.flags = flags.bits();
+    data.bms.max_temperature = 61.0;
+    data.steering.bms_alert.update(flags);
+    if acknowledged {
+        data.steering.bms_alert.acknowledge();
+    }
+    data
+}
+
+fn panic_record() -> CrashRecord {
+    let mut record = CrashRecord::new(CrashKind::Panic, 73_412);
+    record.pc = 0x0800_4F2A;
+    record.lr = 0x0800_4E11;
+    write!(record, "called `Option::unwrap()` on a `None` value").ok();
+    record
+}
+
+fn hardfault_record() -> CrashRecord {
+    let mut record = CrashRecord::new(CrashKind::HardFault, 5_120);
+    record.pc = 0x0800_2C40;
+    record.lr = 0xFFFF_FFF9;
+    // Precise data bus error with a valid BFAR
+    record.cfsr = (1 << 9) | (1 << 15);
+    record.hfsr = 1 << 30;
+    record.bfar = 0x2003_0000;
+    record
+}
@@ -0,0 +1,112 @@
+//! Golden-image snapshots of every screen
+//!
+//! Renders each scene in `display_host::scenes` and compares it pixel for
+//! pixel with `golden/<scene>.png`. On a mismatch the new rendering and a
+//! diff image (changed pixels white) are written next to the test binary's
+//! temp dir, under `snapshots/`. Run with `UPDATE_GOLDEN=1` to accept the
+//! current renderings after reviewing them.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use display_host::drivers::display::framebuffer::Framebuffer;
+use display_host::image::{decode_png, diff, encode_pgm, encode_png};
+use display_host::scenes;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("golden")
+}
+
+fn output_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("snapshots");
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn updating() -> bool {
+    env::var_os("UPDATE_GOLDEN").is_some_and(|value| value != "0")
+}
+
+#[test]
+fn screens_match_golden_images() {
+    let golden = golden_dir();
+    let mut failures = Vec::new();
+
+    for scene in scenes::all() {
+        let frame = scene.render();
+        let path = golden.join(format!("{}.png", scene.name));
+
+        if updating() {
+            fs::create_dir_all(&golden).unwrap();
+            fs::write(&path, encode_png(&frame)).unwrap();
+            continue;
+        }
+
+        let expected = match fs::read(&path) {
+            Ok(bytes) => decode_png(&bytes)
+                .unwrap_or_else(|e| panic!("{}: unreadable golden image: {:?}", path.display(), e)),
+            Err(_) => {
+                failures.push(format!("{}: no golden image", scene.name));
+                continue;
+            }
+        };
+
+        let (changed, diff_image) = diff(&expected, &frame);
+        if changed > 0 {
+            let out = output_dir();
+            let actual_path = out.join(format!("{}.png", scene.name));
+            fs::write(&actual_path, encode_png(&frame)).unwrap();
+            fs::write(out.join(format!("{}.diff.png", scene.name)), encode_png(&diff_image)).unwrap();
+            failures.push(format!(
+                "{}: {} pixels differ, see {}",
+                scene.name,
+                changed,
+                actual_path.display()
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "snapshot mismatch (UPDATE_GOLDEN=1 accepts the new renderings):\n  {}",
+        failures.join("\n  ")
+    );
+}
+
+#[test]
+fn every_golden_image_has_a_scene() {
+    let names: BTreeSet<String> = scenes::all().iter().map(|scene| format!("{}.png", scene.name)).collect();
+    assert_eq!(names.len(), scenes::all().len(), "scene names must be unique");
+
+    let Ok(entries) = fs::read_dir(golden_dir()) else {
+        return;
+    };
+    for entry in entries {
+        let name = entry.unwrap().file_name().into_string().unwrap();
+        assert!(names.contains(&name), "golden/{} has no scene, delete it", name);
+    }
+}
+
+#[test]
+fn png_round_trip() {
+    let mut frame = Framebuffer::new();
+    for x in 0..256 {
+        frame.set_pixel(x, x % 64, (x % 16) as u8);
+    }
+    let decoded = decode_png(&encode_png(&frame)).unwrap();
+    assert_eq!(diff(&frame, &decoded).0, 0);
+    assert_eq!(decoded.as_bytes(), frame.as_bytes());
+}
+
+#[test]
+fn pgm_has_one_byte_per_pixel() {
+    let mut frame = Framebuffer::new();
+    frame.set_pixel(255, 63, 0xF);
+    let pgm = encode_pgm(&frame);
+    let header = b"P5\n256 64\n15\n";
+    assert_eq!(&pgm[..header.len()], header);
+    assert_eq!(pgm.len(), header.len() + 256 * 64);
+    assert_eq!(*pgm.last().unwrap(), 0xF);
+}
@@ -0,0 +1,202 @@
+//! Battery protection (BPS) trip alert
+//!
+//! Decodes `BMS_State.flags` and decides when the driver has to be told.
+//! A BPS trip raises the alert, which takes over the display until the driver
+//! acknowledges it. Once acknowledged it drops to a banner for as long as the
+//! trip lasts; any new fault bit raises the full alert again.
+
+/// BMS status flags as sent in `BMS_State.flags`
+///
+/// Provisional bit layout: it has not been confirmed against the BMS
+/// firmware yet, so only `BPS_TRIPPED` should be relied on. The other bits
+/// only pick the reason shown to the driver. Keep messages.proto in step
+/// when the real layout is known.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub struct BmsFlags(u32);
+
+impl BmsFlags {
+    pub const NONE: Self = Self(0);
+    /// BPS has opened the battery contactors (strobe on)
+    pub const BPS_TRIPPED: Self = Self(1 << 0);
+    pub const OVERVOLTAGE: Self = Self(1 << 1);
+    pub const UNDERVOLTAGE: Self = Self(1 << 2);
+    pub const OVERTEMP: Self = Self(1 << 3);
+    /// Discharge overcurrent
+    pub const OVERCURRENT: Self = Self(1 << 4);
+    pub const CHARGE_OVERCURRENT: Self = Self(1 << 5);
+    /// Too cold to charge
+    pub const UNDERTEMP: Self = Self(1 << 6);
+    /// HV isolation fault
+    pub const ISOLATION: Self = Self(1 << 7);
+    /// BMS lost contact with its cell monitors
+    pub const MONITOR_COMM: Self = Self(1 << 8);
+
+    /// Fault reasons, most severe first, with their display labels
+    const REASONS: [(Self, &'static str); 8] = [
+        (Self::ISOLATION, "ISOLATN"),
+        (Self::OVERTEMP, "OVERTEMP"),
+        (Self::OVERVOLTAGE, "OVERVOLT"),
+        (Self::UNDERVOLTAGE, "UNDERVLT"),
+        (Self::OVERCURRENT, "OVERCURR"),
+        (Self::CHARGE_OVERCURRENT, "CHG CURR"),
+        (Self::UNDERTEMP, "UNDERTMP"),
+        (Self::MONITOR_COMM, "CELL COM"),
+    ];
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Label of the most severe fault reason (at most 8 characters)
+    pub fn reason(self) -> &'static str {
+        Self::REASONS
+            .iter()
+            .find(|(flag, _)| self.contains(*flag))
+            .map_or("UNKNOWN", |(_, label)| label)
+    }
+}
+
+/// Alert state for BPS trips
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BmsAlert {
+    flags: BmsFlags,
+    /// Flags the driver has already seen
+    acknowledged: BmsFlags,
+}
+
+impl BmsAlert {
+    pub const fn new() -> Self {
+        Self {
+            flags: BmsFlags::NONE,
+            acknowledged: BmsFlags::NONE,
+        }
+    }
+
+    /// Feed the latest BMS flags; returns true if this raised the alert
+    pub fn update(&mut self, flags: BmsFlags) -> bool {
+        let was_alerting = self.alerting();
+        self.flags = flags;
+        // Forget acknowledgements for faults that have cleared
+        self.acknowledged = BmsFlags(self.acknowledged.0 & flags.0);
+        self.alerting() && !was_alerting
+    }
+
+    /// Driver has seen the alert
+    pub fn acknowledge(&mut self) {
+        self.acknowledged = self.flags;
+    }
+
+    /// BPS is tripped
+    pub fn tripped(&self) -> bool {
+        self.flags.contains(BmsFlags::BPS_TRIPPED)
+    }
+
+    /// Tripped with something the driver has not acknowledged yet
+    pub fn alerting(&self) -> bool {
+        self.tripped() && !self.acknowledged.contains(self.flags)
+    }
+
+    pub fn flags(&self) -> BmsFlags {
+        self.flags
+    }
+
+    /// Display label of the trip reason
+    pub fn reason(&self) -> &'static str {
+        self.flags.reason()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(bits: &[BmsFlags]) -> BmsFlags {
+        BmsFlags::from_bits(bits.iter().fold(0, |all, flag| all | flag.bits()))
+    }
+
+    #[test]
+    fn reason_is_the_most_severe_flag() {
+        assert_eq!(BmsFlags::NONE.reason(), "UNKNOWN");
+        assert_eq!(BmsFlags::BPS_TRIPPED.reason(), "UNKNOWN");
+        assert_eq!(flags(&[BmsFlags::BPS_TRIPPED, BmsFlags::UNDERTEMP]).reason(), "UNDERTMP");
+        assert_eq!(
+            flags(&[BmsFlags::OVERVOLTAGE, BmsFlags::OVERTEMP, BmsFlags::MONITOR_COMM]).reason(),
+            "OVERTEMP"
+        );
+        assert_eq!(flags(&[BmsFlags::OVERTEMP, BmsFlags::ISOLATION]).reason(), "ISOLATN");
+        for (_, label) in BmsFlags::REASONS {
+            assert!(label.len() <= 8);
+        }
+    }
+
+    #[test]
+    fn faults_without_a_trip_do_not_alert() {
+        let mut alert = BmsAlert::new();
+        assert!(!alert.update(BmsFlags::OVERTEMP));
+        assert!(!alert.tripped());
+        assert!(!alert.alerting());
+    }
+
+    #[test]
+    fn trip_alerts_until_acknowledged() {
+        let mut alert = BmsAlert::new();
+        let trip = flags(&[BmsFlags::BPS_TRIPPED, BmsFlags::OVERTEMP]);
+
+        assert!(alert.update(trip));
+        assert!(alert.tripped() && alert.alerting());
+        assert_eq!(alert.reason(), "OVERTEMP");
+
+        // Repeats of the same flags do not raise it again
+        assert!(!alert.update(trip));
+
+        alert.acknowledge();
+        assert!(alert.tripped());
+        assert!(!alert.alerting());
+        assert!(!alert.update(trip));
+        assert!(!alert.alerting());
+    }
+
+    #[test]
+    fn new_fault_raises_the_alert_again() {
+        let mut alert = BmsAlert::new();
+        alert.update(flags(&[BmsFlags::BPS_TRIPPED, BmsFlags::OVERTEMP]));
+        alert.acknowledge();
+
+        let worse = flags(&[BmsFlags::BPS_TRIPPED, BmsFlags::OVERTEMP, BmsFlags::ISOLATION]);
+        assert!(alert.update(worse));
+        assert_eq!(alert.reason(), "ISOLATN");
+    }
+
+    #[test]
+    fn cleared_faults_forget_their_acknowledgement() {
+        let mut alert = BmsAlert::new();
+        let trip = flags(&[BmsFlags::BPS_TRIPPED, BmsFlags::OVERTEMP]);
+        alert.update(trip);
+        alert.acknowledge();
+
+        assert!(!alert.update(BmsFlags::NONE));
+        assert!(!alert.tripped());
+
+        // The same trip coming back is new to the driver
+        assert!(alert.update(trip));
+    }
+
+    #[test]
+    fn acknowledging_without_a_trip_does_nothing() {
+        let mut alert = BmsAlert::new();
+        alert.acknowledge();
+        assert!(alert.update(BmsFlags::BPS_TRIPPED));
+    }
+}
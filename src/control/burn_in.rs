@@ -0,0 +1,141 @@
+//! OLED burn-in mitigation policy
+//!
+//! The main screen keeps the same icons, letters and box outlines in the
+//! same place for hours during endurance events. Three things spread the
+//! wear:
+//!
+//! - Pixel shift: the whole frame walks around `SHIFT_ORBIT`, one step every
+//!   `shift_period`
+//! - Static decorations are drawn dimmer after `dim_after` without button
+//!   input
+//! - Parked in neutral with the lock on, the screensaver replaces every screen
+//!   after `screensaver_after` without button input
+//!
+//! Inactivity is the time since the last button input, as tracked by
+//! `Dimming`.
+
+use embassy_time::{Duration, Instant};
+
+/// Frame offsets the pixel shift steps through: the rest position, then a
+/// one pixel ring around it
+pub const SHIFT_ORBIT: [(i32, i32); 9] = [
+    (0, 0),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// Burn-in mitigation settings, kept in the shared steering state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BurnIn {
+    /// Time between pixel shift steps (None to keep the frame still)
+    pub shift_period: Option<Duration>,
+    /// Dim static decorations after this long without input (None to never dim)
+    pub dim_after: Option<Duration>,
+    /// Screensaver delay once parked and locked (None to disable)
+    pub screensaver_after: Option<Duration>,
+}
+
+impl BurnIn {
+    /// Offset to draw the frame at, at `now`
+    pub fn offset(&self, now: Instant) -> (i32, i32) {
+        let Some(period) = self.shift_period else {
+            return (0, 0);
+        };
+        let step = now.as_millis() / period.as_millis().max(1);
+        SHIFT_ORBIT[(step % SHIFT_ORBIT.len() as u64) as usize]
+    }
+
+    /// True once static decorations should be dimmed
+    pub fn dim_static(&self, idle: Duration) -> bool {
+        self.dim_after.is_some_and(|after| idle >= after)
+    }
+
+    /// True while the screensaver should replace the screen
+    ///
+    /// `parked` is true in neutral, `locked` while the lock toggle is on.
+    pub fn screensaver(&self, idle: Duration, parked: bool, locked: bool) -> bool {
+        parked && locked && self.screensaver_after.is_some_and(|after| idle >= after)
+    }
+}
+
+impl Default for BurnIn {
+    fn default() -> Self {
+        Self {
+            shift_period: Some(Duration::from_secs(60)),
+            dim_after: Some(Duration::from_secs(30)),
+            screensaver_after: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn offset_steps_around_the_orbit_and_wraps() {
+        let burn_in = BurnIn::default();
+        for (step, expected) in SHIFT_ORBIT.iter().enumerate() {
+            let start = Instant::from_secs(60 * step as u64);
+            assert_eq!(burn_in.offset(start), *expected);
+            // Holds for the whole period
+            assert_eq!(burn_in.offset(start + secs(59)), *expected);
+        }
+        assert_eq!(burn_in.offset(Instant::from_secs(60 * 9)), (0, 0));
+        assert_eq!(burn_in.offset(Instant::from_secs(60 * 10)), (1, 0));
+    }
+
+    #[test]
+    fn offset_stays_at_rest_with_the_shift_off() {
+        let burn_in = BurnIn {
+            shift_period: None,
+            ..BurnIn::default()
+        };
+        for step in 0..20 {
+            assert_eq!(burn_in.offset(Instant::from_secs(60 * step)), (0, 0));
+        }
+    }
+
+    #[test]
+    fn orbit_never_moves_more_than_a_pixel() {
+        assert!(SHIFT_ORBIT.iter().all(|(dx, dy)| dx.abs() <= 1 && dy.abs() <= 1));
+    }
+
+    #[test]
+    fn static_decorations_dim_after_the_idle_delay() {
+        let burn_in = BurnIn::default();
+        assert!(!burn_in.dim_static(secs(29)));
+        assert!(burn_in.dim_static(secs(30)));
+
+        let never = BurnIn {
+            dim_after: None,
+            ..BurnIn::default()
+        };
+        assert!(!never.dim_static(secs(100_000)));
+    }
+
+    #[test]
+    fn screensaver_needs_parked_locked_and_idle() {
+        let burn_in = BurnIn::default();
+        assert!(burn_in.screensaver(secs(60), true, true));
+        assert!(!burn_in.screensaver(secs(59), true, true));
+        assert!(!burn_in.screensaver(secs(1000), false, true));
+        assert!(!burn_in.screensaver(secs(1000), true, false));
+
+        let never = BurnIn {
+            screensaver_after: None,
+            ..BurnIn::default()
+        };
+        assert!(!never.screensaver(secs(100_000), true, true));
+    }
+}
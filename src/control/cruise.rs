@@ -0,0 +1,167 @@
+//! Cruise control set-speed manager
+//!
+//! Keeps the cruise target speed driven by CruiseUp/CruiseDown:
+//!
+//! - Engaging with CruiseDown (set) latches the current speed; engaging with
+//!   CruiseUp (resume) returns to the previous target, or latches the current
+//!   speed if cruise has not been engaged since boot
+//! - While engaged, a short press steps the target by `step`
+//! - Holding a button ramps the target by `ramp_step` per `Held` report
+//! - The target always stays within `min_speed` - `max_speed`
+//!
+//! Whether cruise is engaged is the drive mode controller's call (cruise is
+//! only entered from drive, and brake/regen drops it back to drive), so this
+//! only owns the number. Speeds are in the units the VC reports.
+
+/// Which cruise button was used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum CruiseButton {
+    /// CruiseUp: resume, or raise the target
+    Up,
+    /// CruiseDown: set, or lower the target
+    Down,
+}
+
+/// Step sizes and limits for the target speed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CruiseConfig {
+    /// Change per short press
+    pub step: f32,
+    /// Change per `Held` report while a button is held
+    pub ramp_step: f32,
+    /// Lowest target speed
+    pub min_speed: f32,
+    /// Highest target speed
+    pub max_speed: f32,
+}
+
+impl Default for CruiseConfig {
+    fn default() -> Self {
+        Self {
+            step: 1.0,
+            // Held reports every 250ms, so 2 units per second
+            ramp_step: 0.5,
+            // Matches the drive mode controller's engage limit
+            min_speed: 5.0,
+            max_speed: 60.0,
+        }
+    }
+}
+
+/// Cruise target speed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CruiseControl {
+    pub config: CruiseConfig,
+    target: Option<f32>,
+}
+
+impl CruiseControl {
+    pub const fn new(config: CruiseConfig) -> Self {
+        Self { config, target: None }
+    }
+
+    /// Current target, 0.0 before cruise was first engaged
+    pub fn target(&self) -> f32 {
+        self.target.unwrap_or(0.0)
+    }
+
+    /// Cruise was just engaged at `speed`; returns the target
+    pub fn engage(&mut self, button: CruiseButton, speed: f32) -> f32 {
+        let target = match (button, self.target) {
+            (CruiseButton::Up, Some(previous)) => previous,
+            _ => speed,
+        };
+        self.set(target)
+    }
+
+    /// Short press while engaged; returns the new target
+    pub fn step(&mut self, button: CruiseButton) -> f32 {
+        self.adjust(button, self.config.step)
+    }
+
+    /// Button still held while engaged; returns the new target
+    pub fn ramp(&mut self, button: CruiseButton) -> f32 {
+        self.adjust(button, self.config.ramp_step)
+    }
+
+    fn adjust(&mut self, button: CruiseButton, amount: f32) -> f32 {
+        let delta = match button {
+            CruiseButton::Up => amount,
+            CruiseButton::Down => -amount,
+        };
+        self.set(self.target() + delta)
+    }
+
+    fn set(&mut self, target: f32) -> f32 {
+        let target = target.clamp(self.config.min_speed, self.config.max_speed);
+        self.target = Some(target);
+        target
+    }
+}
+
+impl Default for CruiseControl {
+    fn default() -> Self {
+        Self::new(CruiseConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_latches_the_current_speed() {
+        let mut cruise = CruiseControl::default();
+        assert_eq!(cruise.target(), 0.0);
+        assert_eq!(cruise.engage(CruiseButton::Down, 32.5), 32.5);
+        assert_eq!(cruise.target(), 32.5);
+
+        // Set always takes the speed at the time
+        assert_eq!(cruise.engage(CruiseButton::Down, 40.0), 40.0);
+    }
+
+    #[test]
+    fn resume_returns_to_the_previous_target() {
+        let mut cruise = CruiseControl::default();
+        // Nothing to resume yet: latch the current speed
+        assert_eq!(cruise.engage(CruiseButton::Up, 20.0), 20.0);
+        cruise.step(CruiseButton::Up);
+        assert_eq!(cruise.engage(CruiseButton::Up, 12.0), 21.0);
+    }
+
+    #[test]
+    fn short_presses_step_the_target() {
+        let mut cruise = CruiseControl::default();
+        cruise.engage(CruiseButton::Down, 30.0);
+        assert_eq!(cruise.step(CruiseButton::Up), 31.0);
+        assert_eq!(cruise.step(CruiseButton::Up), 32.0);
+        assert_eq!(cruise.step(CruiseButton::Down), 31.0);
+    }
+
+    #[test]
+    fn held_reports_ramp_the_target() {
+        let mut cruise = CruiseControl::default();
+        cruise.engage(CruiseButton::Down, 30.0);
+        for _ in 0..4 {
+            cruise.ramp(CruiseButton::Up);
+        }
+        assert_eq!(cruise.target(), 32.0);
+        assert_eq!(cruise.ramp(CruiseButton::Down), 31.5);
+    }
+
+    #[test]
+    fn target_stays_within_the_limits() {
+        let mut cruise = CruiseControl::default();
+        let CruiseConfig { min_speed, max_speed, .. } = cruise.config;
+
+        assert_eq!(cruise.engage(CruiseButton::Down, 2.0), min_speed);
+        assert_eq!(cruise.step(CruiseButton::Down), min_speed);
+        assert_eq!(cruise.ramp(CruiseButton::Down), min_speed);
+
+        assert_eq!(cruise.engage(CruiseButton::Down, 75.0), max_speed);
+        assert_eq!(cruise.step(CruiseButton::Up), max_speed);
+        assert_eq!(cruise.ramp(CruiseButton::Up), max_speed);
+        cruise.step(CruiseButton::Down);
+        assert_eq!(cruise.target(), max_speed - cruise.config.step);
+    }
+}
@@ -0,0 +1,180 @@
+//! Display brightness policy
+//!
+//! Picks the brightness preset the OLED should run at:
+//!
+//! - `preset` is the driver's day/night choice from the settings screen
+//! - A PowerSave press latches power save, the next press releases it
+//! - With no button input for `idle_timeout` the display drops to power save
+//!   until the next press, but only while parked: it never dims by itself
+//!   with a drive mode selected
+//!
+//! Running the panel dimmer whenever nobody is looking at it slows burn-in
+//! on long race days. Mapping presets onto panel currents is up to the
+//! display driver.
+
+use embassy_time::{Duration, Instant};
+
+/// Named display brightness levels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub enum BrightnessPreset {
+    /// Full brightness, readable in sunlight
+    #[default]
+    Day,
+    /// Dimmed for driving in the dark
+    Night,
+    /// Barely lit: latched by PowerSave or reached after a parked idle period
+    PowerSave,
+}
+
+impl BrightnessPreset {
+    pub fn label(self) -> &'static str {
+        match self {
+            BrightnessPreset::Day => "DAY",
+            BrightnessPreset::Night => "NIGHT",
+            BrightnessPreset::PowerSave => "SAVE",
+        }
+    }
+}
+
+/// Auto-dim settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DimmingConfig {
+    /// Dim after this long without button input while parked (None to never dim)
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for DimmingConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Some(Duration::from_secs(120)),
+        }
+    }
+}
+
+/// Display brightness state, kept in the shared steering state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dimming {
+    pub config: DimmingConfig,
+    /// Day or night, chosen by the driver
+    pub preset: BrightnessPreset,
+    /// Latched by the PowerSave button
+    power_save: bool,
+    /// Last button input
+    last_input: Instant,
+}
+
+impl Dimming {
+    pub const fn new(config: DimmingConfig) -> Self {
+        Self {
+            config,
+            preset: BrightnessPreset::Day,
+            power_save: false,
+            last_input: Instant::from_ticks(0),
+        }
+    }
+
+    /// Any button input: restarts the idle timer
+    pub fn input(&mut self, now: Instant) {
+        self.last_input = now;
+    }
+
+    /// PowerSave pressed; returns whether power save is now latched
+    pub fn toggle_power_save(&mut self) -> bool {
+        self.power_save = !self.power_save;
+        self.power_save
+    }
+
+    pub fn power_save(&self) -> bool {
+        self.power_save
+    }
+
+    /// Time since the last button input
+    pub fn idle_for(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_input)
+    }
+
+    /// True once the parked idle timeout has run out
+    pub fn idle(&self, now: Instant, parked: bool) -> bool {
+        parked
+            && self
+                .config
+                .idle_timeout
+                .is_some_and(|timeout| self.idle_for(now) >= timeout)
+    }
+
+    /// Preset the display should run at `now`
+    ///
+    /// `parked` is true in neutral; the idle dim only applies then.
+    pub fn level(&self, now: Instant, parked: bool) -> BrightnessPreset {
+        if self.power_save || self.idle(now, parked) {
+            BrightnessPreset::PowerSave
+        } else {
+            self.preset
+        }
+    }
+}
+
+impl Default for Dimming {
+    fn default() -> Self {
+        Self::new(DimmingConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> Instant {
+        Instant::from_secs(secs)
+    }
+
+    #[test]
+    fn idle_dims_only_while_parked() {
+        let mut dimming = Dimming::default();
+        dimming.input(at(10));
+
+        assert_eq!(dimming.level(at(129), true), BrightnessPreset::Day);
+        assert_eq!(dimming.level(at(130), true), BrightnessPreset::PowerSave);
+        assert!(dimming.idle(at(130), true));
+
+        // Never dims by itself with a drive mode selected
+        assert_eq!(dimming.level(at(1000), false), BrightnessPreset::Day);
+        assert!(!dimming.idle(at(1000), false));
+    }
+
+    #[test]
+    fn input_restarts_the_idle_timer() {
+        let mut dimming = Dimming {
+            preset: BrightnessPreset::Night,
+            ..Dimming::default()
+        };
+        assert_eq!(dimming.level(at(200), true), BrightnessPreset::PowerSave);
+
+        dimming.input(at(200));
+        assert_eq!(dimming.idle_for(at(250)), Duration::from_secs(50));
+        assert_eq!(dimming.level(at(250), true), BrightnessPreset::Night);
+    }
+
+    #[test]
+    fn idle_dim_can_be_turned_off() {
+        let dimming = Dimming::new(DimmingConfig { idle_timeout: None });
+        assert_eq!(dimming.level(at(100_000), true), BrightnessPreset::Day);
+    }
+
+    #[test]
+    fn power_save_toggle_latches() {
+        let mut dimming = Dimming::default();
+        dimming.input(at(0));
+
+        assert!(dimming.toggle_power_save());
+        assert!(dimming.power_save());
+        // Latched whatever the drive mode or recent input
+        dimming.input(at(5));
+        assert_eq!(dimming.level(at(5), false), BrightnessPreset::PowerSave);
+        assert_eq!(dimming.level(at(5), true), BrightnessPreset::PowerSave);
+
+        assert!(!dimming.toggle_power_save());
+        assert!(!dimming.power_save());
+        assert_eq!(dimming.level(at(5), false), BrightnessPreset::Day);
+    }
+}
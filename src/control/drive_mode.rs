@@ -0,0 +1,385 @@
+//! Drive mode state machine (D/R/C/N)
+//!
+//! Owns every drive mode transition and its interlocks:
+//!
+//! - Neutral can always be selected
+//! - Reverse only from neutral, at a standstill, with the brake pressed
+//! - Drive from neutral with the brake pressed and the car not rolling
+//!   backwards, or from cruise
+//! - Cruise only from drive, above a minimum speed, with the brake released
+//!
+//! On top of the driver's requests, `update` applies the forced transitions:
+//! the failsafe forces neutral, and a brake press or regen cancels cruise
+//! back to drive. With regen enabled on the VC the first part of the
+//! brake/regen pedal's travel is regen, so a much lighter press counts.
+//! Cancelling looks at the pedal as read, so a brake pedal fault (which
+//! zeroes the reported brake) cannot keep cruise engaged.
+//!
+//! The resulting mode is sent to the VC in SW_State and shown on the display.
+
+/// Drive states matching the C enum (and the network numbering)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+#[repr(u8)]
+pub enum DriveState {
+    Drive = 0,
+    Reverse = 1,
+    Cruise = 2,
+    #[default]
+    Neutral = 3,
+}
+
+impl DriveState {
+    /// Convert the drive mode number used on the network, defaulting to neutral
+    pub fn from_raw(value: u32) -> Self {
+        match value {
+            0 => DriveState::Drive,
+            1 => DriveState::Reverse,
+            2 => DriveState::Cruise,
+            _ => DriveState::Neutral,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DriveState::Drive => "D",
+            DriveState::Reverse => "R",
+            DriveState::Cruise => "C",
+            DriveState::Neutral => "N",
+        }
+    }
+}
+
+/// Why a drive mode request was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum DriveRejection {
+    /// The failsafe is holding neutral
+    Failsafe,
+    /// Only allowed from neutral
+    NotInNeutral,
+    /// Only allowed from drive
+    NotInDrive,
+    /// Car must be at a standstill
+    NotStopped,
+    /// Car is rolling backwards
+    RollingBack,
+    /// Below the minimum cruise speed
+    TooSlow,
+    /// Brake must be pressed to shift out of neutral
+    BrakeNotPressed,
+    /// Brake must be released and regen off to engage cruise
+    BrakePressed,
+}
+
+/// Thresholds for the interlocks
+///
+/// Speeds are in the units the VC reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriveModeConfig {
+    /// Speed below which the car counts as stopped
+    pub stopped_speed: f32,
+    /// Lowest speed cruise can be engaged at
+    pub cruise_min_speed: f32,
+    /// Brake position counted as pressed
+    pub brake_pressed: f32,
+    /// Brake/regen pedal position counted as a regen request, with regen enabled
+    pub regen_pressed: f32,
+    /// Require the brake to shift out of neutral
+    pub shift_needs_brake: bool,
+}
+
+impl Default for DriveModeConfig {
+    fn default() -> Self {
+        Self {
+            stopped_speed: 0.5,
+            cruise_min_speed: 5.0,
+            brake_pressed: 0.1,
+            regen_pressed: 0.02,
+            shift_needs_brake: true,
+        }
+    }
+}
+
+/// What the interlocks look at
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DriveInputs {
+    /// Vehicle speed reported by the VC, negative when rolling backwards
+    pub speed: f32,
+    /// Normalized brake position (0.0 - 1.0), zeroed by pedal faults
+    pub brake: f32,
+    /// Normalized brake position as read, before pedal faults zero it
+    pub brake_input: f32,
+    /// VC has regen enabled on the brake/regen pedal
+    pub regen: bool,
+    /// Failsafe is holding neutral
+    pub force_neutral: bool,
+}
+
+/// Owns the current drive mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriveModeController {
+    pub config: DriveModeConfig,
+    mode: DriveState,
+}
+
+impl DriveModeController {
+    pub const fn new(config: DriveModeConfig) -> Self {
+        Self {
+            config,
+            mode: DriveState::Neutral,
+        }
+    }
+
+    pub fn mode(&self) -> DriveState {
+        self.mode
+    }
+
+    /// Driver asks for a new mode; returns the mode now in effect
+    pub fn request(&mut self, request: DriveState, inputs: DriveInputs) -> Result<DriveState, DriveRejection> {
+        if request == self.mode {
+            return Ok(self.mode);
+        }
+        self.check(request, inputs)?;
+        self.mode = request;
+        Ok(self.mode)
+    }
+
+    /// Interlocks for moving from the current mode to `request`
+    fn check(&self, request: DriveState, inputs: DriveInputs) -> Result<(), DriveRejection> {
+        let config = &self.config;
+        let braking = inputs.brake >= config.brake_pressed;
+        let shift_brake = || {
+            if config.shift_needs_brake && !braking {
+                Err(DriveRejection::BrakeNotPressed)
+            } else {
+                Ok(())
+            }
+        };
+
+        if request == DriveState::Neutral {
+            return Ok(());
+        }
+        if inputs.force_neutral {
+            return Err(DriveRejection::Failsafe);
+        }
+
+        match (self.mode, request) {
+            (DriveState::Neutral, DriveState::Reverse) => {
+                if inputs.speed.abs() > config.stopped_speed {
+                    return Err(DriveRejection::NotStopped);
+                }
+                shift_brake()
+            }
+            (_, DriveState::Reverse) => Err(DriveRejection::NotInNeutral),
+            (DriveState::Neutral, DriveState::Drive) => {
+                if inputs.speed < -config.stopped_speed {
+                    return Err(DriveRejection::RollingBack);
+                }
+                shift_brake()
+            }
+            (DriveState::Cruise, DriveState::Drive) => Ok(()),
+            (_, DriveState::Drive) => Err(DriveRejection::NotInNeutral),
+            (DriveState::Drive, DriveState::Cruise) => {
+                if inputs.speed < config.cruise_min_speed {
+                    Err(DriveRejection::TooSlow)
+                } else if self.cancels_cruise(inputs) {
+                    Err(DriveRejection::BrakePressed)
+                } else {
+                    Ok(())
+                }
+            }
+            (_, DriveState::Cruise) => Err(DriveRejection::NotInDrive),
+            (_, DriveState::Neutral) => Ok(()),
+        }
+    }
+
+    /// Apply the forced transitions; returns the new mode if it changed
+    pub fn update(&mut self, inputs: DriveInputs) -> Option<DriveState> {
+        let forced = if inputs.force_neutral {
+            DriveState::Neutral
+        } else if self.mode == DriveState::Cruise && self.cancels_cruise(inputs) {
+            DriveState::Drive
+        } else {
+            self.mode
+        };
+
+        (forced != self.mode).then(|| {
+            self.mode = forced;
+            forced
+        })
+    }
+
+    /// Brake pressed, or the pedal asking for regen
+    fn cancels_cruise(&self, inputs: DriveInputs) -> bool {
+        let threshold = if inputs.regen {
+            self.config.regen_pressed
+        } else {
+            self.config.brake_pressed
+        };
+        inputs.brake_input >= threshold
+    }
+}
+
+impl Default for DriveModeController {
+    fn default() -> Self {
+        Self::new(DriveModeConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [DriveState; 4] = [
+        DriveState::Drive,
+        DriveState::Reverse,
+        DriveState::Cruise,
+        DriveState::Neutral,
+    ];
+
+    fn in_mode(mode: DriveState) -> DriveModeController {
+        DriveModeController {
+            mode,
+            ..DriveModeController::default()
+        }
+    }
+
+    /// Inputs every interlock on the way into `request` is happy with
+    fn ready_for(request: DriveState) -> DriveInputs {
+        match request {
+            DriveState::Cruise => DriveInputs { speed: 10.0, ..DriveInputs::default() },
+            _ => DriveInputs { brake: 1.0, brake_input: 1.0, ..DriveInputs::default() },
+        }
+    }
+
+    #[test]
+    fn every_transition() {
+        use DriveRejection::*;
+        use DriveState::*;
+
+        let expected = |from, to| match (from, to) {
+            (Neutral, Cruise) | (Reverse, Cruise) => Err(NotInDrive),
+            (Reverse, Drive) => Err(NotInNeutral),
+            (Drive, Reverse) | (Cruise, Reverse) => Err(NotInNeutral),
+            _ => Ok(to),
+        };
+
+        for from in MODES {
+            for to in MODES {
+                let mut controller = in_mode(from);
+                let result = controller.request(to, ready_for(to));
+                assert_eq!(result, expected(from, to), "{:?} -> {:?}", from, to);
+                let now = if result.is_ok() { to } else { from };
+                assert_eq!(controller.mode(), now, "{:?} -> {:?}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn every_rejection() {
+        use DriveRejection::*;
+        use DriveState::*;
+
+        // (from, to, speed, brake, force_neutral, rejection)
+        let cases = [
+            (Neutral, Drive, 0.0, 1.0, true, Failsafe),
+            (Drive, Reverse, 0.0, 1.0, false, NotInNeutral),
+            (Neutral, Cruise, 10.0, 0.0, false, NotInDrive),
+            (Neutral, Reverse, 3.0, 1.0, false, NotStopped),
+            (Neutral, Reverse, -3.0, 1.0, false, NotStopped),
+            (Neutral, Drive, -3.0, 1.0, false, RollingBack),
+            (Drive, Cruise, 2.0, 0.0, false, TooSlow),
+            (Neutral, Drive, 0.0, 0.0, false, BrakeNotPressed),
+            (Neutral, Reverse, 0.0, 0.0, false, BrakeNotPressed),
+            (Drive, Cruise, 10.0, 0.5, false, BrakePressed),
+        ];
+
+        for (from, to, speed, brake, force_neutral, rejection) in cases {
+            let mut controller = in_mode(from);
+            let inputs = DriveInputs {
+                speed,
+                brake,
+                brake_input: brake,
+                regen: false,
+                force_neutral,
+            };
+            assert_eq!(controller.request(to, inputs), Err(rejection));
+            assert_eq!(controller.mode(), from);
+        }
+    }
+
+    #[test]
+    fn neutral_is_always_allowed() {
+        for from in MODES {
+            let inputs = DriveInputs { speed: 20.0, force_neutral: true, ..DriveInputs::default() };
+            assert_eq!(in_mode(from).request(DriveState::Neutral, inputs), Ok(DriveState::Neutral));
+        }
+    }
+
+    #[test]
+    fn shifting_without_the_brake_can_be_allowed() {
+        let mut controller = DriveModeController::new(DriveModeConfig {
+            shift_needs_brake: false,
+            ..DriveModeConfig::default()
+        });
+        let inputs = DriveInputs { brake: 0.0, brake_input: 0.0, ..ready_for(DriveState::Drive) };
+        assert_eq!(controller.request(DriveState::Drive, inputs), Ok(DriveState::Drive));
+    }
+
+    #[test]
+    fn update_forces_neutral_from_every_mode() {
+        let inputs = DriveInputs { force_neutral: true, ..DriveInputs::default() };
+        for from in MODES {
+            let mut controller = in_mode(from);
+            let expected = (from != DriveState::Neutral).then_some(DriveState::Neutral);
+            assert_eq!(controller.update(inputs), expected);
+            assert_eq!(controller.mode(), DriveState::Neutral);
+        }
+    }
+
+    #[test]
+    fn update_cancels_cruise_on_the_brake() {
+        let mut controller = in_mode(DriveState::Cruise);
+        assert_eq!(controller.update(ready_for(DriveState::Cruise)), None);
+        assert_eq!(controller.mode(), DriveState::Cruise);
+
+        let braking = DriveInputs { brake: 0.2, brake_input: 0.2, ..ready_for(DriveState::Cruise) };
+        assert_eq!(controller.update(braking), Some(DriveState::Drive));
+        assert_eq!(controller.mode(), DriveState::Drive);
+
+        // Braking in drive or reverse changes nothing
+        assert_eq!(controller.update(braking), None);
+        let mut controller = in_mode(DriveState::Reverse);
+        assert_eq!(controller.update(braking), None);
+    }
+
+    #[test]
+    fn light_press_cancels_cruise_only_with_regen() {
+        let light = DriveInputs { brake: 0.05, brake_input: 0.05, ..ready_for(DriveState::Cruise) };
+        let mut controller = in_mode(DriveState::Cruise);
+        assert_eq!(controller.update(light), None);
+
+        let regen = DriveInputs { regen: true, ..light };
+        assert_eq!(controller.update(regen), Some(DriveState::Drive));
+        assert_eq!(controller.request(DriveState::Cruise, regen), Err(DriveRejection::BrakePressed));
+
+        // Regen enabled with the pedal released keeps cruise
+        let released = DriveInputs { regen: true, ..ready_for(DriveState::Cruise) };
+        assert_eq!(controller.request(DriveState::Cruise, released), Ok(DriveState::Cruise));
+        assert_eq!(controller.update(released), None);
+    }
+
+    #[test]
+    fn faulty_brake_still_cancels_cruise() {
+        // A brake pedal fault zeroes the brake the interlocks see
+        let faulted = DriveInputs { brake: 0.0, brake_input: 0.8, ..ready_for(DriveState::Cruise) };
+        let mut controller = in_mode(DriveState::Cruise);
+        assert_eq!(controller.update(faulted), Some(DriveState::Drive));
+
+        // ...but never counts as pressed for shifting out of neutral
+        let stopped = DriveInputs { speed: 0.0, ..faulted };
+        assert_eq!(
+            in_mode(DriveState::Neutral).request(DriveState::Drive, stopped),
+            Err(DriveRejection::BrakeNotPressed)
+        );
+    }
+}
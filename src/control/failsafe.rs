@@ -0,0 +1,316 @@
+//! Communication-loss failsafe
+//!
+//! Watches how long ago the VC and BMS were last heard from:
+//!
+//! - VC lost: the throttle request is forced to zero, cruise is held off,
+//!   the drive mode is forced to neutral and a DTC is latched
+//! - BMS lost: an alert is raised for the driver and a DTC is latched
+//! - VC back: commands stay inhibited until the driver re-arms by releasing
+//!   the throttle, so the car never lurches when the link returns
+//!
+//! Each action can be turned off in `FailsafeConfig`. The wheel boots in
+//! `VcLost` and has to be re-armed after the VC is first heard from.
+
+use embassy_time::Duration;
+
+/// Timeouts and actions for the failsafe
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FailsafeConfig {
+    /// VC silence after which the link counts as lost
+    pub vc_timeout: Duration,
+    /// BMS silence after which the link counts as lost
+    pub bms_timeout: Duration,
+    /// Zero the throttle request while the VC is lost or not re-armed
+    pub zero_throttle: bool,
+    /// Hold cruise off while the VC is lost or not re-armed
+    pub cancel_cruise: bool,
+    /// Force neutral while the VC is lost or not re-armed
+    pub force_neutral: bool,
+    /// Raise the driver alert while the BMS is lost
+    pub bms_alert: bool,
+    /// Throttle must be at or below this to re-arm
+    pub rearm_throttle: f32,
+}
+
+impl Default for FailsafeConfig {
+    fn default() -> Self {
+        Self {
+            // Same thresholds as the dead-link indicators on the display
+            vc_timeout: Duration::from_millis(300),
+            bms_timeout: Duration::from_millis(1000),
+            zero_throttle: true,
+            cancel_cruise: true,
+            force_neutral: true,
+            bms_alert: true,
+            rearm_throttle: 0.05,
+        }
+    }
+}
+
+/// Where the failsafe is with the VC link
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub enum FailsafeState {
+    /// VC not heard from within the timeout (also the state at boot)
+    #[default]
+    VcLost,
+    /// VC is back, waiting for the driver to release the throttle
+    AwaitingRearm,
+    /// Normal operation
+    Armed,
+}
+
+/// Latched diagnostic trouble codes (reported as-is in SW_State)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub struct FailsafeDtc(u8);
+
+impl FailsafeDtc {
+    pub const NONE: Self = Self(0);
+    /// VC link was lost after being up
+    pub const VC_LOSS: Self = Self(1 << 0);
+    /// BMS link was lost after being up
+    pub const BMS_LOSS: Self = Self(1 << 1);
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOrAssign for FailsafeDtc {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// What the failsafe currently allows, kept in the shared steering state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct FailsafeStatus {
+    pub state: FailsafeState,
+    /// BMS not heard from within the timeout
+    pub bms_lost: bool,
+    /// Throttle request must be zero
+    pub inhibit_throttle: bool,
+    /// Cruise must stay off
+    pub inhibit_cruise: bool,
+    /// Drive mode must be neutral
+    pub force_neutral: bool,
+    /// Show the BMS alert
+    pub bms_alert: bool,
+    /// DTCs latched since boot
+    pub dtcs: FailsafeDtc,
+}
+
+impl FailsafeStatus {
+    /// Apply the throttle inhibit to a normalized throttle request
+    pub fn limit_throttle(&self, throttle: f32) -> f32 {
+        if self.inhibit_throttle {
+            0.0
+        } else {
+            throttle
+        }
+    }
+}
+
+impl Default for FailsafeStatus {
+    /// Status before the first update: nothing heard, everything inhibited
+    fn default() -> Self {
+        Failsafe::new(FailsafeConfig::default()).status()
+    }
+}
+
+/// Communication-loss state machine
+#[derive(Debug, Clone, Copy)]
+pub struct Failsafe {
+    pub config: FailsafeConfig,
+    state: FailsafeState,
+    bms_lost: bool,
+    dtcs: FailsafeDtc,
+}
+
+impl Failsafe {
+    pub const fn new(config: FailsafeConfig) -> Self {
+        Self {
+            config,
+            state: FailsafeState::VcLost,
+            bms_lost: true,
+            dtcs: FailsafeDtc::NONE,
+        }
+    }
+
+    pub fn state(&self) -> FailsafeState {
+        self.state
+    }
+
+    /// Advance the state machine
+    ///
+    /// `since_vc` and `since_bms` are the times since the last message from
+    /// each node; `throttle` is the driver's normalized throttle position.
+    pub fn update(&mut self, since_vc: Duration, since_bms: Duration, throttle: f32) -> FailsafeStatus {
+        let vc_lost = since_vc > self.config.vc_timeout;
+        let bms_lost = since_bms > self.config.bms_timeout;
+
+        self.state = match self.state {
+            _ if vc_lost => {
+                if self.state != FailsafeState::VcLost {
+                    self.dtcs |= FailsafeDtc::VC_LOSS;
+                }
+                FailsafeState::VcLost
+            }
+            FailsafeState::VcLost | FailsafeState::AwaitingRearm => {
+                if throttle <= self.config.rearm_throttle {
+                    FailsafeState::Armed
+                } else {
+                    FailsafeState::AwaitingRearm
+                }
+            }
+            FailsafeState::Armed => FailsafeState::Armed,
+        };
+
+        if bms_lost && !self.bms_lost {
+            self.dtcs |= FailsafeDtc::BMS_LOSS;
+        }
+        self.bms_lost = bms_lost;
+
+        self.status()
+    }
+
+    /// Current status without advancing
+    pub fn status(&self) -> FailsafeStatus {
+        let inhibited = self.state != FailsafeState::Armed;
+        FailsafeStatus {
+            state: self.state,
+            bms_lost: self.bms_lost,
+            inhibit_throttle: inhibited && self.config.zero_throttle,
+            inhibit_cruise: inhibited && self.config.cancel_cruise,
+            force_neutral: inhibited && self.config.force_neutral,
+            bms_alert: self.bms_lost && self.config.bms_alert,
+            dtcs: self.dtcs,
+        }
+    }
+}
+
+impl Default for Failsafe {
+    fn default() -> Self {
+        Self::new(FailsafeConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEARD: Duration = Duration::from_millis(20);
+    const SILENT: Duration = Duration::from_millis(2000);
+
+    /// Armed with both links up
+    fn armed() -> Failsafe {
+        let mut failsafe = Failsafe::default();
+        assert_eq!(failsafe.update(HEARD, HEARD, 0.0).state, FailsafeState::Armed);
+        failsafe
+    }
+
+    #[test]
+    fn boots_lost_with_everything_inhibited() {
+        let status = Failsafe::default().status();
+        assert_eq!(status, FailsafeStatus::default());
+        assert_eq!(status.state, FailsafeState::VcLost);
+        assert!(status.inhibit_throttle && status.inhibit_cruise && status.force_neutral);
+        assert!(status.bms_lost && status.bms_alert);
+        assert!(status.dtcs.is_empty());
+        assert_eq!(status.limit_throttle(0.7), 0.0);
+    }
+
+    #[test]
+    fn first_contact_arms_without_a_dtc() {
+        let status = armed().status();
+        assert!(!status.inhibit_throttle && !status.inhibit_cruise && !status.force_neutral);
+        assert!(!status.bms_lost && !status.bms_alert);
+        assert!(status.dtcs.is_empty());
+        assert_eq!(status.limit_throttle(0.7), 0.7);
+    }
+
+    #[test]
+    fn vc_loss_inhibits_and_latches_a_dtc() {
+        let mut failsafe = armed();
+
+        // Silence up to the timeout is tolerated
+        let timeout = failsafe.config.vc_timeout;
+        assert_eq!(failsafe.update(timeout, HEARD, 0.5).state, FailsafeState::Armed);
+
+        let status = failsafe.update(timeout + HEARD, HEARD, 0.5);
+        assert_eq!(status.state, FailsafeState::VcLost);
+        assert!(status.inhibit_throttle && status.inhibit_cruise && status.force_neutral);
+        assert!(status.dtcs.contains(FailsafeDtc::VC_LOSS));
+        assert!(!status.dtcs.contains(FailsafeDtc::BMS_LOSS));
+    }
+
+    #[test]
+    fn rearm_waits_for_the_throttle_to_be_released() {
+        let mut failsafe = armed();
+        failsafe.update(SILENT, HEARD, 0.8);
+
+        // VC back with the pedal still down: stays inhibited
+        for _ in 0..10 {
+            let status = failsafe.update(HEARD, HEARD, 0.8);
+            assert_eq!(status.state, FailsafeState::AwaitingRearm);
+            assert_eq!(status.limit_throttle(0.8), 0.0);
+        }
+
+        let rearm = failsafe.config.rearm_throttle;
+        let status = failsafe.update(HEARD, HEARD, rearm);
+        assert_eq!(status.state, FailsafeState::Armed);
+        // The DTC outlives the loss
+        assert!(status.dtcs.contains(FailsafeDtc::VC_LOSS));
+
+        // Pressing again once armed is fine
+        assert_eq!(failsafe.update(HEARD, HEARD, 1.0).state, FailsafeState::Armed);
+    }
+
+    #[test]
+    fn losing_the_vc_while_awaiting_rearm_goes_back_to_lost() {
+        let mut failsafe = armed();
+        failsafe.update(SILENT, HEARD, 0.8);
+        assert_eq!(failsafe.update(HEARD, HEARD, 0.8).state, FailsafeState::AwaitingRearm);
+        assert_eq!(failsafe.update(SILENT, HEARD, 0.0).state, FailsafeState::VcLost);
+        assert_eq!(failsafe.update(HEARD, HEARD, 0.0).state, FailsafeState::Armed);
+    }
+
+    #[test]
+    fn bms_loss_alerts_without_inhibiting() {
+        let mut failsafe = armed();
+
+        let status = failsafe.update(HEARD, SILENT, 0.5);
+        assert_eq!(status.state, FailsafeState::Armed);
+        assert!(status.bms_lost && status.bms_alert);
+        assert!(!status.inhibit_throttle);
+        assert!(status.dtcs.contains(FailsafeDtc::BMS_LOSS));
+
+        let status = failsafe.update(HEARD, HEARD, 0.5);
+        assert!(!status.bms_lost && !status.bms_alert);
+        assert!(status.dtcs.contains(FailsafeDtc::BMS_LOSS));
+    }
+
+    #[test]
+    fn disabled_actions_are_not_applied() {
+        let mut failsafe = Failsafe::new(FailsafeConfig {
+            zero_throttle: false,
+            cancel_cruise: false,
+            force_neutral: false,
+            bms_alert: false,
+            ..FailsafeConfig::default()
+        });
+
+        let status = failsafe.update(SILENT, SILENT, 0.5);
+        assert_eq!(status.state, FailsafeState::VcLost);
+        assert!(!status.inhibit_throttle && !status.inhibit_cruise && !status.force_neutral);
+        assert!(status.bms_lost && !status.bms_alert);
+        assert_eq!(status.limit_throttle(0.5), 0.5);
+    }
+}
@@ -0,0 +1,33 @@
+//! Vehicle control policy
+//!
+//! Decides what the steering wheel asks of the rest of the car, on top of
+//! the raw button and pedal inputs. Everything here is pure and driven by
+//! tasks, so the rules can be exercised on the host.
+//!
+//! # Module Structure
+//!
+//! - `bms_alert` - BMS flag decoding and the BPS trip alert
+//! - `burn_in` - OLED pixel shift, static dimming and screensaver timing
+//! - `cruise` - Cruise target speed from the cruise buttons
+//! - `dimming` - Display brightness presets, power save and auto-dim
+//! - `drive_mode` - D/R/C/N state machine and its interlocks
+//! - `failsafe` - VC/BMS communication-loss handling and re-arming
+//! - `turn_signals` - Turn signals, hazards, auto-cancel and blink phase
+
+pub mod bms_alert;
+pub mod burn_in;
+pub mod cruise;
+pub mod dimming;
+pub mod drive_mode;
+pub mod failsafe;
+pub mod turn_signals;
+
+pub use bms_alert::{BmsAlert, BmsFlags};
+pub use burn_in::BurnIn;
+pub use cruise::{CruiseButton, CruiseConfig, CruiseControl};
+pub use dimming::{BrightnessPreset, Dimming, DimmingConfig};
+pub use drive_mode::{
+    DriveInputs, DriveModeConfig, DriveModeController, DriveRejection, DriveState,
+};
+pub use failsafe::{Failsafe, FailsafeConfig, FailsafeDtc, FailsafeState, FailsafeStatus};
+pub use turn_signals::{TurnSignal, TurnSignalConfig, TurnSignals};
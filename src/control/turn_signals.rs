@@ -0,0 +1,272 @@
+//! Turn signal controller
+//!
+//! Owns the turn signal state instead of the raw button toggles:
+//!
+//! - Left and right are mutually exclusive; pressing the lit side again
+//!   turns it off, pressing the other side switches over
+//! - Pressing both together toggles the hazards (single presses are ignored
+//!   while the hazards are on)
+//! - Left/right cancel themselves after `timeout`, or once the VC reports the
+//!   steering has turned past `turn_angle` and come back within
+//!   `center_angle`
+//!
+//! All blinking (button LEDs, display arrows) comes from `lamps`, so
+//! everything blinks in step. The blink phase restarts whenever the VC
+//! reports its lamps turning on, keeping the wheel in step with the car's
+//! real lights.
+
+use embassy_time::{Duration, Instant};
+
+/// Turn signal state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub enum TurnSignal {
+    #[default]
+    Off,
+    Left,
+    Right,
+    Hazard,
+}
+
+impl TurnSignal {
+    /// Whether the left and right lamps are requested
+    pub fn sides(self) -> (bool, bool) {
+        match self {
+            TurnSignal::Off => (false, false),
+            TurnSignal::Left => (true, false),
+            TurnSignal::Right => (false, true),
+            TurnSignal::Hazard => (true, true),
+        }
+    }
+}
+
+/// Blink rate and auto-cancel settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TurnSignalConfig {
+    /// Full blink period, lamps on for the first half
+    pub blink_period: Duration,
+    /// Cancel left/right after this long (None to keep them on)
+    pub timeout: Option<Duration>,
+    /// Steering angle (degrees) that counts as a turn
+    pub turn_angle: f32,
+    /// Steering angle (degrees) that counts as back to straight
+    pub center_angle: f32,
+}
+
+impl Default for TurnSignalConfig {
+    fn default() -> Self {
+        Self {
+            blink_period: Duration::from_millis(1000),
+            timeout: Some(Duration::from_secs(30)),
+            turn_angle: 20.0,
+            center_angle: 5.0,
+        }
+    }
+}
+
+/// Turn signal state machine
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TurnSignals {
+    pub config: TurnSignalConfig,
+    signal: TurnSignal,
+    /// When the current signal was turned on (also the blink phase origin)
+    started: Instant,
+    /// Blink phase origin, moved onto the VC's lamps when it reports them
+    epoch: Instant,
+    /// Steering went past `turn_angle` in the signalled direction
+    turned: bool,
+    /// VC lamps on at the last VC update
+    vc_lamps: bool,
+}
+
+impl TurnSignals {
+    pub const fn new(config: TurnSignalConfig) -> Self {
+        Self {
+            config,
+            signal: TurnSignal::Off,
+            started: Instant::from_ticks(0),
+            epoch: Instant::from_ticks(0),
+            turned: false,
+            vc_lamps: false,
+        }
+    }
+
+    /// Signal in effect at `now`, with the timeout applied
+    pub fn signal(&self, now: Instant) -> TurnSignal {
+        let timed_out = self
+            .config
+            .timeout
+            .is_some_and(|timeout| now.saturating_duration_since(self.started) >= timeout);
+        match self.signal {
+            TurnSignal::Left | TurnSignal::Right if timed_out => TurnSignal::Off,
+            signal => signal,
+        }
+    }
+
+    /// Driver pressed a turn button (`Hazard` for both together)
+    ///
+    /// Returns the signal now in effect.
+    pub fn press(&mut self, button: TurnSignal, now: Instant) -> TurnSignal {
+        let current = self.signal(now);
+        let next = match (button, current) {
+            (TurnSignal::Hazard, TurnSignal::Hazard) => TurnSignal::Off,
+            (TurnSignal::Hazard, _) => TurnSignal::Hazard,
+            (_, TurnSignal::Hazard) => TurnSignal::Hazard,
+            (TurnSignal::Off, _) => TurnSignal::Off,
+            (side, current) if side == current => TurnSignal::Off,
+            (side, _) => side,
+        };
+        self.set(next, now);
+        next
+    }
+
+    /// Turn everything off
+    pub fn cancel(&mut self, now: Instant) {
+        self.set(TurnSignal::Off, now);
+    }
+
+    fn set(&mut self, signal: TurnSignal, now: Instant) {
+        if signal != self.signal(now) {
+            self.started = now;
+            self.epoch = now;
+            self.turned = false;
+        }
+        self.signal = signal;
+    }
+
+    /// Feed the VC's lamp state and steering angle (degrees, positive right)
+    ///
+    /// Returns true if this cancelled the signal.
+    pub fn vc_update(&mut self, lamps_on: bool, steering_angle: f32, now: Instant) -> bool {
+        if lamps_on && !self.vc_lamps {
+            self.epoch = now;
+        }
+        self.vc_lamps = lamps_on;
+
+        // Positive angles turn right
+        let angle = match self.signal(now) {
+            TurnSignal::Left => -steering_angle,
+            TurnSignal::Right => steering_angle,
+            _ => return false,
+        };
+        if angle >= self.config.turn_angle {
+            self.turned = true;
+        }
+        if self.turned && angle.abs() <= self.config.center_angle {
+            self.cancel(now);
+            return true;
+        }
+        false
+    }
+
+    /// Left and right lamp states at `now`, blinking
+    pub fn lamps(&self, now: Instant) -> (bool, bool) {
+        let period = self.config.blink_period.as_millis().max(1);
+        let phase = now.saturating_duration_since(self.epoch).as_millis() % period;
+        let (left, right) = self.signal(now).sides();
+        let on = phase < period / 2;
+        (left && on, right && on)
+    }
+}
+
+impl Default for TurnSignals {
+    fn default() -> Self {
+        Self::new(TurnSignalConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64) -> Instant {
+        Instant::from_millis(ms)
+    }
+
+    #[test]
+    fn sides_are_mutually_exclusive() {
+        let mut turn = TurnSignals::default();
+        assert_eq!(turn.press(TurnSignal::Left, at(0)), TurnSignal::Left);
+        assert_eq!(turn.press(TurnSignal::Right, at(100)), TurnSignal::Right);
+        assert_eq!(turn.signal(at(100)).sides(), (false, true));
+        // The lit side again turns it off
+        assert_eq!(turn.press(TurnSignal::Right, at(200)), TurnSignal::Off);
+        assert_eq!(turn.press(TurnSignal::Off, at(300)), TurnSignal::Off);
+    }
+
+    #[test]
+    fn hazards_override_the_sides() {
+        let mut turn = TurnSignals::default();
+        turn.press(TurnSignal::Left, at(0));
+        assert_eq!(turn.press(TurnSignal::Hazard, at(100)), TurnSignal::Hazard);
+        assert_eq!(turn.signal(at(100)).sides(), (true, true));
+
+        // Single presses are ignored while the hazards are on
+        assert_eq!(turn.press(TurnSignal::Left, at(200)), TurnSignal::Hazard);
+        assert_eq!(turn.press(TurnSignal::Right, at(300)), TurnSignal::Hazard);
+
+        assert_eq!(turn.press(TurnSignal::Hazard, at(400)), TurnSignal::Off);
+    }
+
+    #[test]
+    fn sides_time_out_but_hazards_do_not() {
+        let mut turn = TurnSignals::default();
+        let timeout = turn.config.timeout.unwrap().as_millis();
+
+        turn.press(TurnSignal::Left, at(1000));
+        assert_eq!(turn.signal(at(1000 + timeout - 1)), TurnSignal::Left);
+        assert_eq!(turn.signal(at(1000 + timeout)), TurnSignal::Off);
+        // A timed out side lights again on the next press
+        assert_eq!(turn.press(TurnSignal::Left, at(1000 + timeout)), TurnSignal::Left);
+
+        turn.press(TurnSignal::Hazard, at(0));
+        assert_eq!(turn.signal(at(10 * timeout)), TurnSignal::Hazard);
+
+        let mut forever = TurnSignals::new(TurnSignalConfig {
+            timeout: None,
+            ..TurnSignalConfig::default()
+        });
+        forever.press(TurnSignal::Right, at(0));
+        assert_eq!(forever.signal(at(10 * timeout)), TurnSignal::Right);
+    }
+
+    #[test]
+    fn steering_back_to_center_cancels() {
+        let mut turn = TurnSignals::default();
+        turn.press(TurnSignal::Left, at(0));
+
+        // Centered without having turned: stays on
+        assert!(!turn.vc_update(true, 0.0, at(100)));
+        // Turning the other way does not count
+        assert!(!turn.vc_update(true, 30.0, at(200)));
+        assert!(!turn.vc_update(true, 2.0, at(300)));
+        assert_eq!(turn.signal(at(300)), TurnSignal::Left);
+
+        // Left is negative: through the turn, then back
+        assert!(!turn.vc_update(true, -25.0, at(400)));
+        assert!(!turn.vc_update(true, -10.0, at(500)));
+        assert!(turn.vc_update(true, -3.0, at(600)));
+        assert_eq!(turn.signal(at(600)), TurnSignal::Off);
+    }
+
+    #[test]
+    fn steering_does_not_cancel_hazards() {
+        let mut turn = TurnSignals::default();
+        turn.press(TurnSignal::Hazard, at(0));
+        assert!(!turn.vc_update(true, 40.0, at(100)));
+        assert!(!turn.vc_update(true, 0.0, at(200)));
+        assert_eq!(turn.signal(at(200)), TurnSignal::Hazard);
+    }
+
+    #[test]
+    fn lamps_blink_in_step_with_the_vc() {
+        let mut turn = TurnSignals::default();
+        turn.press(TurnSignal::Right, at(0));
+        assert_eq!(turn.lamps(at(100)), (false, true));
+        assert_eq!(turn.lamps(at(600)), (false, false));
+
+        // The VC's lamps coming on restarts the phase
+        turn.vc_update(true, 0.0, at(700));
+        assert_eq!(turn.lamps(at(800)), (false, true));
+        assert_eq!(turn.lamps(at(1300)), (false, false));
+    }
+}
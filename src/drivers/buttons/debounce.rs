@@ -0,0 +1,281 @@
+//! Button debouncing and toggle tracking
+//!
+//! `ButtonState` turns raw levels from any `ButtonSource` into debounced
+//! press, release and toggle events. It never touches the hardware, so the
+//! same logic runs against the GPIO pins and against `ScriptedButtons`.
+
+use embassy_time::Duration;
+
+use super::{ButtonId, ButtonSet, ButtonSource};
+
+/// Consecutive matching samples before a button changes state
+const DEBOUNCE_SAMPLES: u8 = 5;
+
+/// Sample period from an EXTI edge until the buttons settle in interrupt
+/// mode, so a press registers within a few ms of the edge
+pub const SETTLE_PERIOD: Duration = Duration::from_millis(1);
+
+/// Button event types
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ButtonEvent {
+    Pressed(ButtonId),
+    Released(ButtonId),
+    Toggled(ButtonId, bool), // (button, new_state)
+}
+
+/// Button type - regular or toggle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ButtonType {
+    Regular,
+    Toggle,
+}
+
+/// Button state tracker with debouncing
+///
+/// A button must read the same for `DEBOUNCE_SAMPLES` consecutive updates
+/// before its state changes. Works with any `ButtonSource`.
+pub struct ButtonState {
+    // Current debounced states (true = pressed)
+    states: heapless::Vec<bool, 16>,
+    // Raw states for debouncing
+    raw_states: heapless::Vec<bool, 16>,
+    // Debounce counters
+    debounce_counters: heapless::Vec<u8, 16>,
+    // Toggle states for toggle-mode buttons
+    toggle_states: heapless::FnvIndexMap<ButtonId, bool, 16>,
+    // Button IDs in the same order as the state vectors
+    ids: heapless::Vec<ButtonId, 16>,
+}
+
+impl ButtonState {
+    pub fn new(inputs: &impl ButtonSource) -> Self {
+        let mut states = heapless::Vec::new();
+        let mut raw_states = heapless::Vec::new();
+        let mut debounce_counters = heapless::Vec::new();
+        let mut ids = heapless::Vec::new();
+
+        // Initialize vectors with the right number of buttons
+        for (id, _) in inputs.buttons() {
+            let _ = states.push(false);
+            let _ = raw_states.push(false);
+            let _ = debounce_counters.push(0);
+            let _ = ids.push(id);
+        }
+
+        // Initialize toggle states for toggle buttons
+        let mut toggle_states = heapless::FnvIndexMap::new();
+        for (id, button_type) in inputs.buttons() {
+            if button_type == ButtonType::Toggle {
+                let _ = toggle_states.insert(id, false);
+            }
+        }
+
+        Self {
+            states,
+            raw_states,
+            debounce_counters,
+            toggle_states,
+            ids,
+        }
+    }
+
+    /// Update button states with debouncing
+    /// Returns a vector of button events that occurred
+    pub fn update(&mut self, inputs: &impl ButtonSource) -> heapless::Vec<ButtonEvent, 16> {
+        let mut events = heapless::Vec::new();
+
+        // Read and debounce each button
+        for (i, (id, button_type)) in inputs.buttons().enumerate() {
+            // Read current raw state
+            let raw = inputs.is_pressed(id);
+
+            if raw != self.raw_states[i] {
+                // State changed, reset debounce counter
+                self.debounce_counters[i] = 0;
+                self.raw_states[i] = raw;
+            } else if self.debounce_counters[i] < DEBOUNCE_SAMPLES {
+                // Same state, increment counter
+                self.debounce_counters[i] += 1;
+
+                // Check if debounced
+                if self.debounce_counters[i] == DEBOUNCE_SAMPLES && self.states[i] != raw {
+                    // State has been stable long enough, update
+                    self.states[i] = raw;
+
+                    // Generate events based on button type
+                    match button_type {
+                        ButtonType::Toggle => {
+                            if self.states[i] {
+                                // Button pressed, toggle the state
+                                if let Some(toggle_state) = self.toggle_states.get_mut(&id) {
+                                    *toggle_state = !*toggle_state;
+                                    let _ = events.push(ButtonEvent::Toggled(id, *toggle_state));
+                                }
+                            }
+                        }
+                        ButtonType::Regular => {
+                            if self.states[i] {
+                                let _ = events.push(ButtonEvent::Pressed(id));
+                            } else {
+                                let _ = events.push(ButtonEvent::Released(id));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        events
+    }
+
+    /// True when no button is part way through debouncing
+    pub fn is_settled(&self) -> bool {
+        self.debounce_counters.iter().all(|count| *count >= DEBOUNCE_SAMPLES)
+    }
+
+    /// Buttons that are physically held down (debounced), toggles included
+    pub fn pressed(&self) -> ButtonSet {
+        let mut set = ButtonSet::EMPTY;
+        for (id, pressed) in self.ids.iter().zip(self.states.iter()) {
+            if *pressed {
+                set.insert(*id);
+            }
+        }
+        set
+    }
+
+    /// Get the current toggle state of a toggle button
+    pub fn get_toggle_state(&self, id: ButtonId) -> Option<bool> {
+        self.toggle_states.get(&id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::buttons::ScriptedButtons;
+
+    /// Updates from an edge to its event: the edge itself plus the stable samples
+    const SETTLE: usize = DEBOUNCE_SAMPLES as usize + 1;
+
+    fn source() -> ScriptedButtons {
+        ScriptedButtons::new([
+            (ButtonId::Horn, ButtonType::Regular),
+            (ButtonId::Lock, ButtonType::Toggle),
+        ])
+    }
+
+    /// Run `count` updates and collect their events
+    fn step(
+        state: &mut ButtonState,
+        inputs: &ScriptedButtons,
+        count: usize,
+    ) -> heapless::Vec<ButtonEvent, 16> {
+        let mut events = heapless::Vec::new();
+        for _ in 0..count {
+            for event in state.update(inputs) {
+                events.push(event).unwrap();
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn press_needs_five_stable_samples() {
+        let mut inputs = source();
+        let mut state = ButtonState::new(&inputs);
+        step(&mut state, &inputs, SETTLE);
+        assert!(state.is_settled());
+
+        inputs.set(ButtonId::Horn, true);
+        assert!(step(&mut state, &inputs, SETTLE - 1).is_empty());
+        assert!(!state.is_settled());
+        assert!(state.pressed().is_empty());
+        assert_eq!(step(&mut state, &inputs, 1), [ButtonEvent::Pressed(ButtonId::Horn)]);
+        assert!(state.is_settled());
+        assert_eq!(state.pressed(), ButtonSet::of(&[ButtonId::Horn]));
+
+        inputs.set(ButtonId::Horn, false);
+        assert!(step(&mut state, &inputs, SETTLE - 1).is_empty());
+        assert_eq!(step(&mut state, &inputs, 1), [ButtonEvent::Released(ButtonId::Horn)]);
+        assert!(state.pressed().is_empty());
+    }
+
+    #[test]
+    fn interrupt_mode_reports_a_press_within_10ms() {
+        let mut inputs = source();
+        let mut state = ButtonState::new(&inputs);
+        step(&mut state, &inputs, SETTLE);
+
+        // The task samples on the EXTI edge, then every SETTLE_PERIOD
+        inputs.set(ButtonId::Horn, true);
+        let mut latency = Duration::from_ticks(0);
+        while state.update(&inputs).is_empty() {
+            latency += SETTLE_PERIOD;
+            assert!(latency < Duration::from_millis(10), "press not reported after {} ms", latency.as_millis());
+        }
+        assert_eq!(state.pressed(), ButtonSet::of(&[ButtonId::Horn]));
+    }
+
+    #[test]
+    fn bounces_restart_the_count() {
+        let mut inputs = source();
+        let mut state = ButtonState::new(&inputs);
+
+        // Contact bounce shorter than the debounce never reports
+        for _ in 0..4 {
+            inputs.set(ButtonId::Horn, true);
+            assert!(step(&mut state, &inputs, 3).is_empty());
+            inputs.set(ButtonId::Horn, false);
+            assert!(step(&mut state, &inputs, 2).is_empty());
+        }
+
+        inputs.set(ButtonId::Horn, true);
+        assert!(step(&mut state, &inputs, SETTLE - 1).is_empty());
+        assert_eq!(step(&mut state, &inputs, 1), [ButtonEvent::Pressed(ButtonId::Horn)]);
+    }
+
+    #[test]
+    fn toggle_flips_on_each_press() {
+        let mut inputs = source();
+        let mut state = ButtonState::new(&inputs);
+        assert_eq!(state.get_toggle_state(ButtonId::Lock), Some(false));
+        assert_eq!(state.get_toggle_state(ButtonId::Horn), None);
+
+        let mut expected = false;
+        for _ in 0..3 {
+            inputs.set(ButtonId::Lock, true);
+            expected = !expected;
+            assert_eq!(
+                step(&mut state, &inputs, SETTLE),
+                [ButtonEvent::Toggled(ButtonId::Lock, expected)]
+            );
+            assert!(state.pressed().contains(ButtonId::Lock));
+
+            // Releasing a toggle reports nothing and keeps its state
+            inputs.set(ButtonId::Lock, false);
+            assert!(step(&mut state, &inputs, SETTLE).is_empty());
+            assert_eq!(state.get_toggle_state(ButtonId::Lock), Some(expected));
+        }
+    }
+
+    #[test]
+    fn sixteen_buttons_fit() {
+        // Only ten buttons exist, so repeat them to fill every slot
+        let mut inputs = ScriptedButtons::new(
+            ButtonId::ALL
+                .iter()
+                .cycle()
+                .take(20)
+                .map(|id| (*id, ButtonType::Regular)),
+        );
+        assert_eq!(inputs.buttons().count(), 16);
+
+        let mut state = ButtonState::new(&inputs);
+        inputs.set_pressed(ButtonSet::of(&ButtonId::ALL));
+        let events = step(&mut state, &inputs, SETTLE);
+        assert_eq!(events.len(), 16);
+        assert!(events.iter().all(|event| matches!(event, ButtonEvent::Pressed(_))));
+        assert_eq!(state.pressed().len(), ButtonId::ALL.len() as u32);
+    }
+}
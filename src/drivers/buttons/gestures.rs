@@ -0,0 +1,282 @@
+//! Gesture detection on top of the debouncer
+//!
+//! Turns the debounced set of held buttons into higher level events:
+//!
+//! - `ShortPress` - a button released before `long_press`
+//! - `LongPress` - a button held past `long_press`
+//! - `LongRelease` - a long press released
+//! - `Held` - repeated every `held_interval` while a long press continues
+//! - `DoublePress` - a second press within `double_press` of a short press
+//!   (each half still reports its own `ShortPress`)
+//! - `Chord` - two or more buttons down together
+//!
+//! Buttons that take part in a chord are excluded from the other gestures
+//! until they are released, so holding a chord never also long-presses its
+//! members.
+//!
+//! `GestureDetector::update` is pure, so timelines can be scripted on the host.
+
+use embassy_time::{Duration, Instant};
+
+use super::{ButtonId, ButtonSet};
+
+/// Gesture timing thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GestureConfig {
+    /// Hold time before `LongPress` fires
+    pub long_press: Duration,
+    /// Maximum gap between a short press's release and the next press
+    pub double_press: Duration,
+    /// Interval between `Held` reports during a long press
+    pub held_interval: Duration,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            long_press: Duration::from_millis(800),
+            double_press: Duration::from_millis(300),
+            held_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Higher level button events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GestureEvent {
+    /// Solo press released before `long_press`, reported on release
+    ShortPress(ButtonId),
+    LongPress(ButtonId),
+    /// Solo press released after `long_press`, reported on release
+    LongRelease(ButtonId),
+    DoublePress(ButtonId),
+    /// Button still held, with the total time since it was pressed
+    Held(ButtonId, Duration),
+    /// Set of buttons currently held together
+    Chord(ButtonSet),
+}
+
+/// Per-button gesture tracking
+#[derive(Debug, Clone, Copy, Default)]
+struct Tracker {
+    pressed_at: Option<Instant>,
+    next_held: Option<Instant>,
+    short_release_at: Option<Instant>,
+    double_pressed: bool,
+}
+
+/// Detects gestures from successive debounced button sets
+pub struct GestureDetector {
+    pub config: GestureConfig,
+    trackers: [Tracker; ButtonId::ALL.len()],
+    previous: ButtonSet,
+    chorded: ButtonSet,
+}
+
+impl GestureDetector {
+    pub fn new(config: GestureConfig) -> Self {
+        Self {
+            config,
+            trackers: [Tracker::default(); ButtonId::ALL.len()],
+            previous: ButtonSet::EMPTY,
+            chorded: ButtonSet::EMPTY,
+        }
+    }
+
+    /// Feed the currently held buttons and return any gestures
+    pub fn update(&mut self, pressed: ButtonSet, now: Instant) -> heapless::Vec<GestureEvent, 16> {
+        let mut events = heapless::Vec::new();
+
+        // A chord forms (or grows) whenever a button joins 1+ others already held
+        let grew = ButtonId::ALL
+            .iter()
+            .any(|id| pressed.contains(*id) && !self.previous.contains(*id));
+        if grew && pressed.len() >= 2 {
+            let _ = events.push(GestureEvent::Chord(pressed));
+            self.chorded = pressed;
+        }
+
+        for id in ButtonId::ALL {
+            let is_down = pressed.contains(id);
+            let was_down = self.previous.contains(id);
+            let in_chord = self.chorded.contains(id);
+            let tracker = &mut self.trackers[id as usize];
+
+            if is_down && !was_down {
+                let double = tracker
+                    .short_release_at
+                    .take()
+                    .is_some_and(|released| now - released <= self.config.double_press);
+                tracker.double_pressed = double && !in_chord;
+                if tracker.double_pressed {
+                    let _ = events.push(GestureEvent::DoublePress(id));
+                }
+                tracker.pressed_at = Some(now);
+                tracker.next_held = Some(now + self.config.long_press);
+            } else if is_down {
+                if let (Some(pressed_at), Some(next_held)) = (tracker.pressed_at, tracker.next_held) {
+                    if now >= next_held && !in_chord {
+                        if next_held == pressed_at + self.config.long_press {
+                            let _ = events.push(GestureEvent::LongPress(id));
+                        }
+                        let _ = events.push(GestureEvent::Held(id, now - pressed_at));
+                        tracker.next_held = Some(next_held + self.config.held_interval);
+                    }
+                }
+            } else if was_down {
+                // Only a short, solo press can be the first half of a double press
+                let long = tracker
+                    .pressed_at
+                    .is_some_and(|pressed_at| now - pressed_at >= self.config.long_press);
+                if !in_chord {
+                    let release = if long {
+                        GestureEvent::LongRelease(id)
+                    } else {
+                        GestureEvent::ShortPress(id)
+                    };
+                    let _ = events.push(release);
+                }
+                let first_half = !long && !in_chord && !tracker.double_pressed;
+                tracker.short_release_at = first_half.then_some(now);
+                tracker.pressed_at = None;
+                tracker.next_held = None;
+            }
+        }
+
+        // Chord members stay excluded until released
+        let mut still_chorded = ButtonSet::EMPTY;
+        for id in self.chorded.iter().filter(|id| pressed.contains(*id)) {
+            still_chorded.insert(id);
+        }
+        self.chorded = still_chorded;
+        self.previous = pressed;
+
+        events
+    }
+}
+
+impl Default for GestureDetector {
+    fn default() -> Self {
+        Self::new(GestureConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Button task polling period
+    const TICK_MS: u64 = 10;
+
+    const LEFT: ButtonSet = ButtonSet::of(&[ButtonId::LeftTurn]);
+    const HAZARD: ButtonSet = ButtonSet::of(&[ButtonId::LeftTurn, ButtonId::RightTurn]);
+
+    /// Hold `pressed` from `from` until just before `to` (in ms), sampled every tick
+    fn hold(
+        detector: &mut GestureDetector,
+        pressed: ButtonSet,
+        from: u64,
+        to: u64,
+    ) -> heapless::Vec<GestureEvent, 32> {
+        let mut events = heapless::Vec::new();
+        for ms in (from..to).step_by(TICK_MS as usize) {
+            for event in detector.update(pressed, Instant::from_millis(ms)) {
+                events.push(event).unwrap();
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn short_press_fires_on_release() {
+        let mut detector = GestureDetector::default();
+        assert!(hold(&mut detector, LEFT, 0, 200).is_empty());
+        assert_eq!(
+            hold(&mut detector, ButtonSet::EMPTY, 200, 210),
+            [GestureEvent::ShortPress(ButtonId::LeftTurn)]
+        );
+    }
+
+    #[test]
+    fn long_press_fires_once_then_repeats_held() {
+        let mut detector = GestureDetector::default();
+        assert_eq!(
+            hold(&mut detector, LEFT, 0, 1100),
+            [
+                GestureEvent::LongPress(ButtonId::LeftTurn),
+                GestureEvent::Held(ButtonId::LeftTurn, Duration::from_millis(800)),
+                GestureEvent::Held(ButtonId::LeftTurn, Duration::from_millis(1050)),
+            ]
+        );
+        // Releasing a long press is not also a short press
+        assert_eq!(
+            hold(&mut detector, ButtonSet::EMPTY, 1100, 1200),
+            [GestureEvent::LongRelease(ButtonId::LeftTurn)]
+        );
+    }
+
+    #[test]
+    fn quick_second_press_is_a_double_press() {
+        let mut detector = GestureDetector::default();
+        let mut events = hold(&mut detector, LEFT, 0, 100);
+        events.extend(hold(&mut detector, ButtonSet::EMPTY, 100, 300));
+        events.extend(hold(&mut detector, LEFT, 300, 400));
+        events.extend(hold(&mut detector, ButtonSet::EMPTY, 400, 500));
+        assert_eq!(
+            events,
+            [
+                GestureEvent::ShortPress(ButtonId::LeftTurn),
+                GestureEvent::DoublePress(ButtonId::LeftTurn),
+                GestureEvent::ShortPress(ButtonId::LeftTurn),
+            ]
+        );
+
+        // The second half of a double press does not start another one
+        assert!(hold(&mut detector, LEFT, 500, 510).is_empty());
+    }
+
+    #[test]
+    fn slow_second_press_is_not_a_double_press() {
+        let mut detector = GestureDetector::default();
+        hold(&mut detector, LEFT, 0, 100);
+        hold(&mut detector, ButtonSet::EMPTY, 100, 450);
+        assert!(hold(&mut detector, LEFT, 450, 550).is_empty());
+    }
+
+    #[test]
+    fn long_press_does_not_start_a_double_press() {
+        let mut detector = GestureDetector::default();
+        hold(&mut detector, LEFT, 0, 900);
+        assert_eq!(
+            hold(&mut detector, ButtonSet::EMPTY, 900, 950),
+            [GestureEvent::LongRelease(ButtonId::LeftTurn)]
+        );
+        assert!(hold(&mut detector, LEFT, 950, 1000).is_empty());
+    }
+
+    #[test]
+    fn chord_members_are_excluded_until_released() {
+        let mut detector = GestureDetector::default();
+        let mut events = hold(&mut detector, LEFT, 0, 30);
+        events.extend(hold(&mut detector, HAZARD, 30, 1500));
+        events.extend(hold(&mut detector, LEFT, 1500, 1600));
+        events.extend(hold(&mut detector, ButtonSet::EMPTY, 1600, 1700));
+        assert_eq!(events, [GestureEvent::Chord(HAZARD)]);
+
+        // A fresh press after the chord is an ordinary press again
+        hold(&mut detector, LEFT, 1700, 1800);
+        assert_eq!(
+            hold(&mut detector, ButtonSet::EMPTY, 1800, 1810),
+            [GestureEvent::ShortPress(ButtonId::LeftTurn)]
+        );
+    }
+
+    #[test]
+    fn chord_is_reported_again_as_it_grows() {
+        let mut detector = GestureDetector::default();
+        let three = ButtonSet::of(&[ButtonId::LeftTurn, ButtonId::RightTurn, ButtonId::Lock]);
+        let mut events = hold(&mut detector, HAZARD, 0, 100);
+        events.extend(hold(&mut detector, three, 100, 200));
+        assert_eq!(events, [GestureEvent::Chord(HAZARD), GestureEvent::Chord(three)]);
+    }
+}
@@ -0,0 +1,80 @@
+//! Button identifiers and button sets (pure, no GPIO)
+
+/// Button identifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ButtonId {
+    CruiseDown,
+    CruiseUp,
+    Reverse,
+    PushToTalk,
+    Horn,
+    PowerSave,
+    Rearview,
+    LeftTurn,
+    RightTurn,
+    Lock, // Toggle mode
+}
+
+impl ButtonId {
+    /// Every button, in declaration order
+    pub const ALL: [ButtonId; 10] = [
+        ButtonId::CruiseDown,
+        ButtonId::CruiseUp,
+        ButtonId::Reverse,
+        ButtonId::PushToTalk,
+        ButtonId::Horn,
+        ButtonId::PowerSave,
+        ButtonId::Rearview,
+        ButtonId::LeftTurn,
+        ButtonId::RightTurn,
+        ButtonId::Lock,
+    ];
+}
+
+/// A set of buttons, one bit per `ButtonId`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub struct ButtonSet(u16);
+
+impl ButtonSet {
+    pub const EMPTY: Self = Self(0);
+
+    /// Build a set from a list of buttons
+    pub const fn of(ids: &[ButtonId]) -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < ids.len() {
+            bits |= 1 << ids[i] as u16;
+            i += 1;
+        }
+        Self(bits)
+    }
+
+    pub fn insert(&mut self, id: ButtonId) {
+        self.0 |= 1 << id as u16;
+    }
+
+    pub fn remove(&mut self, id: ButtonId) {
+        self.0 &= !(1 << id as u16);
+    }
+
+    pub fn contains(&self, id: ButtonId) -> bool {
+        self.0 & (1 << id as u16) != 0
+    }
+
+    /// True if every button in `other` is also in this set
+    pub fn contains_all(&self, other: ButtonSet) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn len(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = ButtonId> {
+        ButtonId::ALL.into_iter().filter(move |id| self.contains(*id))
+    }
+}
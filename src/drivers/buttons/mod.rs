@@ -1,35 +1,53 @@
 use defmt::info;
+use embassy_futures::select::select_slice;
+use embassy_stm32::exti::ExtiInput;
 use embassy_stm32::gpio::{Flex, Pull, Pin};
 use embassy_stm32::Peri;
 
-/// Button identifiers
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum ButtonId {
-    CruiseDown,
-    CruiseUp,
-    Reverse,
-    PushToTalk,
-    Horn,
-    PowerSave,
-    Rearview,
-    LeftTurn,  // Toggle mode
-    RightTurn, // Toggle mode
-    Lock,      // Toggle mode
+mod debounce;
+mod id;
+pub mod gestures;
+pub mod source;
+
+pub use debounce::{ButtonEvent, ButtonState, ButtonType, SETTLE_PERIOD};
+pub use id::{ButtonId, ButtonSet};
+pub use gestures::{GestureConfig, GestureDetector, GestureEvent};
+pub use source::{ButtonSource, ScriptedButtons};
+
+/// How the button task notices button changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum InputMode {
+    /// Read every pin every 10ms
+    Polled,
+    /// Sleep until an EXTI edge, then poll only while buttons are settling or held
+    Interrupt,
 }
 
-/// Button event types
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum ButtonEvent {
-    Pressed(ButtonId),
-    Released(ButtonId),
-    Toggled(ButtonId, bool), // (button, new_state)
+/// Input pin behind a button
+pub enum ButtonPin {
+    /// Plain GPIO input, only ever polled
+    Polled(Flex<'static>),
+    /// GPIO input with its EXTI line, can wake the button task on an edge
+    Interrupt(ExtiInput<'static>),
 }
 
-/// Button type - regular or toggle
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum ButtonType {
-    Regular,
-    Toggle,
+impl ButtonPin {
+    fn polled<P: Pin>(pin: Peri<'static, P>) -> Self {
+        let mut flex = Flex::new(pin);
+        flex.set_as_input(Pull::Up);
+        ButtonPin::Polled(flex)
+    }
+
+    fn interrupt<P: Pin>(pin: Peri<'static, P>, ch: Peri<'static, P::ExtiChannel>) -> Self {
+        ButtonPin::Interrupt(ExtiInput::new(pin, ch, Pull::Up))
+    }
+
+    fn is_high(&self) -> bool {
+        match self {
+            ButtonPin::Polled(flex) => flex.is_high(),
+            ButtonPin::Interrupt(exti) => exti.is_high(),
+        }
+    }
 }
 
 /// Button configuration with pin
@@ -37,31 +55,57 @@ pub struct Button {
     pub id: ButtonId,
     pub name: &'static str,
     pub button_type: ButtonType,
-    pub pin: Flex<'static>,
+    pub pin: ButtonPin,
 }
 
 impl Button {
     /// Create a regular button
     pub fn regular<P: Pin>(id: ButtonId, name: &'static str, pin: Peri<'static, P>) -> Self {
-        let mut flex = Flex::new(pin);
-        flex.set_as_input(Pull::Up);
         Self {
             id,
             name,
             button_type: ButtonType::Regular,
-            pin: flex,
+            pin: ButtonPin::polled(pin),
         }
     }
 
     /// Create a toggle button
     pub fn toggle<P: Pin>(id: ButtonId, name: &'static str, pin: Peri<'static, P>) -> Self {
-        let mut flex = Flex::new(pin);
-        flex.set_as_input(Pull::Up);
         Self {
             id,
             name,
             button_type: ButtonType::Toggle,
-            pin: flex,
+            pin: ButtonPin::polled(pin),
+        }
+    }
+
+    /// Create a regular button that can wake the task through its EXTI line
+    pub fn regular_exti<P: Pin>(
+        id: ButtonId,
+        name: &'static str,
+        pin: Peri<'static, P>,
+        ch: Peri<'static, P::ExtiChannel>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            button_type: ButtonType::Regular,
+            pin: ButtonPin::interrupt(pin, ch),
+        }
+    }
+
+    /// Create a toggle button that can wake the task through its EXTI line
+    pub fn toggle_exti<P: Pin>(
+        id: ButtonId,
+        name: &'static str,
+        pin: Peri<'static, P>,
+        ch: Peri<'static, P::ExtiChannel>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            button_type: ButtonType::Toggle,
+            pin: ButtonPin::interrupt(pin, ch),
         }
     }
 }
@@ -85,108 +129,53 @@ impl ButtonInputs {
         Self { buttons: button_vec }
     }
 
-    /// Read the state of a specific button (returns true if pressed)
-    pub fn is_pressed(&self, id: ButtonId) -> bool {
-        if let Some(button) = self.buttons.iter().find(|b| b.id == id) {
-            // Inverted because we use pull-up resistors (active low)
-            return !button.pin.is_high();
-        }
-        false
-    }
-}
-
-/// Button state tracker with debouncing
-pub struct ButtonState {
-    // Current debounced states (true = pressed)
-    states: heapless::Vec<bool, 16>,
-    // Raw states for debouncing
-    raw_states: heapless::Vec<bool, 16>,
-    // Debounce counters
-    debounce_counters: heapless::Vec<u8, 16>,
-    // Toggle states for toggle-mode buttons
-    toggle_states: heapless::FnvIndexMap<ButtonId, bool, 8>,
-}
-
-impl ButtonState {
-    pub fn new(inputs: &ButtonInputs) -> Self {
-        let mut states = heapless::Vec::new();
-        let mut raw_states = heapless::Vec::new();
-        let mut debounce_counters = heapless::Vec::new();
-
-        // Initialize vectors with the right number of buttons
-        for _button in &inputs.buttons {
-            let _ = states.push(false);
-            let _ = raw_states.push(false);
-            let _ = debounce_counters.push(0);
-        }
-
-        // Initialize toggle states for toggle buttons
-        let mut toggle_states = heapless::FnvIndexMap::new();
-        for button in &inputs.buttons {
-            if button.button_type == ButtonType::Toggle {
-                let _ = toggle_states.insert(button.id, false);
+    /// Buttons without an EXTI line, which only a poll can notice
+    pub fn polled(&self) -> ButtonSet {
+        let mut set = ButtonSet::EMPTY;
+        for button in self.buttons.iter() {
+            if matches!(button.pin, ButtonPin::Polled(_)) {
+                set.insert(button.id);
             }
         }
-
-        Self {
-            states,
-            raw_states,
-            debounce_counters,
-            toggle_states,
-        }
+        set
     }
 
-    /// Update button states with debouncing
-    /// Returns a vector of button events that occurred
-    pub fn update(&mut self, inputs: &ButtonInputs) -> heapless::Vec<ButtonEvent, 10> {
-        let mut events = heapless::Vec::new();
-
-        // Read and debounce each button
-        for (i, button) in inputs.buttons.iter().enumerate() {
-            // Read current raw state
-            let raw = inputs.is_pressed(button.id);
-
-            if raw != self.raw_states[i] {
-                // State changed, reset debounce counter
-                self.debounce_counters[i] = 0;
-                self.raw_states[i] = raw;
-            } else if self.debounce_counters[i] < 5 {
-                // Same state, increment counter
-                self.debounce_counters[i] += 1;
-
-                // Check if debounced
-                if self.debounce_counters[i] == 5 && self.states[i] != raw {
-                    // State has been stable for 5 cycles, update
-                    self.states[i] = raw;
-
-                    // Generate events based on button type
-                    match button.button_type {
-                        ButtonType::Toggle => {
-                            if self.states[i] {
-                                // Button pressed, toggle the state
-                                if let Some(toggle_state) = self.toggle_states.get_mut(&button.id) {
-                                    *toggle_state = !*toggle_state;
-                                    let _ = events.push(ButtonEvent::Toggled(button.id, *toggle_state));
-                                }
-                            }
-                        }
-                        ButtonType::Regular => {
-                            if self.states[i] {
-                                let _ = events.push(ButtonEvent::Pressed(button.id));
-                            } else {
-                                let _ = events.push(ButtonEvent::Released(button.id));
-                            }
-                        }
-                    }
+    /// Wait for an edge on any EXTI-capable button
+    ///
+    /// Never returns if no button has an EXTI line, so callers should race
+    /// it against a timeout.
+    pub async fn wait_for_edge(&mut self) {
+        let mut lines = self.buttons.iter_mut().filter_map(|button| match &mut button.pin {
+            ButtonPin::Interrupt(exti) => Some(exti),
+            ButtonPin::Polled(_) => None,
+        });
+
+        // One slot per possible button; slots without an EXTI line never finish
+        let waits: [_; 16] = core::array::from_fn(|_| {
+            let exti = lines.next();
+            async move {
+                match exti {
+                    Some(exti) => exti.wait_for_any_edge().await,
+                    None => core::future::pending().await,
                 }
             }
-        }
+        });
+        select_slice(core::pin::pin!(waits)).await;
+    }
+}
 
-        events
+/// STM32 GPIO implementation
+impl ButtonSource for ButtonInputs {
+    fn buttons(&self) -> impl Iterator<Item = (ButtonId, ButtonType)> + '_ {
+        self.buttons.iter().map(|b| (b.id, b.button_type))
     }
 
-    /// Get the current toggle state of a toggle button
-    pub fn get_toggle_state(&self, id: ButtonId) -> Option<bool> {
-        self.toggle_states.get(&id).copied()
+    /// Read the state of a specific button (returns true if pressed)
+    fn is_pressed(&self, id: ButtonId) -> bool {
+        if let Some(button) = self.buttons.iter().find(|b| b.id == id) {
+            // Inverted because we use pull-up resistors (active low)
+            return !button.pin.is_high();
+        }
+        false
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,62 @@
+//! Button input sources
+//!
+//! `ButtonState` reads buttons through the `ButtonSource` trait, so the
+//! debounce and toggle logic runs the same against the STM32 GPIO pins
+//! (`ButtonInputs`) or an in-memory source driven by a script
+//! (`ScriptedButtons`).
+
+use super::{ButtonId, ButtonSet, ButtonType};
+
+/// Something that can report raw (undebounced) button levels
+pub trait ButtonSource {
+    /// Configured buttons and their types, always in the same order
+    fn buttons(&self) -> impl Iterator<Item = (ButtonId, ButtonType)> + '_;
+
+    /// Raw level of a button, true if pressed
+    fn is_pressed(&self, id: ButtonId) -> bool;
+}
+
+/// In-memory button source for simulation and host tests
+pub struct ScriptedButtons {
+    buttons: heapless::Vec<(ButtonId, ButtonType), 16>,
+    pressed: ButtonSet,
+}
+
+impl ScriptedButtons {
+    /// Create a source with the given buttons, all released
+    pub fn new(buttons: impl IntoIterator<Item = (ButtonId, ButtonType)>) -> Self {
+        let mut button_vec = heapless::Vec::new();
+        for button in buttons {
+            let _ = button_vec.push(button);
+        }
+
+        Self {
+            buttons: button_vec,
+            pressed: ButtonSet::EMPTY,
+        }
+    }
+
+    /// Set the raw level of one button
+    pub fn set(&mut self, id: ButtonId, pressed: bool) {
+        if pressed {
+            self.pressed.insert(id);
+        } else {
+            self.pressed.remove(id);
+        }
+    }
+
+    /// Set the raw level of every button at once
+    pub fn set_pressed(&mut self, pressed: ButtonSet) {
+        self.pressed = pressed;
+    }
+}
+
+impl ButtonSource for ScriptedButtons {
+    fn buttons(&self) -> impl Iterator<Item = (ButtonId, ButtonType)> + '_ {
+        self.buttons.iter().copied()
+    }
+
+    fn is_pressed(&self, id: ButtonId) -> bool {
+        self.pressed.contains(id)
+    }
+}
@@ -0,0 +1,179 @@
+//! Crash records kept in battery-backed SRAM
+//!
+//! The panic and HardFault handlers write a `CrashRecord` (message, PC/LR,
+//! fault status registers and uptime) to the STM32F429's 4KB backup SRAM and
+//! reset the MCU. The backup SRAM keeps its contents through the reset, so on
+//! the next boot `init` picks the record up and clears it; `last_crash` then
+//! hands it to the USB log, telemetry and the display.
+//!
+//! # Module Structure
+//!
+//! - `record` - Record layout, checksum and fault register decoding (pure)
+//!
+//! # Usage
+//!
+//! ```no_run
+//! #[panic_handler]
+//! fn panic(info: &core::panic::PanicInfo) -> ! {
+//!     crash::record_panic(info)
+//! }
+//!
+//! // Early in main
+//! crash::init();
+//! if let Some(record) = crash::last_crash() {
+//!     // ...
+//! }
+//! ```
+
+mod record;
+
+pub use record::{fault_name, CrashKind, CrashRecord};
+
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use cortex_m::peripheral::SCB;
+use cortex_m_rt::ExceptionFrame;
+use embassy_stm32::pac;
+use embassy_sync::once_lock::OnceLock;
+use embassy_time::Instant;
+
+/// Start of the backup SRAM
+const BKPSRAM_BASE: usize = 0x4002_4000;
+
+/// The record sits at the start of the backup SRAM
+const RECORD: *mut CrashRecord = BKPSRAM_BASE as *mut CrashRecord;
+
+/// Record left by the previous run, captured once by `init`
+static LAST_CRASH: OnceLock<Option<CrashRecord>> = OnceLock::new();
+
+/// Turn on the backup SRAM and allow writes to it
+///
+/// The backup regulator keeps the SRAM alive on VBAT while the car is off;
+/// across a reset it survives either way.
+fn enable_backup_sram() {
+    pac::RCC.apb1enr().modify(|w| w.set_pwren(true));
+    pac::PWR.cr1().modify(|w| w.set_dbp(true));
+    pac::RCC.ahb1enr().modify(|w| w.set_bkpsramen(true));
+    pac::PWR.csr1().modify(|w| w.set_bre(true));
+}
+
+/// Pick up the record left by the previous run and clear it
+///
+/// Call once, early in `main`.
+pub fn init() {
+    enable_backup_sram();
+
+    // SAFETY: the backup SRAM is enabled and the record is 4-byte aligned;
+    // every bit pattern is a valid `CrashRecord`, garbage fails `is_valid`
+    let record = unsafe {
+        let record = RECORD.read_volatile();
+        let mut cleared = record;
+        cleared.clear();
+        RECORD.write_volatile(cleared);
+        record
+    };
+
+    let record = record.is_valid().then_some(record);
+    if let Some(record) = &record {
+        defmt::error!(
+            "Previous run crashed: {} at {} ms, PC={:#010x} LR={:#010x} {}: {}",
+            record.kind().map(CrashKind::label).unwrap_or("?"),
+            record.uptime_ms,
+            record.pc,
+            record.lr,
+            record.fault(),
+            record.message()
+        );
+    }
+    let _ = LAST_CRASH.init(record);
+}
+
+/// Crash from the previous run, if there was one
+pub fn last_crash() -> Option<&'static CrashRecord> {
+    LAST_CRASH.try_get().and_then(|record| record.as_ref())
+}
+
+/// Write the crash to the USB log
+pub fn log_report(record: &CrashRecord) {
+    log::error!(
+        "CRASH: {} at {} ms uptime",
+        record.kind().map(CrashKind::label).unwrap_or("?"),
+        record.uptime_ms
+    );
+    log::error!("  PC={:#010x} LR={:#010x}", record.pc, record.lr);
+    log::error!(
+        "  CFSR={:#010x} HFSR={:#010x} ({})",
+        record.cfsr,
+        record.hfsr,
+        record.fault()
+    );
+    if let Some(address) = record.fault_address() {
+        log::error!("  fault address {:#010x}", address);
+    }
+    if !record.message().is_empty() {
+        log::error!("  {}", record.message());
+    }
+}
+
+fn uptime_ms() -> u32 {
+    Instant::now().as_millis().min(u32::MAX as u64) as u32
+}
+
+/// Copy the fault status registers into the record
+fn capture_fault_status(record: &mut CrashRecord) {
+    // SAFETY: read-only access to the SCB fault registers
+    let scb = unsafe { &*SCB::PTR };
+    record.cfsr = scb.cfsr.read();
+    record.hfsr = scb.hfsr.read();
+    record.mmfar = scb.mmfar.read();
+    record.bfar = scb.bfar.read();
+}
+
+/// Seal and store the record
+///
+/// Done before anything is logged: a logger that faults or blocks must not
+/// lose the record.
+fn store(mut record: CrashRecord) {
+    record.seal();
+    enable_backup_sram();
+    // SAFETY: interrupts are off and nothing else writes the backup SRAM
+    unsafe { RECORD.write_volatile(record) };
+}
+
+/// Record a panic and reset, call from the `#[panic_handler]`
+pub fn record_panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+
+    let mut record = CrashRecord::new(CrashKind::Panic, uptime_ms());
+    record.pc = cortex_m::register::pc::read();
+    record.lr = cortex_m::register::lr::read();
+    capture_fault_status(&mut record);
+
+    // Location first, the message may be truncated
+    if let Some(location) = info.location() {
+        let file = location.file().rsplit('/').next().unwrap_or("");
+        let _ = write!(record, "{}:{} ", file, location.line());
+    }
+    let _ = write!(record, "{}", info.message());
+
+    store(record);
+    defmt::error!("PANIC: {}", record.message());
+    SCB::sys_reset()
+}
+
+/// Record a HardFault and reset, call from the `HardFault` exception handler
+pub fn record_hard_fault(frame: &ExceptionFrame) -> ! {
+    cortex_m::interrupt::disable();
+
+    let mut record = CrashRecord::new(CrashKind::HardFault, uptime_ms());
+    record.pc = frame.pc();
+    record.lr = frame.lr();
+    capture_fault_status(&mut record);
+
+    let fault = record.fault();
+    let _ = write!(record, "HardFault {}", fault);
+
+    store(record);
+    defmt::error!("HARDFAULT: {} at PC={:#010x}", fault, record.pc);
+    SCB::sys_reset()
+}
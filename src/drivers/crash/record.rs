@@ -0,0 +1,353 @@
+//! Crash record layout and fault register decoding
+//!
+//! Pure data, no hardware access - the record is written to backup SRAM by
+//! the panic/HardFault handlers and read back on the next boot.
+
+use core::fmt;
+
+use crate::protocol::{CrashReport, MAX_CRASH_MESSAGE};
+
+const RECORD_MAGIC: u32 = 0x4352_5348; // "CRSH"
+
+/// What ended the previous run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+#[repr(u32)]
+pub enum CrashKind {
+    Panic = 1,
+    HardFault = 2,
+}
+
+impl CrashKind {
+    pub fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            1 => Some(CrashKind::Panic),
+            2 => Some(CrashKind::HardFault),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CrashKind::Panic => "PANIC",
+            CrashKind::HardFault => "HARDFAULT",
+        }
+    }
+}
+
+/// Crash record as stored in backup SRAM
+///
+/// Writing text into the record with `core::fmt::Write` appends to the
+/// message and silently truncates once it is full.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CrashRecord {
+    magic: u32,
+    kind: u32,
+    /// Milliseconds since boot when the crash happened
+    pub uptime_ms: u32,
+    pub pc: u32,
+    pub lr: u32,
+    /// Configurable Fault Status Register
+    pub cfsr: u32,
+    /// HardFault Status Register
+    pub hfsr: u32,
+    /// MemManage fault address (valid when CFSR.MMARVALID is set)
+    pub mmfar: u32,
+    /// BusFault address (valid when CFSR.BFARVALID is set)
+    pub bfar: u32,
+    message_len: u32,
+    message: [u8; MAX_CRASH_MESSAGE],
+    checksum: u32,
+}
+
+impl CrashRecord {
+    pub const fn new(kind: CrashKind, uptime_ms: u32) -> Self {
+        Self {
+            magic: 0,
+            kind: kind as u32,
+            uptime_ms,
+            pc: 0,
+            lr: 0,
+            cfsr: 0,
+            hfsr: 0,
+            mmfar: 0,
+            bfar: 0,
+            message_len: 0,
+            message: [0; MAX_CRASH_MESSAGE],
+            checksum: 0,
+        }
+    }
+
+    /// Crash kind, `None` for a record that was never written
+    pub fn kind(&self) -> Option<CrashKind> {
+        CrashKind::from_raw(self.kind)
+    }
+
+    /// Panic message, cut back to the last complete UTF-8 character
+    pub fn message(&self) -> &str {
+        let bytes = &self.message[..(self.message_len as usize).min(MAX_CRASH_MESSAGE)];
+        match core::str::from_utf8(bytes) {
+            Ok(message) => message,
+            // SAFETY: `valid_up_to` bytes are valid UTF-8
+            Err(e) => unsafe { core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) },
+        }
+    }
+
+    /// Most specific fault recorded in CFSR/HFSR
+    pub fn fault(&self) -> &'static str {
+        fault_name(self.cfsr, self.hfsr)
+    }
+
+    /// Faulting data address, if the hardware latched one
+    pub fn fault_address(&self) -> Option<u32> {
+        if self.cfsr & cfsr::MMARVALID != 0 {
+            Some(self.mmfar)
+        } else if self.cfsr & cfsr::BFARVALID != 0 {
+            Some(self.bfar)
+        } else {
+            None
+        }
+    }
+
+    /// Mark the record as complete, call just before storing it
+    pub fn seal(&mut self) {
+        self.magic = RECORD_MAGIC;
+        self.checksum = self.compute_checksum();
+    }
+
+    /// True for a sealed record that survived the reset intact
+    pub fn is_valid(&self) -> bool {
+        self.magic == RECORD_MAGIC
+            && self.kind().is_some()
+            && self.message_len as usize <= MAX_CRASH_MESSAGE
+            && self.checksum == self.compute_checksum()
+    }
+
+    /// Wipe the record so it is only reported once
+    pub fn clear(&mut self) {
+        self.magic = 0;
+        self.checksum = 0;
+    }
+
+    /// Wire format for telemetry
+    pub fn to_report(&self) -> CrashReport {
+        let mut report = CrashReport::default();
+        report.kind = self.kind;
+        report.uptime_ms = self.uptime_ms;
+        report.pc = self.pc;
+        report.lr = self.lr;
+        report.cfsr = self.cfsr;
+        report.hfsr = self.hfsr;
+        report.mmfar = self.mmfar;
+        report.bfar = self.bfar;
+        report.set_message(self.message().as_bytes());
+        report
+    }
+
+    fn compute_checksum(&self) -> u32 {
+        let words = [
+            self.magic,
+            self.kind,
+            self.uptime_ms,
+            self.pc,
+            self.lr,
+            self.cfsr,
+            self.hfsr,
+            self.mmfar,
+            self.bfar,
+            self.message_len,
+        ];
+        let bytes = self.message.iter().map(|b| *b as u32);
+        words
+            .into_iter()
+            .chain(bytes)
+            .fold(0x811c_9dc5, |hash, value| (hash ^ value).wrapping_mul(0x0100_0193))
+    }
+}
+
+impl fmt::Write for CrashRecord {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let len = self.message_len as usize;
+        let mut take = s.len().min(MAX_CRASH_MESSAGE - len);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.message[len..len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.message_len += take as u32;
+        Ok(())
+    }
+}
+
+/// CFSR bits (MMFSR, BFSR and UFSR combined)
+mod cfsr {
+    pub const IACCVIOL: u32 = 1 << 0;
+    pub const DACCVIOL: u32 = 1 << 1;
+    pub const MUNSTKERR: u32 = 1 << 3;
+    pub const MSTKERR: u32 = 1 << 4;
+    pub const MLSPERR: u32 = 1 << 5;
+    pub const MMARVALID: u32 = 1 << 7;
+    pub const IBUSERR: u32 = 1 << 8;
+    pub const PRECISERR: u32 = 1 << 9;
+    pub const IMPRECISERR: u32 = 1 << 10;
+    pub const UNSTKERR: u32 = 1 << 11;
+    pub const STKERR: u32 = 1 << 12;
+    pub const LSPERR: u32 = 1 << 13;
+    pub const BFARVALID: u32 = 1 << 15;
+    pub const UNDEFINSTR: u32 = 1 << 16;
+    pub const INVSTATE: u32 = 1 << 17;
+    pub const INVPC: u32 = 1 << 18;
+    pub const NOCP: u32 = 1 << 19;
+    pub const UNALIGNED: u32 = 1 << 24;
+    pub const DIVBYZERO: u32 = 1 << 25;
+}
+
+/// HFSR bits
+mod hfsr {
+    pub const VECTTBL: u32 = 1 << 1;
+    pub const FORCED: u32 = 1 << 30;
+    pub const DEBUGEVT: u32 = 1 << 31;
+}
+
+/// CFSR causes, most useful first
+const CFSR_NAMES: [(u32, &str); 17] = [
+    (cfsr::UNDEFINSTR, "UNDEFINSTR"),
+    (cfsr::INVSTATE, "INVSTATE"),
+    (cfsr::INVPC, "INVPC"),
+    (cfsr::NOCP, "NOCP"),
+    (cfsr::UNALIGNED, "UNALIGNED"),
+    (cfsr::DIVBYZERO, "DIVBYZERO"),
+    (cfsr::IACCVIOL, "IACCVIOL"),
+    (cfsr::DACCVIOL, "DACCVIOL"),
+    (cfsr::MUNSTKERR, "MUNSTKERR"),
+    (cfsr::MSTKERR, "MSTKERR"),
+    (cfsr::MLSPERR, "MLSPERR"),
+    (cfsr::IBUSERR, "IBUSERR"),
+    (cfsr::PRECISERR, "PRECISERR"),
+    (cfsr::IMPRECISERR, "IMPRECISERR"),
+    (cfsr::UNSTKERR, "UNSTKERR"),
+    (cfsr::STKERR, "STKERR"),
+    (cfsr::LSPERR, "LSPERR"),
+];
+
+/// Name of the most specific fault set in CFSR/HFSR
+///
+/// A fault escalated to HardFault shows up as FORCED in HFSR with the real
+/// cause in CFSR, so CFSR is checked first.
+pub fn fault_name(cfsr: u32, hfsr: u32) -> &'static str {
+    if let Some((_, name)) = CFSR_NAMES.iter().find(|(bit, _)| cfsr & bit != 0) {
+        return name;
+    }
+    if hfsr & hfsr::VECTTBL != 0 {
+        "VECTTBL"
+    } else if hfsr & hfsr::FORCED != 0 {
+        "FORCED"
+    } else if hfsr & hfsr::DEBUGEVT != 0 {
+        "DEBUGEVT"
+    } else {
+        "NONE"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    fn sealed() -> CrashRecord {
+        let mut record = CrashRecord::new(CrashKind::Panic, 1234);
+        let (file, line) = ("src/main.rs", 42);
+        write!(record, "panicked at {}:{}", file, line).unwrap();
+        record.pc = 0x0800_1234;
+        record.seal();
+        record
+    }
+
+    #[test]
+    fn only_sealed_records_are_valid() {
+        let mut record = CrashRecord::new(CrashKind::HardFault, 10);
+        assert!(!record.is_valid());
+        record.seal();
+        assert!(record.is_valid());
+        assert_eq!(record.kind(), Some(CrashKind::HardFault));
+
+        record.clear();
+        assert!(!record.is_valid());
+    }
+
+    #[test]
+    fn any_changed_field_fails_the_checksum() {
+        let record = sealed();
+        assert!(record.is_valid());
+
+        let mut damaged = record;
+        damaged.uptime_ms += 1;
+        assert!(!damaged.is_valid());
+
+        let mut damaged = record;
+        damaged.message[3] ^= 0x20;
+        assert!(!damaged.is_valid());
+
+        // Garbage in the kind or length is rejected even with a matching checksum
+        let mut damaged = record;
+        damaged.kind = 7;
+        damaged.seal();
+        assert!(!damaged.is_valid());
+        let mut damaged = record;
+        damaged.message_len = MAX_CRASH_MESSAGE as u32 + 1;
+        damaged.seal();
+        assert!(!damaged.is_valid());
+    }
+
+    #[test]
+    fn writes_append_and_truncate_when_full() {
+        let record = sealed();
+        assert_eq!(record.message(), "panicked at src/main.rs:42");
+
+        let mut record = CrashRecord::new(CrashKind::Panic, 0);
+        for _ in 0..MAX_CRASH_MESSAGE {
+            record.write_str("ab").unwrap();
+        }
+        assert_eq!(record.message().len(), MAX_CRASH_MESSAGE);
+        assert!(record.message().bytes().all(|b| b == b'a' || b == b'b'));
+    }
+
+    #[test]
+    fn truncation_stops_at_a_char_boundary() {
+        let mut record = CrashRecord::new(CrashKind::Panic, 0);
+        for _ in 0..MAX_CRASH_MESSAGE - 1 {
+            record.write_str("x").unwrap();
+        }
+        // A two byte character that would straddle the end is left out whole
+        record.write_str("é").unwrap();
+        assert_eq!(record.message().len(), MAX_CRASH_MESSAGE - 1);
+        assert!(record.message().bytes().all(|b| b == b'x'));
+
+        // Later writes that fit still land
+        record.write_str("!").unwrap();
+        assert_eq!(record.message().len(), MAX_CRASH_MESSAGE);
+        assert!(record.message().ends_with('!'));
+    }
+
+    #[test]
+    fn fault_name_prefers_the_cfsr_cause() {
+        // An escalated fault reports its real cause, not FORCED
+        assert_eq!(fault_name(cfsr::PRECISERR | cfsr::BFARVALID, hfsr::FORCED), "PRECISERR");
+        assert_eq!(fault_name(cfsr::DIVBYZERO | cfsr::DACCVIOL, 0), "DIVBYZERO");
+        assert_eq!(fault_name(cfsr::BFARVALID, hfsr::FORCED), "FORCED");
+        assert_eq!(fault_name(0, hfsr::VECTTBL | hfsr::FORCED), "VECTTBL");
+        assert_eq!(fault_name(0, hfsr::DEBUGEVT), "DEBUGEVT");
+        assert_eq!(fault_name(0, 0), "NONE");
+    }
+
+    #[test]
+    fn fault_address_follows_the_valid_bits() {
+        let mut record = sealed();
+        record.mmfar = 0x2000_0000;
+        record.bfar = 0x4000_0000;
+        assert_eq!(record.fault_address(), None);
+        record.cfsr = cfsr::BFARVALID;
+        assert_eq!(record.fault_address(), Some(0x4000_0000));
+        record.cfsr = cfsr::MMARVALID | cfsr::BFARVALID;
+        assert_eq!(record.fault_address(), Some(0x2000_0000));
+    }
+}
@@ -0,0 +1,490 @@
+//! Packed 4bpp framebuffer with change tracking
+//!
+//! Pixels are stored the way the SSD1322 wants them on the wire: two per
+//! byte, left pixel in the high nibble, 128 bytes per row. The frame is split
+//! into 16x8 pixel tiles; writes mark tiles dirty, and `take_changes` hashes
+//! the dirty tiles against what the panel was last sent (`PanelShadow`) so a
+//! clear-and-redraw that ends up identical costs nothing on the bus.
+//!
+//! Two framebuffers may take turns on one panel (double buffering). A
+//! buffer's dirty tiles only cover its own writes since its last flush, so
+//! the panel also remembers the tiles the other buffer sent in between.
+//!
+//! Pure data, no hardware access, so it can be drawn into on the host.
+
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+use super::font16::{FONT16_CHAR_ADDR, FONT_WIDTH as FONT16_WIDTH, FONT_HEIGHT as FONT16_HEIGHT};
+use super::geometry::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+/// Bytes in a packed frame
+pub const FRAMEBUFFER_SIZE: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT / 2;
+
+/// Bytes per row of pixels
+pub const ROW_BYTES: usize = DISPLAY_WIDTH / 2;
+
+/// Tile size; the width is a multiple of the controller's 4-pixel columns
+pub const TILE_WIDTH: usize = 16;
+pub const TILE_HEIGHT: usize = 8;
+
+const TILE_COLUMNS: usize = DISPLAY_WIDTH / TILE_WIDTH;
+const TILE_ROWS: usize = DISPLAY_HEIGHT / TILE_HEIGHT;
+const TILE_COUNT: usize = TILE_COLUMNS * TILE_ROWS;
+
+/// One bit per tile
+type TileMask = u128;
+const _: () = assert!(TILE_COUNT <= TileMask::BITS as usize);
+
+// Font multiplier for large characters
+const FONT_LARGE_MULTIPLIER: usize = 4;
+
+/// Most windows a single flush can produce (every other tile changed)
+pub const MAX_WINDOWS: usize = TILE_COUNT / 2;
+
+/// Area of the panel to rewrite, in pixels and aligned to tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Window {
+    pub const FULL: Window = Window {
+        x: 0,
+        y: 0,
+        width: DISPLAY_WIDTH,
+        height: DISPLAY_HEIGHT,
+    };
+
+    /// First and last 4-pixel controller column
+    pub fn columns(&self) -> (u8, u8) {
+        ((self.x / 4) as u8, ((self.x + self.width) / 4 - 1) as u8)
+    }
+
+    /// First and last row
+    pub fn rows(&self) -> (u8, u8) {
+        (self.y as u8, (self.y + self.height - 1) as u8)
+    }
+
+    /// True if the window spans whole rows, so its bytes are contiguous
+    pub fn full_width(&self) -> bool {
+        self.width == DISPLAY_WIDTH
+    }
+
+    /// Packed bytes the window covers
+    pub fn bytes(&self) -> usize {
+        self.width * self.height / 2
+    }
+}
+
+/// Changed areas from one `take_changes` call, top to bottom
+pub type Windows = heapless::Vec<Window, MAX_WINDOWS>;
+
+/// What the panel is known to be showing, as one hash per tile
+#[derive(Debug, Clone)]
+pub struct PanelShadow {
+    hashes: [u32; TILE_COUNT],
+    known: TileMask,
+    /// Tiles changed by the last flush, which the other buffer doesn't know about
+    recent: TileMask,
+}
+
+impl PanelShadow {
+    /// Panel content unknown, so the first flush sends everything
+    pub const fn new() -> Self {
+        Self {
+            hashes: [0; TILE_COUNT],
+            known: 0,
+            recent: 0,
+        }
+    }
+
+    /// Forget what the panel shows, forcing a full resend
+    pub fn invalidate(&mut self) {
+        self.known = 0;
+    }
+}
+
+impl Default for PanelShadow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 256x64 Gray4 frame, packed two pixels per byte
+#[derive(Clone)]
+pub struct Framebuffer {
+    data: [u8; FRAMEBUFFER_SIZE],
+    dirty: TileMask,
+}
+
+impl Framebuffer {
+    pub const fn new() -> Self {
+        Self {
+            data: [0; FRAMEBUFFER_SIZE],
+            dirty: 0,
+        }
+    }
+
+    /// Set every pixel to `shade`
+    pub fn fill(&mut self, shade: u8) {
+        let shade = shade & 0xF;
+        let byte = (shade << 4) | shade;
+        if self.data.iter().any(|b| *b != byte) {
+            self.data.fill(byte);
+            self.dirty = TileMask::MAX;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.fill(0);
+    }
+
+    /// Set a pixel, ignoring anything off screen
+    pub fn set_pixel(&mut self, x: usize, y: usize, shade: u8) {
+        if x >= DISPLAY_WIDTH || y >= DISPLAY_HEIGHT {
+            return;
+        }
+        let index = y * ROW_BYTES + x / 2;
+        let byte = self.data[index];
+        let updated = if x.is_multiple_of(2) {
+            (byte & 0x0F) | ((shade & 0xF) << 4)
+        } else {
+            (byte & 0xF0) | (shade & 0xF)
+        };
+        if updated != byte {
+            self.data[index] = updated;
+            self.dirty |= 1 << tile_index(x / TILE_WIDTH, y / TILE_HEIGHT);
+        }
+    }
+
+    /// Shade of a pixel, 0 for anything off screen
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        if x >= DISPLAY_WIDTH || y >= DISPLAY_HEIGHT {
+            return 0;
+        }
+        let byte = self.data[y * ROW_BYTES + x / 2];
+        if x.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        }
+    }
+
+    /// Move the whole frame `dx` pixels to the right, blanking the uncovered edge
+    ///
+    /// Applied to a finished frame for the horizontal part of the burn-in pixel
+    /// shift; the vertical part is the panel's start line (see
+    /// `Ssd1322Display::set_vertical_shift`), which costs nothing on the bus.
+    pub fn shift_x(&mut self, dx: i32) {
+        if dx == 0 {
+            return;
+        }
+        let bytes = (dx.unsigned_abs() as usize / 2).min(ROW_BYTES);
+        let odd = dx % 2 != 0;
+        for row in self.data.chunks_exact_mut(ROW_BYTES) {
+            // Whole bytes first, then the odd pixel as a nibble carry along the row
+            if dx > 0 {
+                row.copy_within(..ROW_BYTES - bytes, bytes);
+                row[..bytes].fill(0);
+                if odd {
+                    let mut carry = 0;
+                    for byte in row.iter_mut() {
+                        let next = *byte << 4;
+                        *byte = carry | (*byte >> 4);
+                        carry = next;
+                    }
+                }
+            } else {
+                row.copy_within(bytes.., 0);
+                row[ROW_BYTES - bytes..].fill(0);
+                if odd {
+                    let mut carry = 0;
+                    for byte in row.iter_mut().rev() {
+                        let next = *byte >> 4;
+                        *byte = (*byte << 4) | carry;
+                        carry = next;
+                    }
+                }
+            }
+        }
+        self.dirty = TileMask::MAX;
+    }
+
+    /// The whole packed frame
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Packed bytes of one row of a window
+    pub fn row_bytes(&self, window: &Window, y: usize) -> &[u8] {
+        let start = y * ROW_BYTES + window.x / 2;
+        &self.data[start..start + window.width / 2]
+    }
+
+    /// Packed bytes of a full-width window, contiguous in memory
+    pub fn rows_bytes(&self, window: &Window) -> &[u8] {
+        &self.data[window.y * ROW_BYTES..(window.y + window.height) * ROW_BYTES]
+    }
+
+    /// Areas that differ from what `panel` was last sent, marking them sent
+    ///
+    /// Changed tiles are merged into runs along each tile row, and runs with
+    /// the same columns in consecutive tile rows into one window.
+    pub fn take_changes(&mut self, panel: &mut PanelShadow) -> Windows {
+        let candidates = self.dirty | panel.recent | !panel.known;
+        self.dirty = 0;
+
+        let mut changed: TileMask = 0;
+        for tile in (0..TILE_COUNT).filter(|tile| candidates & (1 << tile) != 0) {
+            let hash = self.tile_hash(tile);
+            let bit = 1 << tile;
+            if panel.known & bit == 0 || panel.hashes[tile] != hash {
+                changed |= bit;
+            }
+            panel.hashes[tile] = hash;
+            panel.known |= bit;
+        }
+        panel.recent = changed;
+
+        let mut windows = Windows::new();
+        for band in 0..TILE_ROWS {
+            let mut column = 0;
+            while column < TILE_COLUMNS {
+                if changed & (1 << tile_index(column, band)) == 0 {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < TILE_COLUMNS && changed & (1 << tile_index(column, band)) != 0 {
+                    column += 1;
+                }
+
+                let run = Window {
+                    x: start * TILE_WIDTH,
+                    y: band * TILE_HEIGHT,
+                    width: (column - start) * TILE_WIDTH,
+                    height: TILE_HEIGHT,
+                };
+                // Grow a window from the band above if it covers the same columns
+                match windows
+                    .iter_mut()
+                    .find(|w| w.x == run.x && w.width == run.width && w.y + w.height == run.y)
+                {
+                    Some(above) => above.height += TILE_HEIGHT,
+                    None => {
+                        let _ = windows.push(run);
+                    }
+                }
+            }
+        }
+        windows
+    }
+
+    /// FNV-1a over the packed bytes of one tile
+    fn tile_hash(&self, tile: usize) -> u32 {
+        let x = (tile % TILE_COLUMNS) * TILE_WIDTH;
+        let y = (tile / TILE_COLUMNS) * TILE_HEIGHT;
+        (y..y + TILE_HEIGHT)
+            .flat_map(|row| {
+                let start = row * ROW_BYTES + x / 2;
+                self.data[start..start + TILE_WIDTH / 2].iter()
+            })
+            .fold(0x811c_9dc5, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x0100_0193))
+    }
+}
+
+// Text drawing used by the full-screen takeovers in display_write
+impl Framebuffer {
+    /// Draw a pixel at the specified location
+    pub fn draw_pixel(&mut self, x: usize, y: usize, value: u8) {
+        self.set_pixel(x, y, value);
+    }
+
+    /// Draw a character using font16
+    pub fn draw_char(&mut self, x: usize, y: usize, fg: u8, bg: u8, ch: char) {
+        let char_idx = ch as usize;
+        if !(32..127).contains(&char_idx) {
+            return; // Outside printable ASCII range
+        }
+        
+        let data = FONT16_CHAR_ADDR[char_idx - 32];
+        
+        let mut byte_count = 0;
+        for stripe in 0..(FONT16_HEIGHT / 8) {
+            for col in 0..FONT16_WIDTH {
+                for row in 0..8 {
+                    let x_offset = col;
+                    let y_offset = stripe * 8 + row;
+                    let shade = if data[byte_count] & (1 << row) != 0 {
+                        fg
+                    } else {
+                        bg
+                    };
+                    self.draw_pixel(x + x_offset, y + y_offset, shade);
+                }
+                byte_count += 1;
+            }
+        }
+    }
+
+    /// Draw a character using large font (4x scaled font16)
+    pub fn draw_char_large(&mut self, x: usize, y: usize, fg: u8, bg: u8, ch: char) {
+        let char_idx = ch as usize;
+        if !(32..127).contains(&char_idx) {
+            return;
+        }
+        
+        let data = FONT16_CHAR_ADDR[char_idx - 32];
+        
+        let mut byte_count = 0;
+        for stripe in 0..(FONT16_HEIGHT / 8) {
+            for col in 0..FONT16_WIDTH {
+                for row in 0..8 {
+                    let shade = if data[byte_count] & (1 << row) != 0 {
+                        fg
+                    } else {
+                        bg
+                    };
+                    
+                    // Draw scaled pixel (4x4 block)
+                    for scale_y in 0..FONT_LARGE_MULTIPLIER {
+                        for scale_x in 0..FONT_LARGE_MULTIPLIER {
+                            let x_offset = col * FONT_LARGE_MULTIPLIER + scale_x;
+                            let y_offset = (stripe * 8 + row) * FONT_LARGE_MULTIPLIER + scale_y;
+                            self.draw_pixel(x + x_offset, y + y_offset, shade);
+                        }
+                    }
+                }
+                byte_count += 1;
+            }
+        }
+    }
+
+    /// Draw a string using font16
+    pub fn draw_string(&mut self, x: usize, y: usize, fg: u8, bg: u8, text: &str) {
+        for (i, ch) in text.chars().enumerate() {
+            self.draw_char(x + (FONT16_WIDTH * i), y, fg, bg, ch);
+        }
+    }
+
+    /// Draw a string using large font
+    pub fn draw_string_large(&mut self, x: usize, y: usize, fg: u8, bg: u8, text: &str) {
+        for (i, ch) in text.chars().enumerate() {
+            self.draw_char_large(x + (FONT16_WIDTH * FONT_LARGE_MULTIPLIER * i), y, fg, bg, ch);
+        }
+    }
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn tile_index(column: usize, band: usize) -> usize {
+    band * TILE_COLUMNS + column
+}
+
+impl DrawTarget for Framebuffer {
+    type Color = Gray4;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels {
+            if coord.x >= 0 && coord.y >= 0 {
+                self.set_pixel(coord.x as usize, coord.y as usize, color.luma());
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        let Some(bottom_right) = area.bottom_right() else {
+            return Ok(());
+        };
+        for y in area.top_left.y..=bottom_right.y {
+            for x in area.top_left.x..=bottom_right.x {
+                self.set_pixel(x as usize, y as usize, color.luma());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Framebuffer {
+    fn size(&self) -> Size {
+        Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A frame with a few shades along the left and right edges of row 3
+    fn marked() -> Framebuffer {
+        let mut frame = Framebuffer::new();
+        frame.set_pixel(0, 3, 0xA);
+        frame.set_pixel(1, 3, 0x5);
+        frame.set_pixel(100, 3, 0xF);
+        frame.set_pixel(DISPLAY_WIDTH - 1, 3, 0x7);
+        frame
+    }
+
+    #[test]
+    fn shift_right_by_one_pixel() {
+        let mut frame = marked();
+        frame.shift_x(1);
+        assert_eq!(frame.pixel(0, 3), 0);
+        assert_eq!(frame.pixel(1, 3), 0xA);
+        assert_eq!(frame.pixel(2, 3), 0x5);
+        assert_eq!(frame.pixel(101, 3), 0xF);
+        // The right edge falls off and nothing wraps onto the next row
+        assert_eq!(frame.pixel(0, 4), 0);
+        assert_eq!(frame.as_bytes().iter().filter(|b| **b != 0).count(), 3);
+    }
+
+    #[test]
+    fn shift_left_by_one_pixel() {
+        let mut frame = marked();
+        frame.shift_x(-1);
+        assert_eq!(frame.pixel(0, 3), 0x5);
+        assert_eq!(frame.pixel(99, 3), 0xF);
+        assert_eq!(frame.pixel(DISPLAY_WIDTH - 2, 3), 0x7);
+        assert_eq!(frame.pixel(DISPLAY_WIDTH - 1, 3), 0);
+    }
+
+    #[test]
+    fn shift_by_whole_and_odd_bytes() {
+        let mut frame = marked();
+        frame.shift_x(5);
+        assert_eq!(frame.pixel(5, 3), 0xA);
+        assert_eq!(frame.pixel(6, 3), 0x5);
+        assert_eq!(frame.pixel(105, 3), 0xF);
+        frame.shift_x(-5);
+        // Back where it was, less the pixel pushed off the right edge
+        let mut expected = marked();
+        expected.set_pixel(DISPLAY_WIDTH - 1, 3, 0);
+        assert_eq!(frame.as_bytes(), expected.as_bytes());
+    }
+
+    #[test]
+    fn shift_marks_the_frame_for_a_resend() {
+        let mut frame = marked();
+        let mut panel = PanelShadow::new();
+        frame.take_changes(&mut panel);
+        frame.shift_x(0);
+        assert!(frame.take_changes(&mut panel).is_empty());
+        frame.shift_x(1);
+        assert!(!frame.take_changes(&mut panel).is_empty());
+    }
+}
@@ -0,0 +1,15 @@
+//! Panel geometry and shades
+//!
+//! Kept apart from the SSD1322 driver so the drawing code needs no HAL, and
+//! the host renderer builds this file as-is.
+
+// Display dimensions
+pub const DISPLAY_WIDTH: usize = 256;
+pub const DISPLAY_HEIGHT: usize = 64;
+
+// Display shade constants
+pub const DISPLAY_WHITE: u8 = 0xF;
+pub const DISPLAY_MID_SHADE: u8 = 0x7;
+pub const DISPLAY_LOW_SHADE: u8 = 0x2;
+pub const DISPLAY_VLOW_SHADE: u8 = 0x1;
+pub const DISPLAY_BLACK: u8 = 0x0;
@@ -0,0 +1,74 @@
+//! Frame pacing and frame-rate statistics
+//!
+//! Frames start on a fixed grid of slots. A frame that overruns its slot
+//! (slow render, or the flush of the previous frame holding both buffers)
+//! skips the slots it missed instead of rendering late frames back to back,
+//! and the skipped slots are counted as dropped.
+
+use embassy_time::{Duration, Instant};
+
+/// Frame rate over one reporting window
+#[derive(Clone, Copy, Debug, defmt::Format)]
+pub struct FrameStats {
+    /// Frames finished in the window
+    pub frames: u32,
+    /// Frame slots skipped because a frame ran late
+    pub dropped: u32,
+    /// Achieved frames per second, in hundredths
+    pub fps_centi: u32,
+}
+
+pub struct FrameGovernor {
+    period: Duration,
+    report_every: Duration,
+    /// Start of the next frame slot
+    next: Instant,
+    window_start: Instant,
+    frames: u32,
+    dropped: u32,
+}
+
+impl FrameGovernor {
+    /// Pace frames to `fps`, reporting statistics every `report_every`
+    pub fn new(fps: u32, report_every: Duration, now: Instant) -> Self {
+        Self {
+            period: Duration::from_hz(fps as u64),
+            report_every,
+            next: now,
+            window_start: now,
+            frames: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Mark a frame finished at `now` and return when the next one should start
+    pub fn frame_done(&mut self, now: Instant) -> Instant {
+        self.frames += 1;
+        self.next += self.period;
+        if now > self.next {
+            // Every slot that started while this frame was still running is lost
+            let missed = (now - self.next).as_ticks() / self.period.as_ticks() + 1;
+            self.next += Duration::from_ticks(missed * self.period.as_ticks());
+            self.dropped += missed as u32;
+        }
+        self.next
+    }
+
+    /// Statistics for the window just ended, once every `report_every`
+    pub fn report(&mut self, now: Instant) -> Option<FrameStats> {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed < self.report_every {
+            return None;
+        }
+
+        let stats = FrameStats {
+            frames: self.frames,
+            dropped: self.dropped,
+            fps_centi: (self.frames as u64 * 100_000 / elapsed.as_millis().max(1)) as u32,
+        };
+        self.window_start = now;
+        self.frames = 0;
+        self.dropped = 0;
+        Some(stats)
+    }
+}
@@ -0,0 +1,107 @@
+//! Screen layout on the font16 character grid
+//!
+//! The 256x64 panel holds 21 columns by 4 rows of 12x16 characters. Screens
+//! place text by cell, and widgets by the named areas below, instead of
+//! multiplying out `FONT_WIDTH`/`FONT_HEIGHT` themselves. Moving an element
+//! is a change here only.
+
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+use super::font16::{FONT_HEIGHT, FONT_WIDTH};
+use super::geometry::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+/// Characters per row
+pub const COLUMNS: usize = DISPLAY_WIDTH / FONT_WIDTH;
+/// Rows of characters
+pub const ROWS: usize = DISPLAY_HEIGHT / FONT_HEIGHT;
+
+/// A character cell on the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub col: usize,
+    pub row: usize,
+}
+
+impl Cell {
+    pub const fn new(col: usize, row: usize) -> Self {
+        Self { col, row }
+    }
+
+    /// Left edge in pixels
+    pub const fn x(self) -> usize {
+        self.col * FONT_WIDTH
+    }
+
+    /// Top edge in pixels
+    pub const fn y(self) -> usize {
+        self.row * FONT_HEIGHT
+    }
+
+    /// The cell `cols` further right on the same row
+    pub const fn right(self, cols: usize) -> Self {
+        Self::new(self.col + cols, self.row)
+    }
+
+    /// Area `cols` characters wide and one row high starting at this cell
+    pub const fn span(self, cols: usize) -> Rectangle {
+        cells(self.col, self.row, cols, 1)
+    }
+}
+
+/// Area covering a block of character cells
+pub const fn cells(col: usize, row: usize, cols: usize, rows: usize) -> Rectangle {
+    Rectangle::new(
+        Point::new((col * FONT_WIDTH) as i32, (row * FONT_HEIGHT) as i32),
+        Size::new((cols * FONT_WIDTH) as u32, (rows * FONT_HEIGHT) as u32),
+    )
+}
+
+/// Area in raw pixels, for elements that don't sit on the grid
+pub const fn pixels(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+    Rectangle::new(Point::new(x, y), Size::new(width, height))
+}
+
+/// Whole-row areas
+pub const fn row(row: usize) -> Rectangle {
+    cells(0, row, COLUMNS, 1)
+}
+
+/// Main (driving) screen
+pub mod main {
+    use super::{cells, pixels};
+    use embedded_graphics::primitives::Rectangle;
+
+    pub const LEFT_TURN: Rectangle = cells(0, 0, 1, 1);
+    pub const RIGHT_TURN: Rectangle = cells(20, 0, 1, 1);
+    pub const REGEN: Rectangle = cells(0, 1, 1, 1);
+    pub const THROTTLE: Rectangle = cells(1, 1, 1, 1);
+    pub const PEDAL_FAULT: Rectangle = cells(0, 2, 5, 1);
+    pub const LOCK: Rectangle = cells(0, 3, 1, 1);
+    /// Two large digits, the full height of the panel
+    pub const SPEED: Rectangle = cells(5, 0, 8, 4);
+    /// Link and BPS alerts, over the bottom of the speed digits
+    pub const ALERT: Rectangle = cells(5, 2, 8, 1);
+    pub const VC_TIMEOUT: Rectangle = pixels(60, 52, 30, 10);
+    pub const BMS_TIMEOUT: Rectangle = pixels(96, 52, 30, 10);
+    pub const CURRENT: Rectangle = cells(13, 0, 8, 1);
+    pub const HIGH_VOLTAGE: Rectangle = cells(13, 1, 8, 1);
+    pub const LOW_VOLTAGE: Rectangle = cells(13, 2, 8, 1);
+    pub const CRUISE: Rectangle = cells(14, 3, 2, 1);
+    pub const DRIVE_STATE: Rectangle = cells(17, 3, 4, 1);
+}
+
+/// Pedal debug screen
+pub mod debug {
+    use super::{cells, pixels};
+    use embedded_graphics::primitives::Rectangle;
+
+    pub const TITLE: Rectangle = cells(0, 0, 6, 1);
+    pub const VC_TIMEOUT: Rectangle = pixels(180, 3, 30, 10);
+    pub const BMS_TIMEOUT: Rectangle = pixels(216, 3, 30, 10);
+    pub const THROTTLE: Rectangle = cells(0, 1, 15, 1);
+    pub const THROTTLE_BAR: Rectangle = pixels(184, 19, 64, 10);
+    pub const REGEN: Rectangle = cells(0, 2, 15, 1);
+    pub const REGEN_BAR: Rectangle = pixels(184, 35, 64, 10);
+    pub const PEDAL: Rectangle = cells(0, 3, 15, 1);
+}
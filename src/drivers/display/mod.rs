@@ -1,6 +1,11 @@
 pub mod ssd1322;
 pub mod font16;
 pub mod display_write;
+pub mod framebuffer;
+pub mod geometry;
+pub mod governor;
+pub mod layout;
+pub mod widget;
 
-pub use ssd1322::{Ssd1322Display, DISPLAY_BLACK, DISPLAY_WHITE, DISPLAY_MID_SHADE, DISPLAY_LOW_SHADE, DISPLAY_VLOW_SHADE};
-pub use display_write::*;
\ No newline at end of file
+pub use geometry::{DISPLAY_BLACK, DISPLAY_WHITE, DISPLAY_MID_SHADE, DISPLAY_LOW_SHADE, DISPLAY_VLOW_SHADE};
+pub use ssd1322::{Brightness, Ssd1322Display};
@@ -3,24 +3,14 @@ use embassy_stm32::gpio::Output;
 use embassy_stm32::mode::Async;
 use embassy_stm32::spi::Spi;
 use embassy_time::Timer;
-use embedded_graphics::pixelcolor::Gray4;
-use embedded_graphics::prelude::*;
-use super::font16::{FONT16_CHAR_ADDR, FONT_WIDTH as FONT16_WIDTH, FONT_HEIGHT as FONT16_HEIGHT};
+use super::framebuffer::{Framebuffer, PanelShadow, Window};
+use super::framebuffer::ROW_BYTES;
+use super::geometry::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::control::BrightnessPreset;
 
-// Display dimensions
-pub const DISPLAY_WIDTH: usize = 256;
-pub const DISPLAY_HEIGHT: usize = 64;
-const DISPLAY_BUFFER_SIZE: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
-
-// Display shade constants
-pub const DISPLAY_WHITE: u8 = 0xF;
-pub const DISPLAY_MID_SHADE: u8 = 0x7;
-pub const DISPLAY_LOW_SHADE: u8 = 0x2;
-pub const DISPLAY_VLOW_SHADE: u8 = 0x1;
-pub const DISPLAY_BLACK: u8 = 0x0;
-
-// Font multiplier for large characters
-const FONT_LARGE_MULTIPLIER: usize = 4;
+/// Flushes between full-frame resends, which repair anything a glitch on the
+/// bus (or a tile hash collision) left on the panel
+const FULL_REFRESH_FRAMES: u32 = 256;
 
 // SSD1322 Commands
 const CMD_SET_COMMAND_LOCK: u8 = 0xFD;
@@ -48,15 +38,61 @@ const CMD_SET_COLUMN_ADDR: u8 = 0x15;
 const CMD_SET_ROW_ADDR: u8 = 0x75;
 const CMD_WRITE_RAM: u8 = 0x5C;
 
+/// Rows of controller RAM; the 64 MUX panel shows a window of them starting
+/// at the display start line
+const RAM_ROWS: usize = 128;
+
 const MIN_SEG: u8 = 0x1C;
 const MAX_SEG: u8 = 0x5B;
+// The panel's 256 pixels map onto segment columns MIN_SEG..=MAX_SEG, 4 pixels each
+const _: () = assert!(MIN_SEG as usize + DISPLAY_WIDTH / 4 - 1 == MAX_SEG as usize);
+
+/// Panel drive currents, which set how bright the OLED is
+///
+/// `contrast` is the segment output current (0-255); `master_current` scales
+/// every segment current in 16 steps (0-15).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Brightness {
+    pub contrast: u8,
+    pub master_current: u8,
+}
+
+impl Brightness {
+    /// Full brightness, what the panel was always initialized to
+    pub const DAY: Self = Self::new(0xFF, 0x0F);
+    pub const NIGHT: Self = Self::new(0x60, 0x08);
+    pub const POWER_SAVE: Self = Self::new(0x20, 0x04);
+
+    /// Master current is clamped to its 4 bits
+    pub const fn new(contrast: u8, master_current: u8) -> Self {
+        let master_current = if master_current > 0x0F { 0x0F } else { master_current };
+        Self { contrast, master_current }
+    }
+
+    pub const fn preset(preset: BrightnessPreset) -> Self {
+        match preset {
+            BrightnessPreset::Day => Self::DAY,
+            BrightnessPreset::Night => Self::NIGHT,
+            BrightnessPreset::PowerSave => Self::POWER_SAVE,
+        }
+    }
+}
 
+/// SSD1322 panel on SPI
+///
+/// Owns no pixels: frames are drawn into a `Framebuffer` and handed to
+/// `flush`, which only sends what changed on the panel.
 pub struct Ssd1322Display<'a> {
     spi: Spi<'a, Async>,
     dc: Output<'a>,
     cs: Output<'a>,
     rst: Output<'a>,
-    framebuffer: [u8; DISPLAY_BUFFER_SIZE],
+    /// What the panel was last sent
+    panel: PanelShadow,
+    flushes: u32,
+    brightness: Brightness,
+    /// Rows the picture is moved down by the display start line
+    shift_y: i32,
 }
 
 impl<'a> Ssd1322Display<'a> {
@@ -71,7 +107,10 @@ impl<'a> Ssd1322Display<'a> {
             dc,
             cs,
             rst,
-            framebuffer: [0; DISPLAY_BUFFER_SIZE],
+            panel: PanelShadow::new(),
+            flushes: 0,
+            brightness: Brightness::DAY,
+            shift_y: 0,
         };
 
         display.init().await;
@@ -117,11 +156,7 @@ impl<'a> Ssd1322Display<'a> {
         self.send_command(CMD_DISPLAY_ENHANCE).await;
         self.send_data(&[0xA0, 0xFD]).await;
 
-        self.send_command(CMD_SET_CONTRAST_CURRENT).await;
-        self.send_data(&[0xFF]).await;
-
-        self.send_command(CMD_MASTER_CURRENT_CONTROL).await;
-        self.send_data(&[0x0F]).await;
+        self.send_brightness().await;
 
         self.send_command(CMD_SELECT_DEFAULT_GRAYSCALE).await;
 
@@ -145,6 +180,8 @@ impl<'a> Ssd1322Display<'a> {
 
         Timer::after_millis(10).await;
 
+        self.clear_offscreen().await;
+
         self.send_command(CMD_DISPLAY_ON).await;
         Timer::after_millis(50).await;
 
@@ -165,157 +202,96 @@ impl<'a> Ssd1322Display<'a> {
         self.cs.set_high();
     }
 
-    pub fn clear(&mut self) {
-        self.framebuffer.fill(0);
+    /// Change the panel drive currents, takes effect immediately
+    pub async fn set_brightness(&mut self, brightness: Brightness) {
+        if brightness != self.brightness {
+            self.brightness = brightness;
+            self.send_brightness().await;
+        }
     }
 
-    pub fn fill(&mut self, shade: u8) {
-        self.framebuffer.fill(shade & 0xF);
+    pub fn brightness(&self) -> Brightness {
+        self.brightness
     }
 
-    /// Draw a pixel at the specified location
-    pub fn draw_pixel(&mut self, x: usize, y: usize, value: u8) {
-        if x < DISPLAY_WIDTH && y < DISPLAY_HEIGHT {
-            self.framebuffer[x + (y * DISPLAY_WIDTH)] = value & 0xF;
+    /// Move the picture `dy` rows down (negative for up), takes effect immediately
+    ///
+    /// The vertical part of the burn-in pixel shift. Moving the display start
+    /// line scrolls the panel's window over controller RAM, so no pixels are
+    /// resent; the rows it brings into view past the frame are kept black by
+    /// `clear_offscreen`.
+    pub async fn set_vertical_shift(&mut self, dy: i32) {
+        if dy != self.shift_y {
+            self.shift_y = dy;
+            // Panel row r shows RAM row (r + start line) mod RAM_ROWS
+            let start_line = (-dy).rem_euclid(RAM_ROWS as i32) as u8;
+            self.send_command(CMD_SET_START_LINE).await;
+            self.send_data(&[start_line]).await;
         }
     }
 
-    /// Draw a character using font16
-    pub fn draw_char(&mut self, x: usize, y: usize, fg: u8, bg: u8, ch: char) {
-        let char_idx = ch as usize;
-        if char_idx < 32 || char_idx >= 127 {
-            return; // Outside printable ASCII range
-        }
-        
-        let data = FONT16_CHAR_ADDR[char_idx - 32];
-        
-        let mut byte_count = 0;
-        for stripe in 0..(FONT16_HEIGHT / 8) {
-            for col in 0..FONT16_WIDTH {
-                for row in 0..8 {
-                    let x_offset = col;
-                    let y_offset = stripe * 8 + row;
-                    let shade = if data[byte_count] & (1 << row) != 0 {
-                        fg
-                    } else {
-                        bg
-                    };
-                    self.draw_pixel(x + x_offset, y + y_offset, shade);
-                }
-                byte_count += 1;
-            }
+    /// Blank the controller RAM rows below the frame
+    async fn clear_offscreen(&mut self) {
+        self.send_command(CMD_SET_COLUMN_ADDR).await;
+        self.send_data(&[MIN_SEG, MAX_SEG]).await;
+        self.send_command(CMD_SET_ROW_ADDR).await;
+        self.send_data(&[DISPLAY_HEIGHT as u8, (RAM_ROWS - 1) as u8]).await;
+        self.send_command(CMD_WRITE_RAM).await;
+
+        self.dc.set_high();
+        self.cs.set_low();
+        for _ in DISPLAY_HEIGHT..RAM_ROWS {
+            self.spi.write(&[0u8; ROW_BYTES]).await.ok();
         }
+        self.cs.set_high();
     }
 
-    /// Draw a character using large font (4x scaled font16)
-    pub fn draw_char_large(&mut self, x: usize, y: usize, fg: u8, bg: u8, ch: char) {
-        let char_idx = ch as usize;
-        if char_idx < 32 || char_idx >= 127 {
-            return;
-        }
-        
-        let data = FONT16_CHAR_ADDR[char_idx - 32];
-        
-        let mut byte_count = 0;
-        for stripe in 0..(FONT16_HEIGHT / 8) {
-            for col in 0..FONT16_WIDTH {
-                for row in 0..8 {
-                    let shade = if data[byte_count] & (1 << row) != 0 {
-                        fg
-                    } else {
-                        bg
-                    };
-                    
-                    // Draw scaled pixel (4x4 block)
-                    for scale_y in 0..FONT_LARGE_MULTIPLIER {
-                        for scale_x in 0..FONT_LARGE_MULTIPLIER {
-                            let x_offset = col * FONT_LARGE_MULTIPLIER + scale_x;
-                            let y_offset = (stripe * 8 + row) * FONT_LARGE_MULTIPLIER + scale_y;
-                            self.draw_pixel(x + x_offset, y + y_offset, shade);
-                        }
-                    }
-                }
-                byte_count += 1;
-            }
-        }
+    async fn send_brightness(&mut self) {
+        self.send_command(CMD_SET_CONTRAST_CURRENT).await;
+        self.send_data(&[self.brightness.contrast]).await;
+
+        self.send_command(CMD_MASTER_CURRENT_CONTROL).await;
+        self.send_data(&[self.brightness.master_current]).await;
     }
 
-    /// Draw a string using font16
-    pub fn draw_string(&mut self, x: usize, y: usize, fg: u8, bg: u8, text: &str) {
-        for (i, ch) in text.chars().enumerate() {
-            self.draw_char(x + (FONT16_WIDTH * i), y, fg, bg, ch);
+    /// Send the parts of `frame` that differ from what the panel shows
+    ///
+    /// Returns the number of pixel bytes sent.
+    pub async fn flush(&mut self, frame: &mut Framebuffer) -> usize {
+        self.flushes = self.flushes.wrapping_add(1);
+        if self.flushes.is_multiple_of(FULL_REFRESH_FRAMES) {
+            self.panel.invalidate();
         }
-    }
 
-    /// Draw a string using large font
-    pub fn draw_string_large(&mut self, x: usize, y: usize, fg: u8, bg: u8, text: &str) {
-        for (i, ch) in text.chars().enumerate() {
-            self.draw_char_large(x + (FONT16_WIDTH * FONT_LARGE_MULTIPLIER * i), y, fg, bg, ch);
+        let mut sent = 0;
+        for window in frame.take_changes(&mut self.panel) {
+            self.send_window(frame, window).await;
+            sent += window.bytes();
         }
+        sent
     }
 
-    pub async fn flush(&mut self) {
-        // Set column address
+    /// Point the controller's RAM window at `window` and stream its pixels
+    async fn send_window(&mut self, frame: &Framebuffer, window: Window) {
+        let (first_column, last_column) = window.columns();
         self.send_command(CMD_SET_COLUMN_ADDR).await;
-        self.send_data(&[MIN_SEG, MAX_SEG]).await;
+        self.send_data(&[MIN_SEG + first_column, MIN_SEG + last_column]).await;
 
-        // Set row address
+        let (first_row, last_row) = window.rows();
         self.send_command(CMD_SET_ROW_ADDR).await;
-        self.send_data(&[0, 63]).await;
+        self.send_data(&[first_row, last_row]).await;
 
-        // Write RAM command
         self.send_command(CMD_WRITE_RAM).await;
 
-        // Pack and send framebuffer data (2 pixels per byte, 4-bit each)
         self.dc.set_high();
         self.cs.set_low();
-
-        // We need to pack the data on the fly to avoid stack allocation
-        // Send in chunks to avoid large stack allocation
-        const CHUNK_SIZE: usize = 256;
-        let mut packed_chunk = [0u8; CHUNK_SIZE / 2];
-
-        for chunk_start in (0..DISPLAY_BUFFER_SIZE).step_by(CHUNK_SIZE) {
-            let chunk_end = (chunk_start + CHUNK_SIZE).min(DISPLAY_BUFFER_SIZE);
-            let chunk_len = chunk_end - chunk_start;
-
-            for i in (0..chunk_len).step_by(2) {
-                let idx = chunk_start + i;
-                packed_chunk[i / 2] = (self.framebuffer[idx] << 4) | self.framebuffer[idx + 1];
+        if window.full_width() {
+            self.spi.write(frame.rows_bytes(&window)).await.ok();
+        } else {
+            for y in window.y..window.y + window.height {
+                self.spi.write(frame.row_bytes(&window, y)).await.ok();
             }
-
-            self.spi.write(&packed_chunk[..chunk_len / 2]).await.ok();
         }
-
         self.cs.set_high();
     }
 }
-
-impl<'a> DrawTarget for Ssd1322Display<'a> {
-    type Color = Gray4;
-    type Error = core::convert::Infallible;
-
-    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
-    where
-        I: IntoIterator<Item = Pixel<Self::Color>>,
-    {
-        for Pixel(coord, color) in pixels.into_iter() {
-            if coord.x >= 0
-                && coord.x < DISPLAY_WIDTH as i32
-                && coord.y >= 0
-                && coord.y < DISPLAY_HEIGHT as i32
-            {
-                let x = coord.x as usize;
-                let y = coord.y as usize;
-                self.framebuffer[x + y * DISPLAY_WIDTH] = color.luma();
-            }
-        }
-        Ok(())
-    }
-}
-
-impl<'a> OriginDimensions for Ssd1322Display<'a> {
-    fn size(&self) -> Size {
-        Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32)
-    }
-}
\ No newline at end of file
@@ -0,0 +1,581 @@
+//! Reusable display widgets
+//!
+//! Widgets draw through `DrawTarget<Color = Gray4>` into an area handed out
+//! by `layout`, and work out their own pixel positions inside it (alignment,
+//! centering, fill ratios). Text uses the font16 glyphs, optionally scaled 4x
+//! for the large readouts; the small labels in timeout boxes use
+//! embedded-graphics' 4x6 font.
+//!
+//! Drawing a widget only touches pixels inside its area, except text that is
+//! wider than the area, which is clipped by the target instead.
+
+use core::fmt::Write;
+use embedded_graphics::geometry::AnchorPoint;
+use embedded_graphics::mono_font::ascii::FONT_4X6;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Line, PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+use heapless::String;
+
+use super::font16::{FONT16_CHAR_ADDR, FONT_HEIGHT, FONT_WIDTH};
+use super::geometry::{DISPLAY_BLACK, DISPLAY_LOW_SHADE, DISPLAY_MID_SHADE, DISPLAY_VLOW_SHADE, DISPLAY_WHITE};
+
+pub const WHITE: Gray4 = Gray4::new(DISPLAY_WHITE);
+pub const MID: Gray4 = Gray4::new(DISPLAY_MID_SHADE);
+pub const LOW: Gray4 = Gray4::new(DISPLAY_LOW_SHADE);
+pub const VLOW: Gray4 = Gray4::new(DISPLAY_VLOW_SHADE);
+pub const BLACK: Gray4 = Gray4::new(DISPLAY_BLACK);
+
+/// Scale factor of `TextSize::Large`
+const LARGE_SCALE: u32 = 4;
+
+/// Something that can be drawn into a layout area
+pub trait Widget {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>;
+}
+
+/// font16 at 12x16, or scaled 4x to 48x64
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextSize {
+    Normal,
+    Large,
+}
+
+impl TextSize {
+    fn scale(self) -> u32 {
+        match self {
+            TextSize::Normal => 1,
+            TextSize::Large => LARGE_SCALE,
+        }
+    }
+}
+
+/// Horizontal placement of text within its area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Colours, size and alignment of a piece of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextStyle {
+    pub fg: Gray4,
+    pub bg: Gray4,
+    pub size: TextSize,
+    pub align: Align,
+}
+
+impl TextStyle {
+    /// Normal size, left aligned, on black
+    pub const fn new(fg: Gray4) -> Self {
+        Self {
+            fg,
+            bg: BLACK,
+            size: TextSize::Normal,
+            align: Align::Left,
+        }
+    }
+
+    pub const fn large(mut self) -> Self {
+        self.size = TextSize::Large;
+        self
+    }
+
+    pub const fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Swap foreground and background
+    pub const fn inverted(self) -> Self {
+        Self {
+            fg: self.bg,
+            bg: self.fg,
+            ..self
+        }
+    }
+}
+
+/// Draw `text` in font16, aligned within `area`
+fn draw_text<D>(target: &mut D, area: Rectangle, text: &str, style: TextStyle) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Gray4>,
+{
+    let scale = style.size.scale();
+    let char_width = FONT_WIDTH as u32 * scale;
+    let width = text.chars().count() as u32 * char_width;
+    let x = match style.align {
+        Align::Left => 0,
+        Align::Center => (area.size.width as i32 - width as i32) / 2,
+        Align::Right => area.size.width as i32 - width as i32,
+    };
+
+    let mut origin = area.top_left + Point::new(x, 0);
+    for ch in text.chars() {
+        draw_glyph(target, origin, ch, style.fg, style.bg, scale)?;
+        origin.x += char_width as i32;
+    }
+    Ok(())
+}
+
+fn draw_glyph<D>(target: &mut D, origin: Point, ch: char, fg: Gray4, bg: Gray4, scale: u32) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Gray4>,
+{
+    let index = ch as usize;
+    if !(32..127).contains(&index) {
+        return Ok(()); // Outside printable ASCII range
+    }
+    let data = FONT16_CHAR_ADDR[index - 32];
+
+    // Glyphs are stored as 8-row stripes, one byte per column, LSB at the top
+    let pixels = (0..FONT_HEIGHT).flat_map(move |y| {
+        (0..FONT_WIDTH).map(move |x| {
+            let on = data[(y / 8) * FONT_WIDTH + x] & (1 << (y % 8)) != 0;
+            (Point::new(x as i32, y as i32), if on { fg } else { bg })
+        })
+    });
+
+    if scale == 1 {
+        target.draw_iter(pixels.map(|(point, color)| Pixel(origin + point, color)))
+    } else {
+        for (point, color) in pixels {
+            let block = Rectangle::new(origin + point * scale as i32, Size::new(scale, scale));
+            target.fill_solid(&block, color)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fixed text
+#[derive(Debug, Clone, Copy)]
+pub struct Label<'a> {
+    pub text: &'a str,
+    pub style: TextStyle,
+}
+
+impl<'a> Label<'a> {
+    pub const fn new(text: &'a str, style: TextStyle) -> Self {
+        Self { text, style }
+    }
+}
+
+impl Widget for Label<'_> {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        draw_text(target, area, self.text, self.style)
+    }
+}
+
+/// A number with a fixed width and precision, followed by its unit
+///
+/// The value is right aligned in `width` characters (`{:width.precision}`),
+/// so readouts don't jump around as the digits change.
+#[derive(Debug, Clone, Copy)]
+pub struct Readout<'a> {
+    pub value: f32,
+    pub width: usize,
+    pub precision: usize,
+    pub unit: &'a str,
+    pub style: TextStyle,
+}
+
+impl<'a> Readout<'a> {
+    /// One decimal place, five characters wide
+    pub const fn new(value: f32, unit: &'a str, style: TextStyle) -> Self {
+        Self {
+            value,
+            width: 5,
+            precision: 1,
+            unit,
+            style,
+        }
+    }
+
+    pub const fn digits(mut self, width: usize, precision: usize) -> Self {
+        self.width = width;
+        self.precision = precision;
+        self
+    }
+}
+
+impl Widget for Readout<'_> {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        let mut text: String<24> = String::new();
+        write!(&mut text, "{:w$.p$}{}", self.value, self.unit, w = self.width, p = self.precision).ok();
+        draw_text(target, area, &text, self.style)
+    }
+}
+
+/// Horizontal bar filled left to right
+#[derive(Debug, Clone, Copy)]
+pub struct BarGauge {
+    /// Fill level, clamped to 0.0 - 1.0
+    pub fraction: f32,
+    pub fill: Gray4,
+    /// Outline shade, None for a bare bar
+    pub outline: Option<Gray4>,
+}
+
+impl BarGauge {
+    pub const fn new(fraction: f32) -> Self {
+        Self {
+            fraction,
+            fill: MID,
+            outline: Some(WHITE),
+        }
+    }
+
+    pub const fn fill(mut self, fill: Gray4) -> Self {
+        self.fill = fill;
+        self
+    }
+}
+
+impl Widget for BarGauge {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        let inner = match self.outline {
+            Some(_) => area.offset(-1),
+            None => area,
+        };
+        let width = (inner.size.width as f32 * self.fraction.clamp(0.0, 1.0)) as u32;
+        target.fill_solid(&Rectangle::new(inner.top_left, Size::new(width, inner.size.height)), self.fill)?;
+
+        if let Some(outline) = self.outline {
+            area.into_styled(PrimitiveStyle::with_stroke(outline, 1)).draw(target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Link watchdog box: fills up as a message goes overdue, crossed out once
+/// it has timed out
+#[derive(Debug, Clone, Copy)]
+pub struct TimeoutBox<'a> {
+    pub label: &'a str,
+    pub elapsed_ms: u32,
+    pub timeout_ms: u32,
+}
+
+impl<'a> TimeoutBox<'a> {
+    pub const fn new(label: &'a str, elapsed_ms: u32, timeout_ms: u32) -> Self {
+        Self {
+            label,
+            elapsed_ms,
+            timeout_ms,
+        }
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.elapsed_ms >= self.timeout_ms
+    }
+}
+
+impl Widget for TimeoutBox<'_> {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        let dead = self.timed_out();
+        let fraction = if dead {
+            1.0
+        } else {
+            self.elapsed_ms as f32 / self.timeout_ms as f32
+        };
+        let bar = BarGauge {
+            fraction,
+            fill: if dead { VLOW } else { MID },
+            outline: None,
+        };
+        bar.draw(target, area.offset(-1))?;
+
+        if dead {
+            let style = PrimitiveStyle::with_stroke(MID, 1);
+            let Some(bottom_right) = area.bottom_right() else {
+                return Ok(());
+            };
+            let top_right = Point::new(bottom_right.x, area.top_left.y);
+            let bottom_left = Point::new(area.top_left.x, bottom_right.y);
+            Line::new(area.top_left, bottom_right).into_styled(style).draw(target)?;
+            Line::new(bottom_left, top_right).into_styled(style).draw(target)?;
+        }
+        area.into_styled(PrimitiveStyle::with_stroke(WHITE, 1)).draw(target)?;
+
+        let text_style = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+        Text::with_text_style(self.label, area.center(), MonoTextStyle::new(&FONT_4X6, WHITE), text_style)
+            .draw(target)?;
+        Ok(())
+    }
+}
+
+/// 1-bit icon bitmap, one `u16` per row with the leftmost pixel in the
+/// highest used bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IconImage {
+    pub width: u32,
+    pub rows: &'static [u16],
+}
+
+impl IconImage {
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.rows.len() as u32)
+    }
+
+    fn pixels(&self) -> impl Iterator<Item = Point> + '_ {
+        let width = self.width;
+        self.rows.iter().enumerate().flat_map(move |(y, row)| {
+            (0..width)
+                .filter(move |x| row & (1 << (width - 1 - x)) != 0)
+                .map(move |x| Point::new(x as i32, y as i32))
+        })
+    }
+}
+
+/// Padlock, 12x15
+pub const LOCK_ICON: IconImage = IconImage {
+    width: 12,
+    rows: &[
+        0b0000_1111_0000,
+        0b0000_1111_0000,
+        0b0011_0000_1100,
+        0b0011_0000_1100,
+        0b0011_0000_1100,
+        0b1111_1111_1111,
+        0b1111_1111_1111,
+        0b1100_0000_0011,
+        0b1100_0000_0011,
+        0b1100_0000_0011,
+        0b1100_0000_0011,
+        0b1100_0000_0011,
+        0b1100_0000_0011,
+        0b1111_1111_1111,
+        0b1111_1111_1111,
+    ],
+};
+
+/// Left turn arrow, 9x9
+pub const LEFT_ARROW_ICON: IconImage = IconImage {
+    width: 9,
+    rows: &[
+        0b0_0001_0000,
+        0b0_0011_0000,
+        0b0_0111_1111,
+        0b0_1111_1111,
+        0b1_1111_1111,
+        0b0_1111_1111,
+        0b0_0111_1111,
+        0b0_0011_0000,
+        0b0_0001_0000,
+    ],
+};
+
+/// Right turn arrow, 9x9
+pub const RIGHT_ARROW_ICON: IconImage = IconImage {
+    width: 9,
+    rows: &[
+        0b0_0001_0000,
+        0b0_0001_1000,
+        0b1_1111_1100,
+        0b1_1111_1110,
+        0b1_1111_1111,
+        0b1_1111_1110,
+        0b1_1111_1100,
+        0b0_0001_1000,
+        0b0_0001_0000,
+    ],
+};
+
+/// Single-shade icon, centered in its area
+#[derive(Debug, Clone, Copy)]
+pub struct Icon {
+    pub image: &'static IconImage,
+    pub shade: Gray4,
+}
+
+impl Icon {
+    pub const fn new(image: &'static IconImage, shade: Gray4) -> Self {
+        Self { image, shade }
+    }
+}
+
+impl Widget for Icon {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        let origin = area.resized(self.image.size(), AnchorPoint::Center).top_left;
+        target.draw_iter(self.image.pixels().map(|point| Pixel(origin + point, self.shade)))
+    }
+}
+
+/// What a `BlinkIndicator` does in the off half of its period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlinkMode {
+    /// Not drawn at all
+    Hide,
+    /// Drawn with foreground and background swapped
+    Invert,
+}
+
+/// Label that blinks with a fixed period while active
+///
+/// The phase comes from `now_ms`, so every indicator with the same period
+/// blinks in step.
+#[derive(Debug, Clone, Copy)]
+pub struct BlinkIndicator<'a> {
+    pub label: Label<'a>,
+    pub period_ms: u32,
+    pub mode: BlinkMode,
+    pub now_ms: u32,
+}
+
+impl<'a> BlinkIndicator<'a> {
+    pub const fn new(label: Label<'a>, period_ms: u32, mode: BlinkMode, now_ms: u32) -> Self {
+        Self {
+            label,
+            period_ms,
+            mode,
+            now_ms,
+        }
+    }
+
+    /// True during the first half of each period
+    pub fn lit(&self) -> bool {
+        self.now_ms % self.period_ms < self.period_ms / 2
+    }
+}
+
+impl Widget for BlinkIndicator<'_> {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        match (self.lit(), self.mode) {
+            (true, _) => self.label.draw(target, area),
+            (false, BlinkMode::Invert) => {
+                Label::new(self.label.text, self.label.style.inverted()).draw(target, area)
+            }
+            (false, BlinkMode::Hide) => Ok(()),
+        }
+    }
+}
+
+/// Row of single-letter options with the selected one highlighted
+#[derive(Debug, Clone, Copy)]
+pub struct Selector<'a> {
+    pub options: &'a str,
+    pub selected: usize,
+    /// Shade of the highlight behind the selected option
+    pub highlight: Gray4,
+    /// Shade of the other options
+    pub dim: Gray4,
+}
+
+impl<'a> Selector<'a> {
+    pub const fn new(options: &'a str, selected: usize) -> Self {
+        Self {
+            options,
+            selected,
+            highlight: MID,
+            dim: LOW,
+        }
+    }
+}
+
+impl Widget for Selector<'_> {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        let mut origin = area.top_left;
+        for (i, ch) in self.options.chars().enumerate() {
+            let (fg, bg) = if i == self.selected {
+                (BLACK, self.highlight)
+            } else {
+                (self.dim, BLACK)
+            };
+            draw_glyph(target, origin, ch, fg, bg, 1)?;
+            origin.x += FONT_WIDTH as i32;
+        }
+        Ok(())
+    }
+}
+
+/// Another widget drawn at reduced brightness while `dimmed` is set
+///
+/// For decorations that sit at the same shade in the same spot for hours
+/// (icons, box outlines, indicator letters). Every lit shade is halved but
+/// kept at least `VLOW`, so nothing disappears.
+#[derive(Debug, Clone, Copy)]
+pub struct Dimmed<W> {
+    pub widget: W,
+    pub dimmed: bool,
+}
+
+impl<W: Widget> Dimmed<W> {
+    pub const fn new(widget: W, dimmed: bool) -> Self {
+        Self { widget, dimmed }
+    }
+}
+
+impl<W: Widget> Widget for Dimmed<W> {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        if self.dimmed {
+            self.widget.draw(&mut DimTarget(target), area)
+        } else {
+            self.widget.draw(target, area)
+        }
+    }
+}
+
+/// Passes drawing through to the wrapped target with every shade dimmed
+struct DimTarget<'a, D>(&'a mut D);
+
+impl<D: DrawTarget<Color = Gray4>> DrawTarget for DimTarget<'_, D> {
+    type Color = Gray4;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Gray4>>,
+    {
+        self.0.draw_iter(pixels.into_iter().map(|Pixel(point, shade)| Pixel(point, dim(shade))))
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, shade: Gray4) -> Result<(), Self::Error> {
+        self.0.fill_solid(area, dim(shade))
+    }
+}
+
+impl<D: DrawTarget<Color = Gray4>> Dimensions for DimTarget<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.0.bounding_box()
+    }
+}
+
+fn dim(shade: Gray4) -> Gray4 {
+    match shade.luma() {
+        0 => shade,
+        luma => Gray4::new((luma / 2).max(DISPLAY_VLOW_SHADE)),
+    }
+}
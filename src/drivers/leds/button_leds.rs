@@ -0,0 +1,79 @@
+//! Button backlight LEDs (active low)
+
+use defmt::info;
+use embassy_stm32::gpio::Pin;
+use embassy_stm32::Peri;
+use embassy_time::{Duration, Instant};
+
+use super::{Led, LedPattern};
+use crate::drivers::buttons::ButtonId;
+
+/// LED behind a button
+pub struct ButtonLed {
+    pub id: ButtonId,
+    pub led: Led,
+}
+
+impl ButtonLed {
+    pub fn new<P: Pin>(id: ButtonId, pin: Peri<'static, P>) -> Self {
+        Self {
+            id,
+            led: Led::active_low(pin),
+        }
+    }
+}
+
+/// All button LEDs
+pub struct ButtonLeds {
+    leds: heapless::Vec<ButtonLed, 16>,
+}
+
+impl ButtonLeds {
+    /// Initialize button LEDs from a list, mirroring `ButtonInputs::new`
+    pub fn new(leds: impl IntoIterator<Item = ButtonLed>) -> Self {
+        info!("Initializing button LEDs");
+
+        let mut led_vec = heapless::Vec::new();
+        for led in leds {
+            let _ = led_vec.push(led);
+        }
+
+        Self { leds: led_vec }
+    }
+
+    fn get_mut(&mut self, id: ButtonId) -> Option<&mut Led> {
+        self.leds.iter_mut().find(|b| b.id == id).map(|b| &mut b.led)
+    }
+
+    /// Set the pattern of a button's LED
+    pub fn set(&mut self, id: ButtonId, pattern: LedPattern, now: Instant) {
+        if let Some(led) = self.get_mut(id) {
+            led.set_pattern(pattern, now);
+        }
+    }
+
+    /// One-shot flash of a button's LED
+    pub fn flash(&mut self, id: ButtonId, duration: Duration, now: Instant) {
+        if let Some(led) = self.get_mut(id) {
+            led.flash(duration, now);
+        }
+    }
+
+    /// Bitmask of LEDs with a lit pattern, bit N = ButtonId variant N
+    pub fn lit_mask(&self) -> u32 {
+        self.leds
+            .iter()
+            .filter(|b| b.led.pattern().is_lit())
+            .fold(0, |mask, b| mask | (1 << b.id as u32))
+    }
+
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Led> {
+        self.leds.iter().map(|b| &b.led)
+    }
+
+    pub fn refresh(&mut self, now: Instant, phase: u8) {
+        for button in self.leds.iter_mut() {
+            button.led.refresh(now, phase);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Button and status LED outputs
+//!
+//! Owns all 14 LEDs on the steering wheel: the 10 active-low button LEDs and
+//! the 4 status LEDs on PD8-PD11. Every LED runs a `LedPattern` and can be
+//! flashed on top of it for a one-shot acknowledgement. Brightness is produced
+//! by software PWM, so while `Leds::needs_pwm` `Leds::refresh` must be called
+//! at a fixed, fast rate; otherwise every LED is plain on or off and only
+//! needs refreshing at `Leds::next_change`.
+//!
+//! # Module Structure
+//!
+//! - `pattern` - Pattern definitions and PWM brightness (pure functions)
+//! - `button_leds` - The 10 button backlight LEDs
+//! - `status_leds` - White/blue/green/red status LEDs
+//!
+//! # Usage
+//!
+//! ```no_run
+//! let mut leds = Leds::new(button_leds, StatusLeds::new(p.PD8, p.PD9, p.PD10, p.PD11));
+//! leds.status.set(StatusLed::Fault, LedPattern::Blink { period_ms: 250 }, now);
+//!
+//! loop {
+//!     let now = Instant::now();
+//!     if leds.needs_pwm(now) {
+//!         leds.refresh(now, phase);
+//!         phase = (phase + 1) % PWM_STEPS;
+//!         Timer::after_micros(500).await;
+//!     } else {
+//!         leds.refresh(now, 0);
+//!         Timer::at(leds.next_change(now).unwrap_or(now + UPDATE_PERIOD)).await;
+//!     }
+//! }
+//! ```
+
+mod button_leds;
+mod pattern;
+mod status_leds;
+
+pub use button_leds::{ButtonLed, ButtonLeds};
+pub use pattern::{pwm_on, LedPattern, PWM_STEPS};
+pub use status_leds::{StatusLed, StatusLeds};
+
+use embassy_stm32::gpio::{Level, Output, Pin, Speed};
+use embassy_stm32::Peri;
+use embassy_time::{Duration, Instant};
+
+/// A single LED output running a pattern
+pub struct Led {
+    pin: Output<'static>,
+    active_low: bool,
+    pattern: LedPattern,
+    pattern_start: Instant,
+    flash_until: Option<Instant>,
+}
+
+impl Led {
+    /// LED lit by driving the pin low (button LEDs)
+    pub fn active_low<P: Pin>(pin: Peri<'static, P>) -> Self {
+        Self::new(Output::new(pin, Level::High, Speed::Low), true)
+    }
+
+    /// LED lit by driving the pin high (status LEDs)
+    pub fn active_high<P: Pin>(pin: Peri<'static, P>) -> Self {
+        Self::new(Output::new(pin, Level::Low, Speed::Low), false)
+    }
+
+    fn new(pin: Output<'static>, active_low: bool) -> Self {
+        Self {
+            pin,
+            active_low,
+            pattern: LedPattern::Off,
+            pattern_start: Instant::from_ticks(0),
+            flash_until: None,
+        }
+    }
+
+    pub fn pattern(&self) -> LedPattern {
+        self.pattern
+    }
+
+    /// Switch pattern; the pattern restarts only if it actually changed
+    pub fn set_pattern(&mut self, pattern: LedPattern, now: Instant) {
+        if pattern != self.pattern {
+            self.pattern = pattern;
+            self.pattern_start = now;
+        }
+    }
+
+    /// Light the LED fully for `duration`, then fall back to the pattern
+    pub fn flash(&mut self, duration: Duration, now: Instant) {
+        self.flash_until = Some(now + duration);
+    }
+
+    fn flashing(&self, now: Instant) -> bool {
+        self.flash_until.is_some_and(|until| now < until)
+    }
+
+    /// True while the LED needs software PWM
+    pub fn needs_pwm(&self, now: Instant) -> bool {
+        !self.flashing(now) && self.pattern.needs_pwm()
+    }
+
+    /// When the LED next turns on or off, `None` if it stays as it is
+    pub fn next_change(&self, now: Instant) -> Option<Instant> {
+        match self.flash_until {
+            Some(until) if now < until => Some(until),
+            _ => {
+                let elapsed = now.saturating_duration_since(self.pattern_start).as_millis();
+                self.pattern
+                    .next_change(elapsed as u32)
+                    .map(|ms| now + Duration::from_millis(ms as u64))
+            }
+        }
+    }
+
+    /// Drive the pin for PWM step `phase`
+    pub fn refresh(&mut self, now: Instant, phase: u8) {
+        let brightness = match self.flash_until {
+            Some(until) if now < until => PWM_STEPS,
+            _ => {
+                self.flash_until = None;
+                let elapsed = now.saturating_duration_since(self.pattern_start).as_millis();
+                self.pattern.brightness(elapsed as u32)
+            }
+        };
+
+        let lit = pwm_on(brightness, phase);
+        self.pin.set_level(if lit != self.active_low { Level::High } else { Level::Low });
+    }
+}
+
+/// Every LED on the steering wheel
+pub struct Leds {
+    pub buttons: ButtonLeds,
+    pub status: StatusLeds,
+}
+
+impl Leds {
+    pub fn new(buttons: ButtonLeds, status: StatusLeds) -> Self {
+        Self { buttons, status }
+    }
+
+    /// Drive all LED pins for PWM step `phase`
+    pub fn refresh(&mut self, now: Instant, phase: u8) {
+        self.buttons.refresh(now, phase);
+        self.status.refresh(now, phase);
+    }
+
+    /// True while any LED needs software PWM
+    pub fn needs_pwm(&self, now: Instant) -> bool {
+        self.iter().any(|led| led.needs_pwm(now))
+    }
+
+    /// When any LED next turns on or off, `None` if none will
+    pub fn next_change(&self, now: Instant) -> Option<Instant> {
+        self.iter().filter_map(|led| led.next_change(now)).min()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Led> {
+        self.buttons.iter().chain(self.status.iter())
+    }
+}
@@ -0,0 +1,111 @@
+//! LED patterns evaluated as software PWM brightness levels
+//!
+//! Only `Breathe` needs levels between off and on; the others are plain
+//! on/off, so the LEDs only need refreshing when they switch.
+
+/// Number of software PWM steps per period (brightness 0..=PWM_STEPS)
+pub const PWM_STEPS: u8 = 16;
+
+/// What an LED should be doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum LedPattern {
+    Off,
+    /// Fully on
+    Solid,
+    /// On for the first half of every `period_ms`
+    Blink { period_ms: u32 },
+    /// Fades up and down once every `period_ms`
+    Breathe { period_ms: u32 },
+}
+
+impl LedPattern {
+    /// Brightness `elapsed_ms` after the pattern started (0..=PWM_STEPS)
+    pub fn brightness(self, elapsed_ms: u32) -> u8 {
+        match self {
+            LedPattern::Off => 0,
+            LedPattern::Solid => PWM_STEPS,
+            LedPattern::Blink { period_ms } => {
+                if period_ms == 0 || elapsed_ms % period_ms < period_ms / 2 {
+                    PWM_STEPS
+                } else {
+                    0
+                }
+            }
+            LedPattern::Breathe { period_ms } => {
+                if period_ms == 0 {
+                    return PWM_STEPS;
+                }
+                // Triangle wave 0 -> 1 -> 0, squared so the fade looks even to the eye
+                let phase = (elapsed_ms % period_ms) as f32 / period_ms as f32;
+                let ramp = 1.0 - (2.0 * phase - 1.0).abs();
+                (ramp * ramp * PWM_STEPS as f32 + 0.5) as u8
+            }
+        }
+    }
+
+    /// True if the pattern lights the LED at all
+    pub fn is_lit(self) -> bool {
+        self != LedPattern::Off
+    }
+
+    /// True if the pattern needs brightness levels between off and fully on
+    pub fn needs_pwm(self) -> bool {
+        matches!(self, LedPattern::Breathe { period_ms } if period_ms != 0)
+    }
+
+    /// Milliseconds from `elapsed_ms` until the LED next turns on or off,
+    /// `None` if it never does
+    ///
+    /// A breathing LED changes all the time, so it reports 1ms.
+    pub fn next_change(self, elapsed_ms: u32) -> Option<u32> {
+        match self {
+            LedPattern::Off | LedPattern::Solid => None,
+            LedPattern::Blink { period_ms: 0 } | LedPattern::Breathe { period_ms: 0 } => None,
+            LedPattern::Blink { period_ms } => {
+                let into = elapsed_ms % period_ms;
+                let half = period_ms / 2;
+                Some(if into < half { half - into } else { period_ms - into })
+            }
+            LedPattern::Breathe { .. } => Some(1),
+        }
+    }
+}
+
+/// Whether an LED at `brightness` is on during PWM step `phase` (0..PWM_STEPS)
+pub fn pwm_on(brightness: u8, phase: u8) -> bool {
+    brightness > phase
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_breathing_needs_pwm() {
+        assert!(LedPattern::Breathe { period_ms: 2000 }.needs_pwm());
+        assert!(!LedPattern::Breathe { period_ms: 0 }.needs_pwm());
+        assert!(!LedPattern::Off.needs_pwm());
+        assert!(!LedPattern::Solid.needs_pwm());
+        assert!(!LedPattern::Blink { period_ms: 250 }.needs_pwm());
+    }
+
+    #[test]
+    fn blink_changes_at_each_half_period() {
+        let blink = LedPattern::Blink { period_ms: 250 };
+        assert_eq!(blink.next_change(0), Some(125));
+        assert_eq!(blink.next_change(100), Some(25));
+        assert_eq!(blink.next_change(125), Some(125));
+        assert_eq!(blink.next_change(240), Some(10));
+        assert_eq!(blink.next_change(250), Some(125));
+        // The reported edge is where the brightness really flips
+        assert_eq!(blink.brightness(124), PWM_STEPS);
+        assert_eq!(blink.brightness(125), 0);
+    }
+
+    #[test]
+    fn steady_patterns_never_change() {
+        assert_eq!(LedPattern::Off.next_change(10), None);
+        assert_eq!(LedPattern::Solid.next_change(10), None);
+        assert_eq!(LedPattern::Blink { period_ms: 0 }.next_change(10), None);
+    }
+}
@@ -0,0 +1,63 @@
+//! System status LEDs on PD8-PD11
+
+use embassy_stm32::peripherals::{PD10, PD11, PD8, PD9};
+use embassy_stm32::Peri;
+use embassy_time::{Duration, Instant};
+
+use super::{Led, LedPattern};
+
+/// Status LEDs and what they report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum StatusLed {
+    /// White (PD8) - firmware heartbeat
+    Heartbeat,
+    /// Blue (PD9) - Ethernet link
+    Network,
+    /// Green (PD10) - VC/BMS connectivity
+    Nodes,
+    /// Red (PD11) - fault
+    Fault,
+}
+
+/// The 4 status LEDs
+pub struct StatusLeds {
+    leds: [Led; 4],
+}
+
+impl StatusLeds {
+    pub fn new(
+        white: Peri<'static, PD8>,
+        blue: Peri<'static, PD9>,
+        green: Peri<'static, PD10>,
+        red: Peri<'static, PD11>,
+    ) -> Self {
+        Self {
+            leds: [
+                Led::active_high(white),
+                Led::active_high(blue),
+                Led::active_high(green),
+                Led::active_high(red),
+            ],
+        }
+    }
+
+    /// Set the pattern of a status LED
+    pub fn set(&mut self, led: StatusLed, pattern: LedPattern, now: Instant) {
+        self.leds[led as usize].set_pattern(pattern, now);
+    }
+
+    /// One-shot flash of a status LED
+    pub fn flash(&mut self, led: StatusLed, duration: Duration, now: Instant) {
+        self.leds[led as usize].flash(duration, now);
+    }
+
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Led> {
+        self.leds.iter()
+    }
+
+    pub fn refresh(&mut self, now: Instant, phase: u8) {
+        for led in self.leds.iter_mut() {
+            led.refresh(now, phase);
+        }
+    }
+}
@@ -1,6 +1,10 @@
 pub mod buttons;
+pub mod crash;
 pub mod display;
+pub mod leds;
 pub mod network;  // Real network with LAN8742A PHY
+pub mod pedals;
+pub mod storage;
 // pub mod network_sim;  // Simulated network for testing
 pub mod usb;
 
@@ -18,6 +18,8 @@ pub const BMS_PORT: u16 = 2001;
 
 /// Local ports
 pub const RECEIVE_PORT: u16 = 4001;
+/// Source port for everything we send, kept fixed so peers can filter on it
+pub const SEND_PORT: u16 = 4002;
 
 /// Telemetry broadcast
 pub const BROADCAST_ADDRESS: Ipv4Address = Ipv4Address::new(192, 168, 0, 255);
@@ -1,103 +1,196 @@
 /// UDP socket management for vehicle communication
+use core::sync::atomic::{AtomicU32, Ordering};
 use defmt::*;
-use embassy_net::{IpEndpoint, IpListenEndpoint, Stack, udp::{PacketMetadata, UdpSocket}};
+use embassy_net::{IpEndpoint, IpListenEndpoint, Stack, udp::{BindError, PacketMetadata, SendError, UdpSocket}};
+use embassy_time::{with_timeout, Duration};
+use static_cell::StaticCell;
 
 use super::config::{
-    BROADCAST_ADDRESS, BMS_ADDRESS, BMS_PORT, RECEIVE_PORT,
+    BROADCAST_ADDRESS, BMS_ADDRESS, BMS_PORT, RECEIVE_PORT, SEND_PORT,
     TELEMETRY_PORT, VC_ADDRESS, VC_PORT
 };
 
 /// Maximum UDP packet size
 pub const MAX_PACKET_SIZE: usize = 1024;
 
-/// Send a message to the Vehicle Computer
-pub async fn send_to_vc(
-    stack: &'static Stack<'static>,
-    data: &[u8],
-) -> Result<(), embassy_net::udp::SendError> {
-    let mut rx_buffer = [0; MAX_PACKET_SIZE];
-    let mut tx_buffer = [0; MAX_PACKET_SIZE];
-    let mut rx_meta = [PacketMetadata::EMPTY; 1];
-    let mut tx_meta = [PacketMetadata::EMPTY; 1];
-
-    let mut socket = UdpSocket::new(
-        stack.clone(),
-        &mut rx_meta,
-        &mut rx_buffer,
-        &mut tx_meta,
-        &mut tx_buffer,
-    );
-
-    // Bind to any available port (port 0 means any ephemeral port)
-    socket.bind(IpListenEndpoint {
-        addr: None,
-        port: 0,
-    }).ok(); // Ignore error if already bound
-
-    let remote_endpoint = IpEndpoint::new(VC_ADDRESS.into(), VC_PORT);
-
-    debug!("Sending {} bytes to VC at {}", data.len(), remote_endpoint);
-    socket.send_to(data, remote_endpoint).await
+/// Number of queued outgoing packets each destination's send socket can hold
+const TX_QUEUE_DEPTH: usize = 4;
+
+/// Longest a send may wait for room in a destination's queue before the
+/// packet is dropped
+///
+/// A peer whose ARP never resolves (absent on the bench, powered down) keeps
+/// its queue full; this bounds how long the sending task is held up by it.
+const SEND_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Destinations the steering wheel sends to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum Destination {
+    Vc,
+    Bms,
+    Telemetry,
 }
 
-/// Send a message to the Battery Management System
-pub async fn send_to_bms(
-    stack: &'static Stack<'static>,
-    data: &[u8],
-) -> Result<(), embassy_net::udp::SendError> {
-    let mut rx_buffer = [0; MAX_PACKET_SIZE];
-    let mut tx_buffer = [0; MAX_PACKET_SIZE];
-    let mut rx_meta = [PacketMetadata::EMPTY; 1];
-    let mut tx_meta = [PacketMetadata::EMPTY; 1];
-
-    let mut socket = UdpSocket::new(
-        stack.clone(),
-        &mut rx_meta,
-        &mut rx_buffer,
-        &mut tx_meta,
-        &mut tx_buffer,
-    );
+impl Destination {
+    pub const ALL: [Destination; 3] = [Destination::Vc, Destination::Bms, Destination::Telemetry];
+
+    /// Remote endpoint for this destination
+    pub fn endpoint(self) -> IpEndpoint {
+        match self {
+            Destination::Vc => IpEndpoint::new(VC_ADDRESS.into(), VC_PORT),
+            Destination::Bms => IpEndpoint::new(BMS_ADDRESS.into(), BMS_PORT),
+            Destination::Telemetry => IpEndpoint::new(BROADCAST_ADDRESS.into(), TELEMETRY_PORT),
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
 
-    // Bind to any available port (port 0 means any ephemeral port)
-    socket.bind(IpListenEndpoint {
-        addr: None,
-        port: 0,
-    }).ok(); // Ignore error if already bound
+/// Why a packet did not go out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum SendFailure {
+    /// Rejected by the socket
+    Socket(SendError),
+    /// The destination's queue stayed full for `SEND_TIMEOUT`
+    Dropped,
+}
 
-    let remote_endpoint = IpEndpoint::new(BMS_ADDRESS.into(), BMS_PORT);
+/// Send counters for a single destination
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Format)]
+pub struct SendStats {
+    pub sent: u32,
+    pub errors: u32,
+    /// Packets dropped because the destination's queue was full
+    pub dropped: u32,
+}
 
-    debug!("Sending {} bytes to BMS at {}", data.len(), remote_endpoint);
-    socket.send_to(data, remote_endpoint).await
+impl SendStats {
+    /// Packets that did not go out, for whatever reason
+    pub fn failed(&self) -> u32 {
+        self.errors + self.dropped
+    }
 }
 
-/// Broadcast telemetry data
-pub async fn broadcast_telemetry(
-    stack: &'static Stack<'static>,
-    data: &[u8],
-) -> Result<(), embassy_net::udp::SendError> {
-    let mut rx_buffer = [0; MAX_PACKET_SIZE];
-    let mut tx_buffer = [0; MAX_PACKET_SIZE];
-    let mut rx_meta = [PacketMetadata::EMPTY; 1];
-    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+struct DestinationCounters {
+    sent: AtomicU32,
+    errors: AtomicU32,
+    dropped: AtomicU32,
+}
 
-    let mut socket = UdpSocket::new(
-        stack.clone(),
-        &mut rx_meta,
-        &mut rx_buffer,
-        &mut tx_meta,
-        &mut tx_buffer,
-    );
+impl DestinationCounters {
+    const fn new() -> Self {
+        Self {
+            sent: AtomicU32::new(0),
+            errors: AtomicU32::new(0),
+            dropped: AtomicU32::new(0),
+        }
+    }
+}
 
-    // Bind to any available port (port 0 means any ephemeral port)
-    socket.bind(IpListenEndpoint {
-        addr: None,
-        port: 0,
-    }).ok(); // Ignore error if already bound
+/// Long-lived UDP sender shared by all transmitting tasks
+///
+/// Owns one socket per destination, all bound to SEND_PORT, so every packet
+/// leaves from the same source port but a peer that never answers ARP only
+/// fills its own queue. Sending only needs `&self`, so tasks can share one
+/// `&'static UdpSender` without a mutex.
+pub struct UdpSender {
+    sockets: [UdpSocket<'static>; 3],
+    counters: [DestinationCounters; 3],
+}
 
-    let broadcast_endpoint = IpEndpoint::new(BROADCAST_ADDRESS.into(), TELEMETRY_PORT);
+impl UdpSender {
+    /// Send a packet to `destination`, updating its counters
+    ///
+    /// Never waits longer than `SEND_TIMEOUT`; a packet that can't be queued
+    /// by then is dropped.
+    pub async fn send(&self, destination: Destination, data: &[u8]) -> Result<(), SendFailure> {
+        let endpoint = destination.endpoint();
+        debug!("Sending {} bytes to {:?} at {}", data.len(), destination, endpoint);
+
+        let counters = &self.counters[destination.index()];
+        let socket = &self.sockets[destination.index()];
+        let result = match with_timeout(SEND_TIMEOUT, socket.send_to(data, endpoint)).await {
+            Ok(result) => result.map_err(SendFailure::Socket),
+            Err(_) => Err(SendFailure::Dropped),
+        };
+        match result {
+            Ok(()) => counters.sent.fetch_add(1, Ordering::Relaxed),
+            Err(SendFailure::Socket(_)) => counters.errors.fetch_add(1, Ordering::Relaxed),
+            Err(SendFailure::Dropped) => counters.dropped.fetch_add(1, Ordering::Relaxed),
+        };
+        result
+    }
+
+    /// Send a message to the Vehicle Computer
+    pub async fn send_to_vc(&self, data: &[u8]) -> Result<(), SendFailure> {
+        self.send(Destination::Vc, data).await
+    }
+
+    /// Send a message to the Battery Management System
+    pub async fn send_to_bms(&self, data: &[u8]) -> Result<(), SendFailure> {
+        self.send(Destination::Bms, data).await
+    }
+
+    /// Broadcast telemetry data
+    pub async fn broadcast_telemetry(&self, data: &[u8]) -> Result<(), SendFailure> {
+        self.send(Destination::Telemetry, data).await
+    }
+
+    /// Send counters for `destination` since boot
+    pub fn stats(&self, destination: Destination) -> SendStats {
+        let counters = &self.counters[destination.index()];
+        SendStats {
+            sent: counters.sent.load(Ordering::Relaxed),
+            errors: counters.errors.load(Ordering::Relaxed),
+            dropped: counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Log the send counters for every destination
+    pub fn log_stats(&self) {
+        for destination in Destination::ALL {
+            let stats = self.stats(destination);
+            info!(
+                "UDP {:?}: {} sent, {} errors, {} dropped",
+                destination, stats.sent, stats.errors, stats.dropped
+            );
+        }
+    }
+}
 
-    info!("Broadcasting {} bytes of telemetry to {}", data.len(), broadcast_endpoint);
-    socket.send_to(data, broadcast_endpoint).await
+/// Create the shared UDP sender
+///
+/// Call once after the network stack is created. The sockets and their
+/// buffers live in static memory for the lifetime of the firmware.
+pub fn init_sender(stack: &'static Stack<'static>) -> Result<&'static UdpSender, BindError> {
+    static TX_BUFFERS: StaticCell<[[u8; MAX_PACKET_SIZE * TX_QUEUE_DEPTH]; 3]> = StaticCell::new();
+    static TX_META: StaticCell<[[PacketMetadata; TX_QUEUE_DEPTH]; 3]> = StaticCell::new();
+    static SENDER: StaticCell<UdpSender> = StaticCell::new();
+
+    let [vc_buffer, bms_buffer, telemetry_buffer] =
+        TX_BUFFERS.init([[0; MAX_PACKET_SIZE * TX_QUEUE_DEPTH]; 3]);
+    let [vc_meta, bms_meta, telemetry_meta] = TX_META.init([[PacketMetadata::EMPTY; TX_QUEUE_DEPTH]; 3]);
+
+    // Send sockets never receive, so they get no RX buffers
+    let mut sockets = [
+        UdpSocket::new(*stack, &mut [], &mut [], vc_meta, vc_buffer),
+        UdpSocket::new(*stack, &mut [], &mut [], bms_meta, bms_buffer),
+        UdpSocket::new(*stack, &mut [], &mut [], telemetry_meta, telemetry_buffer),
+    ];
+    for socket in &mut sockets {
+        socket.bind(IpListenEndpoint {
+            addr: None,
+            port: SEND_PORT,
+        })?;
+    }
+    info!("UDP send sockets bound to port {}", SEND_PORT);
+
+    Ok(SENDER.init(UdpSender {
+        sockets,
+        counters: [const { DestinationCounters::new() }; 3],
+    }))
 }
 
 /// Create a UDP socket for receiving messages
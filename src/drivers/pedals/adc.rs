@@ -0,0 +1,63 @@
+//! DMA-driven ADC sampling of the throttle and brake pedals
+
+use embassy_stm32::adc::{Adc, Resolution, RingBufferedAdc, SampleTime, Sequence};
+use embassy_stm32::peripherals::{ADC1, DMA2_CH4, PB0, PB1};
+use embassy_stm32::Peri;
+use static_cell::StaticCell;
+
+use super::filter::average_channel;
+use super::PedalCounts;
+
+/// Number of ADC channels in the scan sequence (throttle, brake)
+const CHANNEL_COUNT: usize = 2;
+
+/// Samples per channel averaged into each reading
+const SAMPLES_PER_READ: usize = 8;
+
+/// Samples returned by each DMA read (half the ring buffer)
+const READ_LEN: usize = CHANNEL_COUNT * SAMPLES_PER_READ;
+
+/// Throttle and brake pedal inputs on ADC1
+///
+/// Throttle: PB1 (ADC1_IN9), Brake: PB0 (ADC1_IN8). Both are sampled at
+/// 12 bits with an 84-cycle sample time; DMA2 stream 4 moves the results.
+pub struct PedalInputs {
+    adc: RingBufferedAdc<'static, ADC1>,
+}
+
+impl PedalInputs {
+    pub fn new(
+        adc: Peri<'static, ADC1>,
+        mut throttle_pin: Peri<'static, PB1>,
+        mut brake_pin: Peri<'static, PB0>,
+        dma: Peri<'static, DMA2_CH4>,
+    ) -> Self {
+        static DMA_BUF: StaticCell<[u16; READ_LEN * 2]> = StaticCell::new();
+
+        let mut adc = Adc::new(adc);
+        adc.set_resolution(Resolution::BITS12);
+
+        let mut adc = adc.into_ring_buffered(dma, DMA_BUF.init([0; READ_LEN * 2]));
+        adc.set_sample_sequence(Sequence::One, &mut throttle_pin, SampleTime::CYCLES84);
+        adc.set_sample_sequence(Sequence::Two, &mut brake_pin, SampleTime::CYCLES84);
+
+        Self { adc }
+    }
+
+    /// Capture a block of samples and return the per-channel averages
+    ///
+    /// Conversions are stopped again after each block, so the sample rate is
+    /// set by how often this is called. Returns None on a DMA overrun; the
+    /// next call restarts the ADC.
+    pub async fn read(&mut self) -> Option<PedalCounts> {
+        let mut samples = [0u16; READ_LEN];
+        let result = self.adc.read(&mut samples).await;
+        self.adc.teardown_adc();
+        result.ok()?;
+
+        Some(PedalCounts {
+            throttle: average_channel(&samples, 0, CHANNEL_COUNT),
+            brake: average_channel(&samples, 1, CHANNEL_COUNT),
+        })
+    }
+}
@@ -0,0 +1,261 @@
+//! Pedal calibration and dead zones
+//!
+//! Calibration captures the minimum and maximum ADC counts of each pedal
+//! while the driver sweeps both pedals through their full travel. The
+//! resulting ranges are persisted so they survive a power cycle.
+
+use crate::drivers::buttons::ButtonId;
+
+use super::{PedalCounts, PedalRange};
+
+/// Buttons held together to start and finish calibration
+pub const CALIBRATION_COMBO: [ButtonId; 2] = [ButtonId::PushToTalk, ButtonId::Rearview];
+
+/// Smallest captured span (in ADC counts) accepted as a valid calibration
+pub const MIN_SPAN: u16 = 400;
+
+/// Size of a serialized `PedalCalibration`
+pub const CALIBRATION_SIZE: usize = 9;
+
+/// Serialization format version, bump when the layout changes
+const CALIBRATION_VERSION: u8 = 1;
+
+/// Fractions of pedal travel ignored at each end
+///
+/// Travel below `low` reads as 0.0 and travel above `1.0 - high` reads as
+/// 1.0; everything in between is linearly interpolated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeadZones {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Default for DeadZones {
+    fn default() -> Self {
+        Self { low: 0.05, high: 0.05 }
+    }
+}
+
+/// Apply dead zones to a normalized (0.0 - 1.0) pedal position
+pub fn apply_dead_zones(value: f32, dead_zones: DeadZones) -> f32 {
+    let start = dead_zones.low;
+    let end = 1.0 - dead_zones.high;
+    if end <= start || value <= start {
+        return 0.0;
+    }
+    if value >= end {
+        return 1.0;
+    }
+    (value - start) / (end - start)
+}
+
+/// Calibrated ADC ranges for both pedals
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PedalCalibration {
+    pub throttle: PedalRange,
+    pub brake: PedalRange,
+}
+
+impl PedalCalibration {
+    pub fn to_bytes(&self) -> [u8; CALIBRATION_SIZE] {
+        let mut bytes = [0u8; CALIBRATION_SIZE];
+        bytes[0] = CALIBRATION_VERSION;
+        bytes[1..3].copy_from_slice(&self.throttle.min.to_le_bytes());
+        bytes[3..5].copy_from_slice(&self.throttle.max.to_le_bytes());
+        bytes[5..7].copy_from_slice(&self.brake.min.to_le_bytes());
+        bytes[7..9].copy_from_slice(&self.brake.max.to_le_bytes());
+        bytes
+    }
+
+    /// Parse a stored calibration, rejecting unknown versions and bad ranges
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != CALIBRATION_SIZE || bytes[0] != CALIBRATION_VERSION {
+            return None;
+        }
+        let read = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let calibration = Self {
+            throttle: PedalRange { min: read(1), max: read(3) },
+            brake: PedalRange { min: read(5), max: read(7) },
+        };
+        calibration.is_valid().then_some(calibration)
+    }
+
+    /// Both pedals span at least `MIN_SPAN` counts
+    pub fn is_valid(&self) -> bool {
+        span_ok(self.throttle) && span_ok(self.brake)
+    }
+}
+
+fn span_ok(range: PedalRange) -> bool {
+    range.max > range.min && range.max - range.min >= MIN_SPAN
+}
+
+/// Why a calibration attempt was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum CalibrationError {
+    /// `finish` was called without `start`
+    NotStarted,
+    /// A pedal moved less than `MIN_SPAN` counts during capture
+    RangeTooNarrow,
+}
+
+/// Calibration state machine: Idle -> Capturing -> Idle
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Calibrator {
+    capture: Option<PedalCalibration>,
+}
+
+impl Calibrator {
+    pub const fn new() -> Self {
+        Self { capture: None }
+    }
+
+    /// True while min/max capture is in progress
+    pub fn is_active(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Begin capturing, seeding both ranges from the current readings
+    pub fn start(&mut self, counts: PedalCounts) {
+        self.capture = Some(PedalCalibration {
+            throttle: PedalRange { min: counts.throttle, max: counts.throttle },
+            brake: PedalRange { min: counts.brake, max: counts.brake },
+        });
+    }
+
+    /// Widen the captured ranges with a new reading (ignored when idle)
+    pub fn sample(&mut self, counts: PedalCounts) {
+        if let Some(capture) = &mut self.capture {
+            widen(&mut capture.throttle, counts.throttle);
+            widen(&mut capture.brake, counts.brake);
+        }
+    }
+
+    /// Stop capturing and return the new calibration if it is usable
+    pub fn finish(&mut self) -> Result<PedalCalibration, CalibrationError> {
+        let capture = self.capture.take().ok_or(CalibrationError::NotStarted)?;
+        if capture.is_valid() {
+            Ok(capture)
+        } else {
+            Err(CalibrationError::RangeTooNarrow)
+        }
+    }
+
+    /// Abandon the capture in progress
+    pub fn cancel(&mut self) {
+        self.capture = None;
+    }
+}
+
+fn widen(range: &mut PedalRange, value: u16) {
+    range.min = range.min.min(value);
+    range.max = range.max.max(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(min: u16, max: u16) -> PedalRange {
+        PedalRange { min, max }
+    }
+
+    fn counts(throttle: u16, brake: u16) -> PedalCounts {
+        PedalCounts { throttle, brake }
+    }
+
+    fn calibration() -> PedalCalibration {
+        PedalCalibration {
+            throttle: range(0x0123, 0x0ABC),
+            brake: range(600, 3400),
+        }
+    }
+
+    #[test]
+    fn bytes_are_versioned_little_endian() {
+        let bytes = calibration().to_bytes();
+        assert_eq!(bytes, [CALIBRATION_VERSION, 0x23, 0x01, 0xBC, 0x0A, 0x58, 0x02, 0x48, 0x0D]);
+        assert_eq!(PedalCalibration::from_bytes(&bytes), Some(calibration()));
+    }
+
+    #[test]
+    fn other_versions_and_sizes_are_rejected() {
+        let mut bytes = calibration().to_bytes();
+        bytes[0] = CALIBRATION_VERSION + 1;
+        assert_eq!(PedalCalibration::from_bytes(&bytes), None);
+
+        let bytes = calibration().to_bytes();
+        assert_eq!(PedalCalibration::from_bytes(&bytes[..CALIBRATION_SIZE - 1]), None);
+        assert_eq!(PedalCalibration::from_bytes(&[]), None);
+    }
+
+    #[test]
+    fn narrow_or_inverted_ranges_are_rejected() {
+        let with_throttle = |throttle| PedalCalibration { throttle, ..calibration() };
+        assert!(with_throttle(range(1000, 1000 + MIN_SPAN)).is_valid());
+        assert!(!with_throttle(range(1000, 1000 + MIN_SPAN - 1)).is_valid());
+        assert!(!with_throttle(range(3000, 1000)).is_valid());
+
+        // A stored record with a bad range reads as no calibration
+        let bytes = with_throttle(range(1000, 1100)).to_bytes();
+        assert_eq!(PedalCalibration::from_bytes(&bytes), None);
+    }
+
+    #[test]
+    fn dead_zones_clamp_the_ends_and_rescale_the_middle() {
+        let zones = DeadZones::default();
+        assert_eq!(apply_dead_zones(0.0, zones), 0.0);
+        assert_eq!(apply_dead_zones(0.05, zones), 0.0);
+        assert!((apply_dead_zones(0.5, zones) - 0.5).abs() < 1e-6);
+        assert!((apply_dead_zones(0.275, zones) - 0.25).abs() < 1e-6);
+        assert_eq!(apply_dead_zones(0.95, zones), 1.0);
+        assert_eq!(apply_dead_zones(1.0, zones), 1.0);
+
+        // Zones that leave no travel read as released
+        let overlapping = DeadZones { low: 0.6, high: 0.5 };
+        assert_eq!(apply_dead_zones(0.8, overlapping), 0.0);
+    }
+
+    #[test]
+    fn calibrator_captures_the_swept_ranges() {
+        let mut calibrator = Calibrator::new();
+        // Samples before the start are ignored
+        calibrator.sample(counts(0, 4095));
+        assert!(!calibrator.is_active());
+
+        calibrator.start(counts(500, 600));
+        assert!(calibrator.is_active());
+        for (throttle, brake) in [(3500, 600), (480, 3400), (2000, 620)] {
+            calibrator.sample(counts(throttle, brake));
+        }
+        assert_eq!(
+            calibrator.finish(),
+            Ok(PedalCalibration {
+                throttle: range(480, 3500),
+                brake: range(600, 3400),
+            })
+        );
+        assert!(!calibrator.is_active());
+        assert_eq!(calibrator.finish(), Err(CalibrationError::NotStarted));
+    }
+
+    #[test]
+    fn calibrator_rejects_an_unswept_pedal() {
+        let mut calibrator = Calibrator::new();
+        calibrator.start(counts(500, 600));
+        // Throttle swept, brake barely touched
+        calibrator.sample(counts(3500, 900));
+        assert_eq!(calibrator.finish(), Err(CalibrationError::RangeTooNarrow));
+        assert!(!calibrator.is_active());
+    }
+
+    #[test]
+    fn cancel_abandons_the_capture() {
+        let mut calibrator = Calibrator::new();
+        calibrator.start(counts(500, 600));
+        calibrator.sample(counts(3500, 3400));
+        calibrator.cancel();
+        assert!(!calibrator.is_active());
+        assert_eq!(calibrator.finish(), Err(CalibrationError::NotStarted));
+    }
+}
@@ -0,0 +1,24 @@
+//! Raw pedal samples and count ranges, shared by the ADC driver and the pure
+//! processing stages
+
+use super::filter::ADC_MAX;
+
+/// Raw ADC counts for both pedals
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PedalCounts {
+    pub throttle: u16,
+    pub brake: u16,
+}
+
+/// ADC count range mapped onto 0.0 - 1.0 for one pedal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PedalRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl Default for PedalRange {
+    fn default() -> Self {
+        Self { min: 0, max: ADC_MAX }
+    }
+}
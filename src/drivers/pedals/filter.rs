@@ -0,0 +1,148 @@
+//! Pedal signal processing
+//!
+//! Pure functions with no hardware dependencies, shared by the ADC sampler
+//! and the calibration logic.
+
+/// Filter coefficient used by the C firmware
+pub const DEFAULT_ALPHA: f32 = 0.5;
+
+/// Full-scale reading of the 12-bit ADC
+pub const ADC_MAX: u16 = 4095;
+
+/// One step of a single-pole low-pass IIR filter
+///
+/// y[n] = α·x[n] + (1 - α)·y[n-1]
+pub fn iir_step(previous: f32, sample: f32, alpha: f32) -> f32 {
+    alpha * sample + (1.0 - alpha) * previous
+}
+
+/// Low-pass IIR filter over raw ADC counts
+#[derive(Debug, Clone, Copy)]
+pub struct LowPassFilter {
+    alpha: f32,
+    value: Option<f32>,
+}
+
+impl LowPassFilter {
+    pub const fn new(alpha: f32) -> Self {
+        Self { alpha, value: None }
+    }
+
+    /// Feed a new sample and return the filtered value
+    ///
+    /// The first sample seeds the filter directly so the output does not
+    /// ramp up from zero after boot.
+    pub fn update(&mut self, sample: u16) -> u16 {
+        let sample = sample as f32;
+        let value = match self.value {
+            Some(previous) => iir_step(previous, sample, self.alpha),
+            None => sample,
+        };
+        self.value = Some(value);
+        (value + 0.5) as u16
+    }
+
+    /// Forget the filter history
+    pub fn reset(&mut self) {
+        self.value = None;
+    }
+}
+
+impl Default for LowPassFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_ALPHA)
+    }
+}
+
+/// Linearly map `raw` from `min..=max` onto 0.0..=1.0, clamping outside values
+///
+/// Returns 0.0 if the range is empty or inverted.
+pub fn normalize(raw: u16, min: u16, max: u16) -> f32 {
+    if max <= min {
+        return 0.0;
+    }
+    let clamped = raw.clamp(min, max);
+    (clamped - min) as f32 / (max - min) as f32
+}
+
+/// Average one channel out of a block of interleaved multi-channel samples
+///
+/// `samples` is laid out as [ch0, ch1, ..., ch0, ch1, ...]. Returns 0 if the
+/// block contains no samples for `channel`.
+pub fn average_channel(samples: &[u16], channel: usize, channel_count: usize) -> u16 {
+    let mut sum = 0u32;
+    let mut count = 0u32;
+    for sample in samples.iter().skip(channel).step_by(channel_count) {
+        sum += *sample as u32;
+        count += 1;
+    }
+    if count == 0 {
+        return 0;
+    }
+    ((sum + count / 2) / count) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iir_step_weights_sample_by_alpha() {
+        assert_eq!(iir_step(0.0, 100.0, 0.5), 50.0);
+        assert_eq!(iir_step(100.0, 0.0, 0.25), 75.0);
+        assert_eq!(iir_step(40.0, 80.0, 1.0), 80.0);
+        assert_eq!(iir_step(40.0, 80.0, 0.0), 40.0);
+    }
+
+    #[test]
+    fn filter_seeds_from_first_sample() {
+        let mut filter = LowPassFilter::default();
+        assert_eq!(filter.update(2000), 2000);
+        assert_eq!(filter.update(3000), 2500);
+        assert_eq!(filter.update(3000), 2750);
+    }
+
+    #[test]
+    fn filter_settles_on_a_step() {
+        let mut filter = LowPassFilter::new(0.5);
+        filter.update(0);
+        let outputs = [0; 16].map(|_| filter.update(ADC_MAX));
+        // Rises monotonically and reaches full scale without overshoot
+        assert!(outputs.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(outputs[15], ADC_MAX);
+    }
+
+    #[test]
+    fn filter_reset_forgets_history() {
+        let mut filter = LowPassFilter::default();
+        filter.update(4000);
+        filter.reset();
+        assert_eq!(filter.update(100), 100);
+    }
+
+    #[test]
+    fn normalize_maps_and_clamps() {
+        assert_eq!(normalize(500, 500, 1500), 0.0);
+        assert_eq!(normalize(1000, 500, 1500), 0.5);
+        assert_eq!(normalize(1500, 500, 1500), 1.0);
+        assert_eq!(normalize(100, 500, 1500), 0.0);
+        assert_eq!(normalize(4000, 500, 1500), 1.0);
+        assert_eq!(normalize(ADC_MAX, 0, ADC_MAX), 1.0);
+    }
+
+    #[test]
+    fn normalize_rejects_empty_ranges() {
+        assert_eq!(normalize(1000, 1000, 1000), 0.0);
+        assert_eq!(normalize(1000, 1500, 500), 0.0);
+    }
+
+    #[test]
+    fn average_channel_deinterleaves() {
+        let samples = [10, 100, 20, 200, 31, 300];
+        assert_eq!(average_channel(&samples, 0, 2), 20);
+        assert_eq!(average_channel(&samples, 1, 2), 200);
+        // Rounds to nearest
+        assert_eq!(average_channel(&[1, 2], 0, 1), 2);
+        assert_eq!(average_channel(&[], 0, 2), 0);
+    }
+}
@@ -0,0 +1,89 @@
+//! Throttle and brake pedal inputs
+//!
+//! # Module Structure
+//!
+//! - `adc` - DMA-driven ADC sampling on PB1/PB0
+//! - `calibration` - Min/max capture, dead zones and persistence format
+//! - `counts` - Raw ADC sample pair and calibrated count range
+//! - `filter` - IIR filtering and normalization (pure functions)
+//! - `plausibility` - Open/short, rate and conflict checks with fault latching
+//!
+//! # Usage
+//!
+//! ```no_run
+//! let mut pedals = PedalInputs::new(p.ADC1, p.PB1, p.PB0, p.DMA2_CH4);
+//! let mut processor = PedalProcessor::new();
+//!
+//! if let Some(counts) = pedals.read().await {
+//!     let reading = processor.process(counts);
+//! }
+//! ```
+
+mod adc;
+pub mod calibration;
+mod counts;
+pub mod filter;
+pub mod plausibility;
+
+pub use adc::PedalInputs;
+pub use counts::{PedalCounts, PedalRange};
+pub use calibration::{Calibrator, DeadZones, PedalCalibration};
+pub use plausibility::{PedalFaults, PlausibilityChecker, PlausibilityLimits};
+
+use calibration::apply_dead_zones;
+use filter::{normalize, LowPassFilter};
+
+/// Filtered and normalized pedal values
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PedalReading {
+    /// Filtered throttle ADC counts
+    pub raw_throttle: u16,
+    /// Filtered brake ADC counts
+    pub raw_brake: u16,
+    /// Throttle position (0.0 - 1.0)
+    pub throttle: f32,
+    /// Brake position (0.0 - 1.0)
+    pub brake: f32,
+}
+
+/// Turns raw ADC counts into filtered, normalized pedal readings
+pub struct PedalProcessor {
+    throttle_filter: LowPassFilter,
+    brake_filter: LowPassFilter,
+    pub calibration: PedalCalibration,
+    pub dead_zones: DeadZones,
+}
+
+impl PedalProcessor {
+    pub fn new() -> Self {
+        Self {
+            throttle_filter: LowPassFilter::default(),
+            brake_filter: LowPassFilter::default(),
+            calibration: PedalCalibration::default(),
+            dead_zones: DeadZones::default(),
+        }
+    }
+
+    /// Filter a new pair of ADC readings and normalize them
+    pub fn process(&mut self, counts: PedalCounts) -> PedalReading {
+        let raw_throttle = self.throttle_filter.update(counts.throttle);
+        let raw_brake = self.brake_filter.update(counts.brake);
+
+        PedalReading {
+            raw_throttle,
+            raw_brake,
+            throttle: self.scale(raw_throttle, self.calibration.throttle),
+            brake: self.scale(raw_brake, self.calibration.brake),
+        }
+    }
+
+    fn scale(&self, raw: u16, range: PedalRange) -> f32 {
+        apply_dead_zones(normalize(raw, range.min, range.max), self.dead_zones)
+    }
+}
+
+impl Default for PedalProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,351 @@
+//! Pedal plausibility checking and fault latching
+//!
+//! Catches wiring and sensor faults before they reach the VC:
+//!
+//! - Out of range: a broken wire or short pulls a channel to a rail, far
+//!   outside anything a working pot can produce
+//! - Rate of change: a channel jumping further in one sample than a foot
+//!   can move the pedal
+//! - Pedal conflict: throttle and brake pressed together
+//!
+//! Faults latch. While any fault is latched the throttle is forced to zero
+//! (and the brake too, if its own channel is faulty). The latch only
+//! releases after `release_samples` samples in a row with no fault condition
+//! and the throttle back at rest, so even a one-sample glitch holds the
+//! throttle off for a while, and the car can never lurch forward when a
+//! fault clears.
+//!
+//! Everything here is pure, so the rules can be exercised on the host with
+//! synthetic ADC traces.
+
+use super::PedalCounts;
+
+/// Set of pedal faults, one bit per rule (reported as-is in SW_State)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub struct PedalFaults(u8);
+
+impl PedalFaults {
+    pub const NONE: Self = Self(0);
+    /// Throttle channel outside the valid ADC window (open/short)
+    pub const THROTTLE_RANGE: Self = Self(1 << 0);
+    /// Brake channel outside the valid ADC window (open/short)
+    pub const BRAKE_RANGE: Self = Self(1 << 1);
+    /// Throttle channel changed implausibly fast
+    pub const THROTTLE_RATE: Self = Self(1 << 2);
+    /// Brake channel changed implausibly fast
+    pub const BRAKE_RATE: Self = Self(1 << 3);
+    /// Throttle and brake pressed at the same time
+    pub const PEDAL_CONFLICT: Self = Self(1 << 4);
+
+    /// Faults that make the brake reading untrustworthy
+    const BRAKE_CHANNEL: Self = Self(Self::BRAKE_RANGE.0 | Self::BRAKE_RATE.0);
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Apply the fault policy to a pair of normalized pedal values
+    pub fn limit(self, throttle: f32, brake: f32) -> (f32, f32) {
+        if self.is_empty() {
+            return (throttle, brake);
+        }
+        let brake = if self.intersects(Self::BRAKE_CHANNEL) { 0.0 } else { brake };
+        (0.0, brake)
+    }
+
+    /// Short label for the display, most severe fault first
+    pub fn label(self) -> &'static str {
+        if self.contains(Self::THROTTLE_RANGE) {
+            "THR!"
+        } else if self.contains(Self::BRAKE_RANGE) {
+            "BRK!"
+        } else if self.intersects(Self(Self::THROTTLE_RATE.0 | Self::BRAKE_RATE.0)) {
+            "RATE"
+        } else if self.contains(Self::PEDAL_CONFLICT) {
+            "T+B!"
+        } else {
+            ""
+        }
+    }
+}
+
+impl core::ops::BitOr for PedalFaults {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for PedalFaults {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Thresholds for the plausibility rules
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlausibilityLimits {
+    /// Lowest ADC count a connected pot can produce
+    pub min_counts: u16,
+    /// Highest ADC count a connected pot can produce
+    pub max_counts: u16,
+    /// Largest change in ADC counts allowed between two samples
+    pub max_step: u16,
+    /// Throttle position counted as pressed for the conflict check
+    pub conflict_throttle: f32,
+    /// Brake position counted as pressed for the conflict check
+    pub conflict_brake: f32,
+    /// Throttle must be at or below this to release a latched fault
+    pub release_throttle: f32,
+    /// Fault-free samples with the throttle released needed to clear the latch
+    pub release_samples: u16,
+}
+
+impl Default for PlausibilityLimits {
+    fn default() -> Self {
+        Self {
+            min_counts: 50,
+            max_counts: 4045,
+            // A full sweep in one 20ms sample is not something a foot can do
+            max_step: 1500,
+            conflict_throttle: 0.25,
+            conflict_brake: 0.25,
+            release_throttle: 0.05,
+            // One second at the 20ms sample period
+            release_samples: 50,
+        }
+    }
+}
+
+/// Runs the plausibility rules on every sample and latches faults
+#[derive(Debug, Clone, Copy)]
+pub struct PlausibilityChecker {
+    pub limits: PlausibilityLimits,
+    last: Option<PedalCounts>,
+    latched: PedalFaults,
+    /// Consecutive samples with no fault condition and the throttle released
+    quiet: u16,
+}
+
+impl PlausibilityChecker {
+    pub const fn new(limits: PlausibilityLimits) -> Self {
+        Self {
+            limits,
+            last: None,
+            latched: PedalFaults::NONE,
+            quiet: 0,
+        }
+    }
+
+    /// Currently latched faults
+    pub fn faults(&self) -> PedalFaults {
+        self.latched
+    }
+
+    /// Check one sample and return the latched faults
+    ///
+    /// `counts` are the unfiltered ADC counts; `throttle` and `brake` are
+    /// the normalized positions derived from them.
+    pub fn check(&mut self, counts: PedalCounts, throttle: f32, brake: f32) -> PedalFaults {
+        let active = self.detect(counts, throttle, brake);
+        self.last = Some(counts);
+
+        self.latched |= active;
+        if active.is_empty() && throttle <= self.limits.release_throttle {
+            self.quiet = self.quiet.saturating_add(1);
+        } else {
+            self.quiet = 0;
+        }
+        if self.quiet >= self.limits.release_samples {
+            self.latched = PedalFaults::NONE;
+        }
+        self.latched
+    }
+
+    /// Fault conditions present in this sample, without latching
+    pub fn detect(&self, counts: PedalCounts, throttle: f32, brake: f32) -> PedalFaults {
+        let limits = &self.limits;
+        let mut faults = PedalFaults::NONE;
+
+        let out_of_range = |value: u16| value < limits.min_counts || value > limits.max_counts;
+        if out_of_range(counts.throttle) {
+            faults |= PedalFaults::THROTTLE_RANGE;
+        }
+        if out_of_range(counts.brake) {
+            faults |= PedalFaults::BRAKE_RANGE;
+        }
+
+        if let Some(last) = self.last {
+            if counts.throttle.abs_diff(last.throttle) > limits.max_step {
+                faults |= PedalFaults::THROTTLE_RATE;
+            }
+            if counts.brake.abs_diff(last.brake) > limits.max_step {
+                faults |= PedalFaults::BRAKE_RATE;
+            }
+        }
+
+        if throttle >= limits.conflict_throttle && brake >= limits.conflict_brake {
+            faults |= PedalFaults::PEDAL_CONFLICT;
+        }
+
+        faults
+    }
+}
+
+impl Default for PlausibilityChecker {
+    fn default() -> Self {
+        Self::new(PlausibilityLimits::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::pedals::filter::normalize;
+
+    /// Pot travel used to turn the synthetic counts into pedal positions
+    const REST: u16 = 500;
+    const FULL: u16 = 3500;
+
+    /// Feed a trace of (throttle, brake) ADC counts, returning the latched
+    /// faults after each sample
+    fn run<const N: usize>(checker: &mut PlausibilityChecker, trace: [(u16, u16); N]) -> [PedalFaults; N] {
+        trace.map(|(throttle, brake)| {
+            let counts = PedalCounts { throttle, brake };
+            checker.check(counts, normalize(throttle, REST, FULL), normalize(brake, REST, FULL))
+        })
+    }
+
+    /// `samples` copies of the same reading
+    fn hold(checker: &mut PlausibilityChecker, throttle: u16, brake: u16, samples: u16) -> PedalFaults {
+        let mut faults = checker.faults();
+        for _ in 0..samples {
+            faults = run(checker, [(throttle, brake)])[0];
+        }
+        faults
+    }
+
+    #[test]
+    fn healthy_sweep_has_no_faults() {
+        let mut checker = PlausibilityChecker::default();
+        // Throttle up and back down, then the brake
+        for step in (0..=30).chain((0..=30).rev()) {
+            let throttle = REST + step * 100;
+            assert_eq!(run(&mut checker, [(throttle, REST)]), [PedalFaults::NONE]);
+        }
+        for step in (0..=30).chain((0..=30).rev()) {
+            let brake = REST + step * 100;
+            assert_eq!(run(&mut checker, [(REST, brake)]), [PedalFaults::NONE]);
+        }
+    }
+
+    #[test]
+    fn open_throttle_wire_latches_while_open() {
+        let mut checker = PlausibilityChecker::default();
+        // Slow fall to the rail, so only the range rule fires
+        let faults = run(&mut checker, [(REST, REST), (300, REST), (100, REST), (0, REST)]);
+        assert_eq!(faults[2], PedalFaults::NONE);
+        assert_eq!(faults[3], PedalFaults::THROTTLE_RANGE);
+
+        // Still open after much longer than the release time
+        let faults = hold(&mut checker, 0, REST, 200);
+        assert_eq!(faults, PedalFaults::THROTTLE_RANGE);
+        assert_eq!(faults.limit(0.8, 0.3), (0.0, 0.3));
+    }
+
+    #[test]
+    fn shorted_brake_zeroes_both_pedals() {
+        let mut checker = PlausibilityChecker::default();
+        let faults = run(&mut checker, [(REST, 3800), (REST, 4095)]);
+        assert_eq!(faults, [PedalFaults::NONE, PedalFaults::BRAKE_RANGE]);
+        assert_eq!(faults[1].limit(0.5, 0.9), (0.0, 0.0));
+    }
+
+    #[test]
+    fn rate_fault_holds_for_the_release_time() {
+        let mut checker = PlausibilityChecker::default();
+        let release = checker.limits.release_samples;
+        // Throttle jumps 2500 counts in one sample, then drops straight back
+        let faults = run(&mut checker, [(REST, REST), (3000, REST), (REST, REST)]);
+        assert_eq!(faults[1], PedalFaults::THROTTLE_RATE);
+        assert!(faults[2].contains(PedalFaults::THROTTLE_RATE));
+
+        // Back at rest, but not for long enough yet
+        assert_eq!(hold(&mut checker, REST, REST, release - 1), PedalFaults::THROTTLE_RATE);
+        assert_eq!(hold(&mut checker, REST, REST, 1), PedalFaults::NONE);
+    }
+
+    #[test]
+    fn latch_waits_for_the_throttle_to_be_released() {
+        let mut checker = PlausibilityChecker::default();
+        let release = checker.limits.release_samples;
+        run(&mut checker, [(REST, REST), (3000, REST)]);
+
+        // The reading is plausible again, but the driver is still on the throttle
+        assert_eq!(hold(&mut checker, 3000, REST, 3 * release), PedalFaults::THROTTLE_RATE);
+
+        // Lifting off starts the release time
+        let faults = run(&mut checker, [(2000, REST), (1000, REST), (REST, REST)]);
+        assert_eq!(faults, [PedalFaults::THROTTLE_RATE; 3]);
+        assert_eq!(hold(&mut checker, REST, REST, release - 1), PedalFaults::NONE);
+    }
+
+    #[test]
+    fn new_fault_restarts_the_release_time() {
+        let mut checker = PlausibilityChecker::default();
+        let release = checker.limits.release_samples;
+        run(&mut checker, [(REST, REST), (3000, REST), (REST, REST)]);
+        hold(&mut checker, REST, REST, release - 5);
+
+        // Brake glitch just before the release
+        let faults = run(&mut checker, [(REST, 3000), (REST, REST)]);
+        assert!(faults[1].contains(PedalFaults::THROTTLE_RATE | PedalFaults::BRAKE_RATE));
+        assert_eq!(hold(&mut checker, REST, REST, release - 1), PedalFaults::THROTTLE_RATE | PedalFaults::BRAKE_RATE);
+        assert_eq!(hold(&mut checker, REST, REST, 1), PedalFaults::NONE);
+    }
+
+    #[test]
+    fn both_pedals_pressed_is_a_conflict() {
+        let mut checker = PlausibilityChecker::default();
+        let faults = run(&mut checker, [(REST, REST), (1000, 1000), (1500, 1500), (2000, 2000)]);
+        // 1000 counts is about 17%, under the 25% thresholds
+        assert_eq!(faults[1], PedalFaults::NONE);
+        assert_eq!(faults[2], PedalFaults::PEDAL_CONFLICT);
+        assert_eq!(faults[3], PedalFaults::PEDAL_CONFLICT);
+        // Braking alone does not release the latch while the throttle is down
+        assert_eq!(hold(&mut checker, 2000, REST, 100), PedalFaults::PEDAL_CONFLICT);
+        assert_eq!(faults[3].limit(0.5, 0.5), (0.0, 0.5));
+    }
+
+    #[test]
+    fn first_sample_has_no_rate_check() {
+        let mut checker = PlausibilityChecker::default();
+        assert_eq!(run(&mut checker, [(3000, REST)]), [PedalFaults::NONE]);
+    }
+
+    #[test]
+    fn label_shows_the_most_severe_fault() {
+        assert_eq!(PedalFaults::NONE.label(), "");
+        assert_eq!((PedalFaults::PEDAL_CONFLICT | PedalFaults::BRAKE_RATE).label(), "RATE");
+        assert_eq!((PedalFaults::BRAKE_RANGE | PedalFaults::THROTTLE_RATE).label(), "BRK!");
+        assert_eq!((PedalFaults::THROTTLE_RANGE | PedalFaults::BRAKE_RANGE).label(), "THR!");
+        assert_eq!(PedalFaults::PEDAL_CONFLICT.label(), "T+B!");
+    }
+}
@@ -0,0 +1,295 @@
+//! Persistent configuration storage in internal flash
+//!
+//! Each `ConfigStore` owns one flash erase sector and holds a single record:
+//!
+//! | Offset | Size | Field                      |
+//! |--------|------|----------------------------|
+//! | 0      | 4    | Magic (`RECORD_MAGIC`)     |
+//! | 4      | 2    | Payload length             |
+//! | 6      | 2    | CRC-16/CCITT of payload    |
+//! | 8      | n    | Payload, padded with 0xFF  |
+//!
+//! Works over any `embedded_storage_async` NOR flash, so the same code runs
+//! against the STM32 flash driver or an in-memory flash.
+//!
+//! NOTE: Erasing a 128KB sector takes up to ~2s on the F429. The sectors used
+//! here are in bank 2, so with the async flash driver the firmware keeps
+//! running from bank 1 meanwhile; `save` still holds up its caller for that
+//! long, so call it from a task with nothing else to do.
+
+use embedded_storage_async::nor_flash::NorFlash;
+
+/// Offset of the pedal calibration sector from the start of flash
+///
+/// This is the last 128KB sector of bank 2 (0x081E_0000), well clear of the
+/// firmware image at the start of bank 1.
+pub const CALIBRATION_OFFSET: u32 = 0x1E_0000;
+
+/// Largest payload a record can hold
+pub const MAX_PAYLOAD_SIZE: usize = 56;
+
+/// Marks a valid record header ("SWCF")
+const RECORD_MAGIC: u32 = 0x4643_5753;
+
+const HEADER_SIZE: usize = 8;
+const MAX_RECORD_SIZE: usize = HEADER_SIZE + MAX_PAYLOAD_SIZE;
+
+/// Errors returned by `ConfigStore`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum StorageError {
+    /// No record has been saved yet (or the sector was erased)
+    NotFound,
+    /// A record header was found but the payload failed its checksum
+    Corrupt,
+    /// Payload does not fit in the record or the caller's buffer
+    TooLarge,
+    /// The flash driver reported an error
+    Flash,
+}
+
+/// A single configuration record stored in its own flash sector
+pub struct ConfigStore<F> {
+    flash: F,
+    offset: u32,
+}
+
+impl<F: NorFlash> ConfigStore<F> {
+    /// `offset` must be aligned to the start of an erase sector
+    pub fn new(flash: F, offset: u32) -> Self {
+        Self { flash, offset }
+    }
+
+    /// Load the stored payload into `payload`, returning its length
+    pub async fn load(&mut self, payload: &mut [u8]) -> Result<usize, StorageError> {
+        let mut header = [0u8; HEADER_SIZE];
+        self.flash
+            .read(self.offset, &mut header)
+            .await
+            .map_err(|_| StorageError::Flash)?;
+
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        if magic != RECORD_MAGIC {
+            return Err(StorageError::NotFound);
+        }
+
+        let len = u16::from_le_bytes([header[4], header[5]]) as usize;
+        let crc = u16::from_le_bytes([header[6], header[7]]);
+        if len > MAX_PAYLOAD_SIZE {
+            return Err(StorageError::Corrupt);
+        }
+        if len > payload.len() {
+            return Err(StorageError::TooLarge);
+        }
+
+        self.flash
+            .read(self.offset + HEADER_SIZE as u32, &mut payload[..len])
+            .await
+            .map_err(|_| StorageError::Flash)?;
+
+        if crc16(&payload[..len]) != crc {
+            return Err(StorageError::Corrupt);
+        }
+        Ok(len)
+    }
+
+    /// Erase the sector and write `payload` as the new record
+    pub async fn save(&mut self, payload: &[u8]) -> Result<(), StorageError> {
+        if payload.len() > MAX_PAYLOAD_SIZE {
+            return Err(StorageError::TooLarge);
+        }
+
+        let mut record = [0xFFu8; MAX_RECORD_SIZE];
+        record[0..4].copy_from_slice(&RECORD_MAGIC.to_le_bytes());
+        record[4..6].copy_from_slice(&(payload.len() as u16).to_le_bytes());
+        record[6..8].copy_from_slice(&crc16(payload).to_le_bytes());
+        record[HEADER_SIZE..HEADER_SIZE + payload.len()].copy_from_slice(payload);
+
+        // Writes must be a multiple of the flash write size
+        let len = (HEADER_SIZE + payload.len()).next_multiple_of(F::WRITE_SIZE);
+
+        self.flash
+            .erase(self.offset, self.offset + F::ERASE_SIZE as u32)
+            .await
+            .map_err(|_| StorageError::Flash)?;
+        self.flash
+            .write(self.offset, &record[..len])
+            .await
+            .map_err(|_| StorageError::Flash)
+    }
+}
+
+/// CRC-16/CCITT-FALSE
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_futures::block_on;
+    use embedded_storage_async::nor_flash::{ErrorType, NorFlashErrorKind, ReadNorFlash};
+
+    use crate::drivers::pedals::calibration::{PedalCalibration, CALIBRATION_SIZE};
+    use crate::drivers::pedals::PedalRange;
+
+    const SECTOR_SIZE: usize = 256;
+
+    /// Two erase sectors of NOR flash in memory: erasing sets bits, writing
+    /// can only clear them
+    struct MemFlash {
+        data: [u8; 2 * SECTOR_SIZE],
+    }
+
+    impl MemFlash {
+        /// Fresh out of the factory: fully erased
+        fn new() -> Self {
+            Self { data: [0xFF; 2 * SECTOR_SIZE] }
+        }
+    }
+
+    impl ErrorType for MemFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for MemFlash {
+        const READ_SIZE: usize = 1;
+
+        async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let start = offset as usize;
+            let source = self.data.get(start..start + bytes.len()).ok_or(NorFlashErrorKind::OutOfBounds)?;
+            bytes.copy_from_slice(source);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl NorFlash for MemFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = SECTOR_SIZE;
+
+        async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            if !(from as usize).is_multiple_of(SECTOR_SIZE) || !(to as usize).is_multiple_of(SECTOR_SIZE) {
+                return Err(NorFlashErrorKind::NotAligned);
+            }
+            self.data[from as usize..to as usize].fill(0xFF);
+            Ok(())
+        }
+
+        async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            if !(offset as usize).is_multiple_of(Self::WRITE_SIZE) || !bytes.len().is_multiple_of(Self::WRITE_SIZE) {
+                return Err(NorFlashErrorKind::NotAligned);
+            }
+            for (cell, byte) in self.data[offset as usize..].iter_mut().zip(bytes) {
+                *cell &= *byte;
+            }
+            Ok(())
+        }
+    }
+
+    /// Load into a buffer big enough for any record
+    fn load(store: &mut ConfigStore<&mut MemFlash>) -> Result<heapless::Vec<u8, MAX_PAYLOAD_SIZE>, StorageError> {
+        let mut payload = [0u8; MAX_PAYLOAD_SIZE];
+        let len = block_on(store.load(&mut payload))?;
+        Ok(heapless::Vec::from_slice(&payload[..len]).unwrap())
+    }
+
+    #[test]
+    fn crc_matches_ccitt_false() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc16(&[]), 0xFFFF);
+    }
+
+    #[test]
+    fn erased_sector_has_no_record() {
+        let mut flash = MemFlash::new();
+        let mut store = ConfigStore::new(&mut flash, 0);
+        assert_eq!(load(&mut store), Err(StorageError::NotFound));
+    }
+
+    #[test]
+    fn saved_record_reads_back() {
+        let mut flash = MemFlash::new();
+        let mut store = ConfigStore::new(&mut flash, SECTOR_SIZE as u32);
+        block_on(store.save(b"first record")).unwrap();
+        // Saving again erases first, so the old bits don't bleed through
+        block_on(store.save(b"second")).unwrap();
+        assert_eq!(load(&mut store).unwrap(), b"second");
+
+        // Header: magic, length and CRC, little endian, payload padded with 0xFF
+        let record = &flash.data[SECTOR_SIZE..SECTOR_SIZE + HEADER_SIZE + 8];
+        assert_eq!(&record[0..4], b"SWCF");
+        assert_eq!(&record[4..6], &6u16.to_le_bytes());
+        assert_eq!(&record[6..8], &crc16(b"second").to_le_bytes());
+        assert_eq!(&record[8..14], b"second");
+        assert_eq!(&record[14..], [0xFF, 0xFF]);
+        // The other sector is untouched
+        assert!(flash.data[..SECTOR_SIZE].iter().all(|b| *b == 0xFF));
+    }
+
+    #[test]
+    fn corrupted_payload_or_crc_is_rejected() {
+        let mut flash = MemFlash::new();
+        block_on(ConfigStore::new(&mut flash, 0).save(b"payload")).unwrap();
+
+        let mut damaged = MemFlash { data: flash.data };
+        damaged.data[HEADER_SIZE + 2] ^= 0x10;
+        assert_eq!(load(&mut ConfigStore::new(&mut damaged, 0)), Err(StorageError::Corrupt));
+
+        let mut damaged = MemFlash { data: flash.data };
+        damaged.data[6] ^= 0x01;
+        assert_eq!(load(&mut ConfigStore::new(&mut damaged, 0)), Err(StorageError::Corrupt));
+
+        // A length past the largest payload is never trusted
+        let mut damaged = MemFlash { data: flash.data };
+        damaged.data[4..6].copy_from_slice(&(MAX_PAYLOAD_SIZE as u16 + 1).to_le_bytes());
+        assert_eq!(load(&mut ConfigStore::new(&mut damaged, 0)), Err(StorageError::Corrupt));
+    }
+
+    #[test]
+    fn oversized_payloads_are_refused() {
+        let mut flash = MemFlash::new();
+        let mut store = ConfigStore::new(&mut flash, 0);
+        assert_eq!(block_on(store.save(&[0; MAX_PAYLOAD_SIZE + 1])), Err(StorageError::TooLarge));
+        block_on(store.save(&[0x42; MAX_PAYLOAD_SIZE])).unwrap();
+
+        // The caller's buffer is too small for the stored record
+        let mut small = [0u8; 4];
+        assert_eq!(block_on(store.load(&mut small)), Err(StorageError::TooLarge));
+    }
+
+    #[test]
+    fn pedal_calibration_round_trips_through_flash() {
+        let calibration = PedalCalibration {
+            throttle: PedalRange { min: 480, max: 3500 },
+            brake: PedalRange { min: 600, max: 3400 },
+        };
+        let mut flash = MemFlash::new();
+        let mut store = ConfigStore::new(&mut flash, 0);
+        block_on(store.save(&calibration.to_bytes())).unwrap();
+
+        let mut buf = [0u8; CALIBRATION_SIZE];
+        let len = block_on(store.load(&mut buf)).unwrap();
+        assert_eq!(PedalCalibration::from_bytes(&buf[..len]), Some(calibration));
+
+        // A record in another format version passes its CRC but is not used
+        let mut bytes = calibration.to_bytes();
+        bytes[0] += 1;
+        block_on(store.save(&bytes)).unwrap();
+        let len = block_on(store.load(&mut buf)).unwrap();
+        assert_eq!(PedalCalibration::from_bytes(&buf[..len]), None);
+    }
+}
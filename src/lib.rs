@@ -1,4 +1,8 @@
 #![no_std]
 
+pub mod control;
 pub mod drivers;
+pub mod protocol;
+pub mod screens;
+pub mod state;
 pub mod tasks;
@@ -2,17 +2,40 @@
 #![no_main]
 
 use defmt::info;
+use embassy_vehiclecomputer::control::FailsafeConfig;
+use embassy_vehiclecomputer::drivers::crash;
 use embassy_net::Stack;
 use embassy_executor::Spawner;
 use embassy_stm32::gpio::{Level, Output, Speed};
 use embassy_stm32::spi::{self, Spi};
 use embassy_stm32::time::Hertz;
+use embassy_stm32::wdg::IndependentWatchdog;
+use embassy_stm32::flash::Flash;
 use embassy_stm32::Config;
-use embassy_vehiclecomputer::drivers::buttons::{ButtonInputs, Button, ButtonId};
+use embassy_vehiclecomputer::drivers::buttons::{ButtonInputs, Button, ButtonId, InputMode};
+use embassy_vehiclecomputer::drivers::leds::{ButtonLed, ButtonLeds, Leds, StatusLeds};
 use embassy_vehiclecomputer::drivers::network;
+use embassy_vehiclecomputer::drivers::pedals::PedalInputs;
+use embassy_vehiclecomputer::drivers::storage::{ConfigStore, CALIBRATION_OFFSET};
 use embassy_vehiclecomputer::drivers::usb::setup_usb_logger;
+use embassy_vehiclecomputer::state;
 use embassy_vehiclecomputer::tasks;
-use {defmt_rtt as _, panic_probe as _};
+use defmt_rtt as _;
+
+// Crashes are written to backup SRAM and reported on the next boot
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    crash::record_panic(info)
+}
+
+#[cortex_m_rt::exception]
+unsafe fn HardFault(frame: &cortex_m_rt::ExceptionFrame) -> ! {
+    crash::record_hard_fault(frame)
+}
+
+/// Button input mode: `InputMode::Polled` reads every pin every 10ms,
+/// `InputMode::Interrupt` sleeps until a button edge
+const BUTTON_INPUT_MODE: InputMode = InputMode::Interrupt;
 
 // Async task for waiting for network link
 #[embassy_executor::task]
@@ -50,8 +73,8 @@ async fn main(spawner: Spawner) {
 
     let p = embassy_stm32::init(config);
 
-    // Initialize LED on PD8 (starts OFF)
-    let led = Output::new(p.PD8, Level::Low, Speed::Low);
+    // Pick up a crash record left in backup SRAM by the previous run
+    crash::init();
 
     // Initialize USB logger for debugging
     // This creates a USB serial device that will appear on your computer
@@ -60,6 +83,11 @@ async fn main(spawner: Spawner) {
     setup_usb_logger(&spawner, p.USB_OTG_FS, p.PA12, p.PA11)
         .expect("Failed to initialize USB logger");
 
+    // Buffered until a host opens the serial port
+    if let Some(record) = crash::last_crash() {
+        crash::log_report(record);
+    }
+
     // Reset the LAN8742A PHY before initializing Ethernet
     // The PHY reset pin is on PD15 (active low)
     // This must happen BEFORE Ethernet initialization
@@ -93,23 +121,58 @@ async fn main(spawner: Spawner) {
     info!("IP: 192.168.0.30");
     info!("Network targets: VC=192.168.0.20:3001, BMS=192.168.0.10:2001");
 
+    // Shared vehicle state - written by producers (buttons, pedals, network receive)
+    // and read by consumers (display, telemetry)
+    let shared_state = state::init();
+
     // Initialize button inputs - all button definitions in one place!
     // To add a new button:
     // 1. Add its ButtonId variant to the enum in drivers/buttons/mod.rs
     // 2. Add a Button entry here with the pin assignment
+    //
+    // Each EXTI line serves one pin number across all ports, so PD12/PE12 and
+    // PD14/PE14 cannot both use interrupts. Left turn and the horn get the
+    // lines; the cruise buttons are polled, and only matter while driving.
     let button_inputs = ButtonInputs::new([
-        Button::regular(ButtonId::CruiseDown,  "Cruise Down",   p.PD12),
-        Button::regular(ButtonId::CruiseUp,    "Cruise Up",     p.PE14),
-        Button::regular(ButtonId::Reverse,     "Reverse",       p.PE0),
-        Button::regular(ButtonId::PushToTalk,  "Push-to-Talk",  p.PE4),
-        Button::regular(ButtonId::Horn,        "Horn",          p.PD14),
-        Button::regular(ButtonId::PowerSave,   "Power Save",    p.PE2),
-        Button::regular(ButtonId::Rearview,    "Rearview",      p.PE8),
-        Button::toggle(ButtonId::LeftTurn,     "Left Turn",     p.PE12),
-        Button::toggle(ButtonId::RightTurn,    "Right Turn",    p.PE6),
-        Button::toggle(ButtonId::Lock,         "Lock",          p.PE10),
+        Button::regular(ButtonId::CruiseDown,      "Cruise Down",   p.PD12),
+        Button::regular(ButtonId::CruiseUp,        "Cruise Up",     p.PE14),
+        Button::regular_exti(ButtonId::Reverse,    "Reverse",       p.PE0,  p.EXTI0),
+        Button::regular_exti(ButtonId::PushToTalk, "Push-to-Talk",  p.PE4,  p.EXTI4),
+        Button::regular_exti(ButtonId::Horn,       "Horn",          p.PD14, p.EXTI14),
+        Button::regular_exti(ButtonId::PowerSave,  "Power Save",    p.PE2,  p.EXTI2),
+        Button::regular_exti(ButtonId::Rearview,   "Rearview",      p.PE8,  p.EXTI8),
+        Button::regular_exti(ButtonId::LeftTurn,   "Left Turn",     p.PE12, p.EXTI12),
+        Button::regular_exti(ButtonId::RightTurn,  "Right Turn",    p.PE6,  p.EXTI6),
+        Button::toggle_exti(ButtonId::Lock,        "Lock",          p.PE10, p.EXTI10),
     ]);
 
+    // Button LEDs (active low) sit on the pin after their button's input pin.
+    // TODO: the plan puts the horn LED on PD15, which is the PHY reset; leave
+    // it unmapped until the board's real horn LED pin is confirmed.
+    let button_leds = ButtonLeds::new([
+        ButtonLed::new(ButtonId::CruiseDown,  p.PD13),
+        ButtonLed::new(ButtonId::CruiseUp,    p.PE15),
+        ButtonLed::new(ButtonId::Reverse,     p.PE1),
+        ButtonLed::new(ButtonId::PushToTalk,  p.PE5),
+        ButtonLed::new(ButtonId::PowerSave,   p.PE3),
+        ButtonLed::new(ButtonId::Rearview,    p.PE9),
+        ButtonLed::new(ButtonId::LeftTurn,    p.PE13),
+        ButtonLed::new(ButtonId::RightTurn,   p.PE7),
+        ButtonLed::new(ButtonId::Lock,        p.PE11),
+    ]);
+
+    // Status LEDs: PD8 white, PD9 blue, PD10 green, PD11 red
+    let leds = Leds::new(button_leds, StatusLeds::new(p.PD8, p.PD9, p.PD10, p.PD11));
+
+    // Initialize pedal inputs: throttle on PB1 (ADC1_IN9), brake on PB0 (ADC1_IN8)
+    // DMA2_CH0 is taken by SPI1 RX, so ADC1 uses its alternate stream DMA2_CH4
+    let pedal_inputs = PedalInputs::new(p.ADC1, p.PB1, p.PB0, p.DMA2_CH4);
+
+    // Pedal calibration is persisted in its own internal flash sector
+    let mut calibration_store =
+        ConfigStore::new(Flash::new(p.FLASH, tasks::pedals::FlashIrqs), CALIBRATION_OFFSET);
+    let calibration = tasks::pedals::load_calibration(&mut calibration_store).await;
+
     // Configure SPI1 for display
     let mut spi_config = spi::Config::default();
     spi_config.mode = spi::Mode {
@@ -133,12 +196,23 @@ async fn main(spawner: Spawner) {
     let cs = Output::new(p.PA15, Level::High, Speed::High);  // Chip Select
     let rst = Output::new(p.PD7, Level::High, Speed::High);  // Reset
 
-    // Spawn tasks
-    spawner.spawn(tasks::display_task(spi, dc, cs, rst)).unwrap();
-    spawner.spawn(tasks::blinky_task(led)).unwrap();
-    spawner.spawn(tasks::button_task(button_inputs)).unwrap();
 
-    // Spawn network tasks
-    spawner.spawn(tasks::telemetry_task(stack)).unwrap();
-    spawner.spawn(tasks::steering_update_task(stack)).unwrap();
+    // Independent watchdog, fed by the supervisor while all tasks check in
+    let watchdog = IndependentWatchdog::new(p.IWDG, tasks::watchdog::WATCHDOG_TIMEOUT_US);
+
+    // Spawn tasks
+    spawner.spawn(tasks::watchdog_task(watchdog)).unwrap();
+    spawner.spawn(tasks::button_task(button_inputs, BUTTON_INPUT_MODE, shared_state)).unwrap();
+    spawner.spawn(tasks::pedal_task(pedal_inputs, calibration, shared_state)).unwrap();
+    spawner.spawn(tasks::calibration_store_task(calibration_store)).unwrap();
+    spawner.spawn(tasks::failsafe_task(FailsafeConfig::default(), shared_state)).unwrap();
+
+    // Spawn network tasks - all outgoing packets share one bound socket
+    let sender = network::init_sender(stack).expect("Failed to bind UDP send sockets");
+    spawner.spawn(tasks::display_flush_task(spi, dc, cs, rst)).unwrap();
+    spawner.spawn(tasks::display_task(stack, sender, shared_state)).unwrap();
+    spawner.spawn(tasks::telemetry_task(stack, sender, shared_state)).unwrap();
+    spawner.spawn(tasks::steering_update_task(stack, sender, shared_state)).unwrap();
+    spawner.spawn(tasks::network_receive_task(stack, shared_state)).unwrap();
+    spawner.spawn(tasks::led_task(leds, stack, shared_state)).unwrap();
 }
\ No newline at end of file
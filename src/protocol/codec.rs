@@ -0,0 +1,383 @@
+//! Protobuf wire format primitives
+//!
+//! A small, allocation-free subset of the protobuf binary encoding: varints,
+//! fixed32 floats and length-delimited sub-messages. This is everything the
+//! steering wheel schema uses, and matches what nanopb produced on the C side.
+
+/// Protobuf wire types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum WireType {
+    Varint = 0,
+    Fixed64 = 1,
+    LengthDelimited = 2,
+    Fixed32 = 5,
+}
+
+impl WireType {
+    fn from_u8(value: u8) -> Result<Self, DecodeError> {
+        match value {
+            0 => Ok(WireType::Varint),
+            1 => Ok(WireType::Fixed64),
+            2 => Ok(WireType::LengthDelimited),
+            5 => Ok(WireType::Fixed32),
+            _ => Err(DecodeError::InvalidWireType),
+        }
+    }
+}
+
+/// Error returned when a message does not fit in the output buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum EncodeError {
+    BufferTooSmall,
+}
+
+/// Error returned when an incoming packet is not a valid message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum DecodeError {
+    /// The packet ended in the middle of a field
+    Truncated,
+    /// A varint was longer than 10 bytes
+    InvalidVarint,
+    /// Unsupported wire type (groups) or a known field with the wrong type
+    InvalidWireType,
+    /// Field number zero is reserved
+    InvalidField,
+}
+
+/// A protobuf message that can be encoded into and decoded from a fixed buffer
+pub trait Message: Default {
+    /// Write every non-default field of this message
+    fn encode_fields(&self, writer: &mut Writer) -> Result<(), EncodeError>;
+
+    /// Merge a single field read from the wire into this message
+    ///
+    /// Unknown fields must be skipped with `reader.skip(wire_type)` so that
+    /// newer senders stay compatible with older receivers.
+    fn merge_field(
+        &mut self,
+        field: u32,
+        wire_type: WireType,
+        reader: &mut Reader,
+    ) -> Result<(), DecodeError>;
+
+    /// Number of bytes `encode` will write
+    fn encoded_len(&self) -> usize {
+        let mut counter = Writer::counting();
+        // A counting writer never runs out of space
+        let _ = self.encode_fields(&mut counter);
+        counter.position()
+    }
+
+    /// Encode into `buf`, returning the number of bytes written
+    fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut writer = Writer::new(buf);
+        self.encode_fields(&mut writer)?;
+        Ok(writer.position())
+    }
+
+    /// Merge every field in `buf` into this message
+    fn merge(&mut self, buf: &[u8]) -> Result<(), DecodeError> {
+        let mut reader = Reader::new(buf);
+        while !reader.is_empty() {
+            let (field, wire_type) = reader.key()?;
+            self.merge_field(field, wire_type, &mut reader)?;
+        }
+        Ok(())
+    }
+
+    /// Decode a message from `buf`
+    fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        let mut message = Self::default();
+        message.merge(buf)?;
+        Ok(message)
+    }
+}
+
+/// Serializes fields into a byte buffer
+///
+/// Proto3 semantics: scalar fields equal to their default are not written.
+pub struct Writer<'a> {
+    buf: Option<&'a mut [u8]>,
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf: Some(buf), pos: 0 }
+    }
+
+    /// A writer that only counts bytes, used to size sub-messages
+    fn counting() -> Self {
+        Self { buf: None, pos: 0 }
+    }
+
+    /// Number of bytes written so far
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn put(&mut self, byte: u8) -> Result<(), EncodeError> {
+        if let Some(buf) = self.buf.as_deref_mut() {
+            *buf.get_mut(self.pos).ok_or(EncodeError::BufferTooSmall)? = byte;
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn put_varint(&mut self, mut value: u64) -> Result<(), EncodeError> {
+        while value >= 0x80 {
+            self.put((value as u8) | 0x80)?;
+            value >>= 7;
+        }
+        self.put(value as u8)
+    }
+
+    fn put_key(&mut self, field: u32, wire_type: WireType) -> Result<(), EncodeError> {
+        self.put_varint(((field as u64) << 3) | wire_type as u64)
+    }
+
+    pub fn uint32(&mut self, field: u32, value: u32) -> Result<(), EncodeError> {
+        if value == 0 {
+            return Ok(());
+        }
+        self.put_key(field, WireType::Varint)?;
+        self.put_varint(value as u64)
+    }
+
+    pub fn bool(&mut self, field: u32, value: bool) -> Result<(), EncodeError> {
+        if !value {
+            return Ok(());
+        }
+        self.put_key(field, WireType::Varint)?;
+        self.put(1)
+    }
+
+    pub fn float(&mut self, field: u32, value: f32) -> Result<(), EncodeError> {
+        if value == 0.0 {
+            return Ok(());
+        }
+        self.put_key(field, WireType::Fixed32)?;
+        for byte in value.to_le_bytes() {
+            self.put(byte)?;
+        }
+        Ok(())
+    }
+
+    /// Write a bytes/string field, skipping it if empty
+    pub fn bytes(&mut self, field: u32, value: &[u8]) -> Result<(), EncodeError> {
+        if value.is_empty() {
+            return Ok(());
+        }
+        self.put_key(field, WireType::LengthDelimited)?;
+        self.put_varint(value.len() as u64)?;
+        for byte in value {
+            self.put(*byte)?;
+        }
+        Ok(())
+    }
+
+    /// Write a sub-message, skipping it entirely if it has no set fields
+    pub fn message<M: Message>(&mut self, field: u32, message: &M) -> Result<(), EncodeError> {
+        let len = message.encoded_len();
+        if len == 0 {
+            return Ok(());
+        }
+        self.put_key(field, WireType::LengthDelimited)?;
+        self.put_varint(len as u64)?;
+        message.encode_fields(self)
+    }
+
+    /// Write a sub-message even if it is empty (explicit presence)
+    pub fn present_message<M: Message>(&mut self, field: u32, message: &M) -> Result<(), EncodeError> {
+        self.put_key(field, WireType::LengthDelimited)?;
+        self.put_varint(message.encoded_len() as u64)?;
+        message.encode_fields(self)
+    }
+}
+
+/// Reads fields from a received byte buffer
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self.pos.checked_add(len).ok_or(DecodeError::Truncated)?;
+        let bytes = self.buf.get(self.pos..end).ok_or(DecodeError::Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for i in 0..10 {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7F) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::InvalidVarint)
+    }
+
+    /// Read a field key, returning (field number, wire type)
+    pub fn key(&mut self) -> Result<(u32, WireType), DecodeError> {
+        let key = self.varint()?;
+        let field = (key >> 3) as u32;
+        if field == 0 {
+            return Err(DecodeError::InvalidField);
+        }
+        Ok((field, WireType::from_u8((key & 0x7) as u8)?))
+    }
+
+    pub fn uint32(&mut self, wire_type: WireType) -> Result<u32, DecodeError> {
+        expect(wire_type, WireType::Varint)?;
+        // Proto3 truncates out-of-range varints to the field width
+        Ok(self.varint()? as u32)
+    }
+
+    pub fn bool(&mut self, wire_type: WireType) -> Result<bool, DecodeError> {
+        expect(wire_type, WireType::Varint)?;
+        Ok(self.varint()? != 0)
+    }
+
+    pub fn float(&mut self, wire_type: WireType) -> Result<f32, DecodeError> {
+        expect(wire_type, WireType::Fixed32)?;
+        let bytes = self.take(4)?;
+        Ok(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read a bytes/string field, borrowing from the input buffer
+    pub fn bytes(&mut self, wire_type: WireType) -> Result<&'a [u8], DecodeError> {
+        expect(wire_type, WireType::LengthDelimited)?;
+        let len = self.varint()? as usize;
+        self.take(len)
+    }
+
+    /// Merge a length-delimited sub-message into `message`
+    pub fn message<M: Message>(&mut self, wire_type: WireType, message: &mut M) -> Result<(), DecodeError> {
+        expect(wire_type, WireType::LengthDelimited)?;
+        let len = self.varint()? as usize;
+        message.merge(self.take(len)?)
+    }
+
+    /// Skip over a field this side does not know about
+    pub fn skip(&mut self, wire_type: WireType) -> Result<(), DecodeError> {
+        match wire_type {
+            WireType::Varint => self.varint().map(|_| ()),
+            WireType::Fixed64 => self.take(8).map(|_| ()),
+            WireType::LengthDelimited => {
+                let len = self.varint()? as usize;
+                self.take(len).map(|_| ())
+            }
+            WireType::Fixed32 => self.take(4).map(|_| ()),
+        }
+    }
+}
+
+fn expect(actual: WireType, expected: WireType) -> Result<(), DecodeError> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(DecodeError::InvalidWireType)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode a lone varint
+    fn varint_of(bytes: &[u8]) -> Result<u64, DecodeError> {
+        Reader::new(bytes).varint()
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut buf = [0u8; 10];
+            let mut writer = Writer::new(&mut buf);
+            writer.put_varint(value).unwrap();
+            let len = writer.position();
+            assert_eq!(varint_of(&buf[..len]), Ok(value));
+        }
+    }
+
+    #[test]
+    fn known_encodings() {
+        // Same bytes nanopb produces for these fields
+        let mut buf = [0u8; 16];
+        let mut writer = Writer::new(&mut buf);
+        writer.uint32(1, 150).unwrap();
+        writer.bool(2, true).unwrap();
+        writer.float(3, 0.5).unwrap();
+        writer.bytes(4, b"hi").unwrap();
+        let len = writer.position();
+        assert_eq!(
+            &buf[..len],
+            &[0x08, 0x96, 0x01, 0x10, 0x01, 0x1D, 0x00, 0x00, 0x00, 0x3F, 0x22, 0x02, b'h', b'i']
+        );
+    }
+
+    #[test]
+    fn default_values_are_not_written() {
+        let mut buf = [0u8; 8];
+        let mut writer = Writer::new(&mut buf);
+        writer.uint32(1, 0).unwrap();
+        writer.bool(2, false).unwrap();
+        writer.float(3, 0.0).unwrap();
+        writer.bytes(4, &[]).unwrap();
+        assert_eq!(writer.position(), 0);
+    }
+
+    #[test]
+    fn full_buffer_is_reported() {
+        let mut buf = [0u8; 3];
+        let mut writer = Writer::new(&mut buf);
+        assert_eq!(writer.float(1, 1.0), Err(EncodeError::BufferTooSmall));
+    }
+
+    #[test]
+    fn skips_every_wire_type() {
+        let bytes = [
+            0x08, 0x96, 0x01, // varint
+            0x11, 1, 2, 3, 4, 5, 6, 7, 8, // fixed64
+            0x1A, 0x02, 0xAA, 0xBB, // length-delimited
+            0x25, 1, 2, 3, 4, // fixed32
+        ];
+        let mut reader = Reader::new(&bytes);
+        for expected in [WireType::Varint, WireType::Fixed64, WireType::LengthDelimited, WireType::Fixed32] {
+            let (_, wire_type) = reader.key().unwrap();
+            assert_eq!(wire_type, expected);
+            reader.skip(wire_type).unwrap();
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        // Varint cut off after a continuation byte
+        assert_eq!(varint_of(&[0x96]), Err(DecodeError::Truncated));
+        // Eleven bytes of continuation
+        assert_eq!(varint_of(&[0xFF; 11]), Err(DecodeError::InvalidVarint));
+        // Field number zero
+        assert_eq!(Reader::new(&[0x00]).key(), Err(DecodeError::InvalidField));
+        // Start group (wire type 3)
+        assert_eq!(Reader::new(&[0x0B]).key(), Err(DecodeError::InvalidWireType));
+        // Float with two of its four bytes
+        assert_eq!(Reader::new(&[0, 0]).float(WireType::Fixed32), Err(DecodeError::Truncated));
+        // Length prefix longer than the rest of the packet
+        assert_eq!(Reader::new(&[0x05, 1, 2]).bytes(WireType::LengthDelimited), Err(DecodeError::Truncated));
+        // A varint where a float was expected
+        assert_eq!(Reader::new(&[0x01]).float(WireType::Varint), Err(DecodeError::InvalidWireType));
+    }
+}
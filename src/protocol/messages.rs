@@ -0,0 +1,583 @@
+//! Steering wheel network messages
+//!
+//! Hand-written equivalents of the types in `messages.proto`. Field numbers
+//! must match the .proto file exactly - they are what goes on the wire.
+//!
+//! Only the fields in the migration plan's schema are known to match the C
+//! firmware; messages.proto lists which those are. The other `VcState`,
+//! `BmsState` and `Lights` fields are provisional and decode as 0 from a VC
+//! or BMS that doesn't send them.
+
+use super::codec::{DecodeError, EncodeError, Message, Reader, WireType, Writer};
+
+/// Largest encoded `DataMessage` we expect to send or receive
+pub const MAX_MESSAGE_SIZE: usize = 512;
+
+/// Longest crash message carried in a `CrashReport`
+pub const MAX_CRASH_MESSAGE: usize = 96;
+
+/// Top-level message exchanged between steering wheel, VC and BMS
+///
+/// Each node fills in its own section; the others are left empty.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DataMessage {
+    pub sw_state: Option<SwState>,
+    pub vc_state: Option<VcState>,
+    pub bms_state: Option<BmsState>,
+    /// Crash from the previous run, sent with telemetry
+    pub crash_report: Option<CrashReport>,
+}
+
+impl DataMessage {
+    /// Message carrying only the steering wheel state
+    pub fn from_sw_state(sw_state: SwState) -> Self {
+        Self {
+            sw_state: Some(sw_state),
+            ..Self::default()
+        }
+    }
+}
+
+impl Message for DataMessage {
+    fn encode_fields(&self, w: &mut Writer) -> Result<(), EncodeError> {
+        if let Some(sw_state) = &self.sw_state {
+            w.present_message(1, sw_state)?;
+        }
+        if let Some(vc_state) = &self.vc_state {
+            w.present_message(2, vc_state)?;
+        }
+        if let Some(bms_state) = &self.bms_state {
+            w.present_message(3, bms_state)?;
+        }
+        if let Some(crash_report) = &self.crash_report {
+            w.present_message(4, crash_report)?;
+        }
+        Ok(())
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: WireType, r: &mut Reader) -> Result<(), DecodeError> {
+        match field {
+            1 => r.message(wire_type, self.sw_state.get_or_insert_with(Default::default)),
+            2 => r.message(wire_type, self.vc_state.get_or_insert_with(Default::default)),
+            3 => r.message(wire_type, self.bms_state.get_or_insert_with(Default::default)),
+            4 => r.message(wire_type, self.crash_report.get_or_insert_with(Default::default)),
+            _ => r.skip(wire_type),
+        }
+    }
+}
+
+/// Steering wheel state, sent to the VC and BMS every 50ms
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SwState {
+    pub button_state: SteerButtonState,
+    /// Normalized throttle pedal position (0.0 - 1.0)
+    pub throttle: f32,
+    /// Normalized brake/regen pedal position (0.0 - 1.0)
+    pub brake: f32,
+    /// Currently displayed screen
+    pub screen: u32,
+    /// Milliseconds since the last VC message was received
+    pub time_tracker_vc: u32,
+    /// Milliseconds since the last BMS message was received
+    pub time_tracker_bms: u32,
+    /// Latched pedal fault bits (see `PedalFaults`), 0 when healthy
+    pub pedal_faults: u32,
+    /// Latched failsafe DTC bits (see `FailsafeDtc`), 0 when healthy
+    pub dtcs: u32,
+    /// Drive mode selected on the wheel, same numbering as `DriveState`
+    pub drive_mode: u32,
+    /// Cruise engaged on the wheel
+    pub cruise_enabled: bool,
+    /// Cruise target speed
+    pub cruise_speed: f32,
+}
+
+impl Message for SwState {
+    fn encode_fields(&self, w: &mut Writer) -> Result<(), EncodeError> {
+        w.message(1, &self.button_state)?;
+        w.float(2, self.throttle)?;
+        w.float(3, self.brake)?;
+        w.uint32(4, self.screen)?;
+        w.uint32(5, self.time_tracker_vc)?;
+        w.uint32(6, self.time_tracker_bms)?;
+        w.uint32(7, self.pedal_faults)?;
+        w.uint32(8, self.dtcs)?;
+        w.uint32(9, self.drive_mode)?;
+        w.bool(10, self.cruise_enabled)?;
+        w.float(11, self.cruise_speed)
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: WireType, r: &mut Reader) -> Result<(), DecodeError> {
+        match field {
+            1 => r.message(wire_type, &mut self.button_state),
+            2 => r.float(wire_type).map(|v| self.throttle = v),
+            3 => r.float(wire_type).map(|v| self.brake = v),
+            4 => r.uint32(wire_type).map(|v| self.screen = v),
+            5 => r.uint32(wire_type).map(|v| self.time_tracker_vc = v),
+            6 => r.uint32(wire_type).map(|v| self.time_tracker_bms = v),
+            7 => r.uint32(wire_type).map(|v| self.pedal_faults = v),
+            8 => r.uint32(wire_type).map(|v| self.dtcs = v),
+            9 => r.uint32(wire_type).map(|v| self.drive_mode = v),
+            10 => r.bool(wire_type).map(|v| self.cruise_enabled = v),
+            11 => r.float(wire_type).map(|v| self.cruise_speed = v),
+            _ => r.skip(wire_type),
+        }
+    }
+}
+
+/// Steering wheel button states
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SteerButtonState {
+    pub cruise_down_on: bool,
+    pub cruise_up_on: bool,
+    pub reverse_on: bool,
+    pub horn_on: bool,
+    pub lock_on: bool,
+    pub rearview_on: bool,
+    pub power_save_on: bool,
+    pub ptt_on: bool,
+    pub left_turn_on: bool,
+    pub right_turn_on: bool,
+    /// Bitmask of lit button LEDs
+    pub led_state: u32,
+}
+
+impl Message for SteerButtonState {
+    fn encode_fields(&self, w: &mut Writer) -> Result<(), EncodeError> {
+        w.bool(1, self.cruise_down_on)?;
+        w.bool(2, self.cruise_up_on)?;
+        w.bool(3, self.reverse_on)?;
+        w.bool(4, self.horn_on)?;
+        w.bool(5, self.lock_on)?;
+        w.bool(6, self.rearview_on)?;
+        w.bool(7, self.power_save_on)?;
+        w.bool(8, self.ptt_on)?;
+        w.bool(9, self.left_turn_on)?;
+        w.bool(10, self.right_turn_on)?;
+        w.uint32(11, self.led_state)
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: WireType, r: &mut Reader) -> Result<(), DecodeError> {
+        match field {
+            1 => r.bool(wire_type).map(|v| self.cruise_down_on = v),
+            2 => r.bool(wire_type).map(|v| self.cruise_up_on = v),
+            3 => r.bool(wire_type).map(|v| self.reverse_on = v),
+            4 => r.bool(wire_type).map(|v| self.horn_on = v),
+            5 => r.bool(wire_type).map(|v| self.lock_on = v),
+            6 => r.bool(wire_type).map(|v| self.rearview_on = v),
+            7 => r.bool(wire_type).map(|v| self.power_save_on = v),
+            8 => r.bool(wire_type).map(|v| self.ptt_on = v),
+            9 => r.bool(wire_type).map(|v| self.left_turn_on = v),
+            10 => r.bool(wire_type).map(|v| self.right_turn_on = v),
+            11 => r.uint32(wire_type).map(|v| self.led_state = v),
+            _ => r.skip(wire_type),
+        }
+    }
+}
+
+/// Vehicle computer state
+///
+/// Fields 1-3 come from the migration plan; the rest are provisional.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VcState {
+    pub speed: f32,
+    /// Drive mode, same numbering as `DriveState`
+    pub drive_mode: u32,
+    pub vc_lights: Lights,
+    pub left_motor_velocity: f32,
+    pub right_motor_velocity: f32,
+    pub cruise_enabled: bool,
+    pub cruise_speed: f32,
+    pub regen_enabled: bool,
+    pub throttle_enabled: bool,
+    pub brake_pressed: bool,
+    pub low_voltage: f32,
+    /// Steering angle in degrees, positive to the right
+    pub steering_angle: f32,
+}
+
+impl Message for VcState {
+    fn encode_fields(&self, w: &mut Writer) -> Result<(), EncodeError> {
+        w.float(1, self.speed)?;
+        w.uint32(2, self.drive_mode)?;
+        w.message(3, &self.vc_lights)?;
+        w.float(4, self.left_motor_velocity)?;
+        w.float(5, self.right_motor_velocity)?;
+        w.bool(6, self.cruise_enabled)?;
+        w.float(7, self.cruise_speed)?;
+        w.bool(8, self.regen_enabled)?;
+        w.bool(9, self.throttle_enabled)?;
+        w.bool(10, self.brake_pressed)?;
+        w.float(11, self.low_voltage)?;
+        w.float(12, self.steering_angle)
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: WireType, r: &mut Reader) -> Result<(), DecodeError> {
+        match field {
+            1 => r.float(wire_type).map(|v| self.speed = v),
+            2 => r.uint32(wire_type).map(|v| self.drive_mode = v),
+            3 => r.message(wire_type, &mut self.vc_lights),
+            4 => r.float(wire_type).map(|v| self.left_motor_velocity = v),
+            5 => r.float(wire_type).map(|v| self.right_motor_velocity = v),
+            6 => r.bool(wire_type).map(|v| self.cruise_enabled = v),
+            7 => r.float(wire_type).map(|v| self.cruise_speed = v),
+            8 => r.bool(wire_type).map(|v| self.regen_enabled = v),
+            9 => r.bool(wire_type).map(|v| self.throttle_enabled = v),
+            10 => r.bool(wire_type).map(|v| self.brake_pressed = v),
+            11 => r.float(wire_type).map(|v| self.low_voltage = v),
+            12 => r.float(wire_type).map(|v| self.steering_angle = v),
+            _ => r.skip(wire_type),
+        }
+    }
+}
+
+/// Exterior light state as reported by the VC
+///
+/// Provisional: the plan names the `vc_lights` field but not its layout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Lights {
+    pub left_turn: bool,
+    pub right_turn: bool,
+    pub hazards: bool,
+    pub brake: bool,
+    pub headlights: bool,
+}
+
+impl Message for Lights {
+    fn encode_fields(&self, w: &mut Writer) -> Result<(), EncodeError> {
+        w.bool(1, self.left_turn)?;
+        w.bool(2, self.right_turn)?;
+        w.bool(3, self.hazards)?;
+        w.bool(4, self.brake)?;
+        w.bool(5, self.headlights)
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: WireType, r: &mut Reader) -> Result<(), DecodeError> {
+        match field {
+            1 => r.bool(wire_type).map(|v| self.left_turn = v),
+            2 => r.bool(wire_type).map(|v| self.right_turn = v),
+            3 => r.bool(wire_type).map(|v| self.hazards = v),
+            4 => r.bool(wire_type).map(|v| self.brake = v),
+            5 => r.bool(wire_type).map(|v| self.headlights = v),
+            _ => r.skip(wire_type),
+        }
+    }
+}
+
+/// Battery management system state
+///
+/// Fields 1-3 come from the migration plan; the rest are provisional.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BmsState {
+    /// Pack voltage in volts
+    pub voltage: f32,
+    /// Pack current in amps (positive = discharging)
+    pub current: f32,
+    /// BMS status and fault flags (see `BmsFlags`)
+    pub flags: u32,
+    pub min_cell_voltage: f32,
+    pub max_cell_voltage: f32,
+    pub max_temperature: f32,
+    /// State of charge (0.0 - 1.0)
+    pub soc: f32,
+}
+
+impl Message for BmsState {
+    fn encode_fields(&self, w: &mut Writer) -> Result<(), EncodeError> {
+        w.float(1, self.voltage)?;
+        w.float(2, self.current)?;
+        w.uint32(3, self.flags)?;
+        w.float(4, self.min_cell_voltage)?;
+        w.float(5, self.max_cell_voltage)?;
+        w.float(6, self.max_temperature)?;
+        w.float(7, self.soc)
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: WireType, r: &mut Reader) -> Result<(), DecodeError> {
+        match field {
+            1 => r.float(wire_type).map(|v| self.voltage = v),
+            2 => r.float(wire_type).map(|v| self.current = v),
+            3 => r.uint32(wire_type).map(|v| self.flags = v),
+            4 => r.float(wire_type).map(|v| self.min_cell_voltage = v),
+            5 => r.float(wire_type).map(|v| self.max_cell_voltage = v),
+            6 => r.float(wire_type).map(|v| self.max_temperature = v),
+            7 => r.float(wire_type).map(|v| self.soc = v),
+            _ => r.skip(wire_type),
+        }
+    }
+}
+
+/// Crash recorded before the last reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrashReport {
+    /// 1 = panic, 2 = HardFault
+    pub kind: u32,
+    /// Milliseconds since boot when the crash happened
+    pub uptime_ms: u32,
+    pub pc: u32,
+    pub lr: u32,
+    /// Configurable Fault Status Register
+    pub cfsr: u32,
+    /// HardFault Status Register
+    pub hfsr: u32,
+    /// MemManage fault address
+    pub mmfar: u32,
+    /// BusFault address
+    pub bfar: u32,
+    message: [u8; MAX_CRASH_MESSAGE],
+    message_len: usize,
+}
+
+impl CrashReport {
+    /// Panic message (UTF-8, possibly truncated)
+    pub fn message(&self) -> &[u8] {
+        &self.message[..self.message_len]
+    }
+
+    /// Set the message, truncating to `MAX_CRASH_MESSAGE` bytes
+    pub fn set_message(&mut self, message: &[u8]) {
+        let len = message.len().min(MAX_CRASH_MESSAGE);
+        self.message[..len].copy_from_slice(&message[..len]);
+        self.message_len = len;
+    }
+}
+
+impl Default for CrashReport {
+    fn default() -> Self {
+        Self {
+            kind: 0,
+            uptime_ms: 0,
+            pc: 0,
+            lr: 0,
+            cfsr: 0,
+            hfsr: 0,
+            mmfar: 0,
+            bfar: 0,
+            message: [0; MAX_CRASH_MESSAGE],
+            message_len: 0,
+        }
+    }
+}
+
+impl Message for CrashReport {
+    fn encode_fields(&self, w: &mut Writer) -> Result<(), EncodeError> {
+        w.uint32(1, self.kind)?;
+        w.uint32(2, self.uptime_ms)?;
+        w.uint32(3, self.pc)?;
+        w.uint32(4, self.lr)?;
+        w.uint32(5, self.cfsr)?;
+        w.uint32(6, self.hfsr)?;
+        w.uint32(7, self.mmfar)?;
+        w.uint32(8, self.bfar)?;
+        w.bytes(9, self.message())
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: WireType, r: &mut Reader) -> Result<(), DecodeError> {
+        match field {
+            1 => r.uint32(wire_type).map(|v| self.kind = v),
+            2 => r.uint32(wire_type).map(|v| self.uptime_ms = v),
+            3 => r.uint32(wire_type).map(|v| self.pc = v),
+            4 => r.uint32(wire_type).map(|v| self.lr = v),
+            5 => r.uint32(wire_type).map(|v| self.cfsr = v),
+            6 => r.uint32(wire_type).map(|v| self.hfsr = v),
+            7 => r.uint32(wire_type).map(|v| self.mmfar = v),
+            8 => r.uint32(wire_type).map(|v| self.bfar = v),
+            9 => r.bytes(wire_type).map(|v| self.set_message(v)),
+            _ => r.skip(wire_type),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<M: Message + PartialEq + core::fmt::Debug>(message: &M) {
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        let len = message.encode(&mut buf).unwrap();
+        assert_eq!(len, message.encoded_len());
+        assert_eq!(&M::decode(&buf[..len]).unwrap(), message);
+    }
+
+    fn buttons() -> SteerButtonState {
+        SteerButtonState {
+            cruise_up_on: true,
+            lock_on: true,
+            ptt_on: true,
+            right_turn_on: true,
+            led_state: 0b10_0101,
+            ..SteerButtonState::default()
+        }
+    }
+
+    fn sw_state() -> SwState {
+        SwState {
+            button_state: buttons(),
+            throttle: 0.42,
+            brake: 0.05,
+            screen: 2,
+            time_tracker_vc: 40,
+            time_tracker_bms: 1_250,
+            pedal_faults: 0b100,
+            dtcs: 0b1,
+            drive_mode: 2,
+            cruise_enabled: true,
+            cruise_speed: 45.5,
+        }
+    }
+
+    fn vc_state() -> VcState {
+        VcState {
+            speed: 44.8,
+            drive_mode: 2,
+            vc_lights: Lights {
+                left_turn: true,
+                brake: true,
+                headlights: true,
+                ..Lights::default()
+            },
+            left_motor_velocity: 45.0,
+            right_motor_velocity: -44.6,
+            cruise_enabled: true,
+            cruise_speed: 45.0,
+            regen_enabled: true,
+            throttle_enabled: true,
+            brake_pressed: false,
+            low_voltage: 12.6,
+            steering_angle: -3.5,
+        }
+    }
+
+    fn bms_state() -> BmsState {
+        BmsState {
+            voltage: 118.4,
+            current: -1.2,
+            flags: 0b1001,
+            min_cell_voltage: 3.94,
+            max_cell_voltage: 3.98,
+            max_temperature: 31.5,
+            soc: 0.87,
+        }
+    }
+
+    fn crash_report() -> CrashReport {
+        let mut report = CrashReport {
+            kind: 1,
+            uptime_ms: 73_412,
+            pc: 0x0800_4F2A,
+            lr: 0x0800_4E11,
+            cfsr: 1 << 9,
+            hfsr: 1 << 30,
+            mmfar: 0,
+            bfar: 0x2003_0000,
+            ..CrashReport::default()
+        };
+        report.set_message(b"called `Option::unwrap()` on a `None` value");
+        report
+    }
+
+    #[test]
+    fn every_message_round_trips() {
+        round_trip(&buttons());
+        round_trip(&sw_state());
+        round_trip(&vc_state().vc_lights);
+        round_trip(&vc_state());
+        round_trip(&bms_state());
+        round_trip(&crash_report());
+        round_trip(&DataMessage {
+            sw_state: Some(sw_state()),
+            vc_state: Some(vc_state()),
+            bms_state: Some(bms_state()),
+            crash_report: Some(crash_report()),
+        });
+    }
+
+    #[test]
+    fn empty_sections_keep_their_presence() {
+        round_trip(&DataMessage::default());
+        round_trip(&DataMessage::from_sw_state(SwState::default()));
+        round_trip(&DataMessage {
+            bms_state: Some(BmsState::default()),
+            ..DataMessage::default()
+        });
+    }
+
+    #[test]
+    fn sw_state_matches_nanopb_layout() {
+        let state = SwState {
+            throttle: 0.5,
+            screen: 3,
+            ..SwState::default()
+        };
+        let mut buf = [0u8; 16];
+        let len = DataMessage::from_sw_state(state).encode(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &[0x0A, 0x07, 0x15, 0x00, 0x00, 0x00, 0x3F, 0x20, 0x03]);
+    }
+
+    #[test]
+    fn unknown_fields_are_skipped() {
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        let mut writer = Writer::new(&mut buf);
+        // Fields a newer VC might send, before and after the known ones
+        writer.uint32(99, 150).unwrap();
+        writer.bytes(100, b"future").unwrap();
+        vc_state().encode_fields(&mut writer).unwrap();
+        writer.float(101, 1.5).unwrap();
+        let len = writer.position();
+        // Fixed64 field 102
+        let tail = [0xB1, 0x06, 1, 2, 3, 4, 5, 6, 7, 8];
+        buf[len..len + tail.len()].copy_from_slice(&tail);
+
+        assert_eq!(VcState::decode(&buf[..len + tail.len()]), Ok(vc_state()));
+    }
+
+    #[test]
+    fn unknown_sections_are_skipped() {
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        let mut writer = Writer::new(&mut buf);
+        writer.message(9, &bms_state()).unwrap();
+        writer.message(3, &bms_state()).unwrap();
+        let len = writer.position();
+
+        let decoded = DataMessage::decode(&buf[..len]).unwrap();
+        assert_eq!(decoded.bms_state, Some(bms_state()));
+        assert_eq!(decoded.sw_state, None);
+    }
+
+    #[test]
+    fn truncated_packets_are_rejected() {
+        let message = DataMessage {
+            sw_state: Some(sw_state()),
+            vc_state: Some(vc_state()),
+            ..DataMessage::default()
+        };
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        let len = message.encode(&mut buf).unwrap();
+        let sw_len = DataMessage::from_sw_state(sw_state()).encoded_len();
+
+        for cut in 1..len {
+            let result = DataMessage::decode(&buf[..cut]);
+            if cut == sw_len {
+                // Ends cleanly between the two sections
+                assert_eq!(result.map(|m| m.sw_state), Ok(Some(sw_state())));
+            } else {
+                assert_eq!(result, Err(DecodeError::Truncated), "cut at {}", cut);
+            }
+        }
+    }
+
+    #[test]
+    fn long_crash_messages_are_truncated() {
+        let mut report = CrashReport::default();
+        report.set_message(&[b'x'; MAX_CRASH_MESSAGE + 10]);
+        assert_eq!(report.message().len(), MAX_CRASH_MESSAGE);
+        round_trip(&report);
+    }
+
+    #[test]
+    fn small_buffers_are_reported() {
+        let message = DataMessage::from_sw_state(sw_state());
+        let mut buf = [0u8; 8];
+        assert_eq!(message.encode(&mut buf), Err(EncodeError::BufferTooSmall));
+    }
+}
@@ -0,0 +1,26 @@
+//! Network protocol for communicating with the VC and BMS
+//!
+//! Messages use the protobuf binary encoding so the steering wheel can talk
+//! to the existing nanopb-based nodes on the car network. Everything encodes
+//! into and decodes from caller-provided buffers - no allocation.
+//!
+//! # Module Structure
+//!
+//! - `codec` - Protobuf wire format primitives and the `Message` trait
+//! - `messages` - `DataMessage` and its sub-messages (see `messages.proto`)
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use embassy_vehiclecomputer::protocol::{DataMessage, Message, SwState, MAX_MESSAGE_SIZE};
+//!
+//! let mut buf = [0u8; MAX_MESSAGE_SIZE];
+//! let len = DataMessage::from_sw_state(SwState::default()).encode(&mut buf)?;
+//! let decoded = DataMessage::decode(&buf[..len])?;
+//! ```
+
+pub mod codec;
+pub mod messages;
+
+pub use codec::{DecodeError, EncodeError, Message};
+pub use messages::*;
@@ -0,0 +1,50 @@
+//! BMS cell detail screen
+
+use core::fmt::Write;
+use heapless::String;
+
+use super::{draw_text, Screen, ScreenContext};
+use crate::control::BmsFlags;
+use crate::drivers::display::layout::Cell;
+use crate::drivers::display::widget::{TextStyle, MID, WHITE};
+use crate::drivers::display::framebuffer::Framebuffer;
+
+/// Pack, cell and temperature readings with the decoded BMS flags
+#[derive(Default)]
+pub struct BmsScreen;
+
+impl Screen for BmsScreen {
+    fn render(&mut self, frame: &mut Framebuffer, ctx: &ScreenContext<'_>) {
+        let bms = &ctx.data.bms;
+        let mut line: String<32> = String::new();
+
+        write!(&mut line, "SOC{:5.1}% {:6.1}v", bms.soc, bms.voltage).ok();
+        draw_text(frame, Cell::new(0, 0), TextStyle::new(WHITE), &line);
+
+        line.clear();
+        write!(&mut line, "CELL {:.3}-{:.3}v", bms.min_cell_voltage, bms.max_cell_voltage).ok();
+        draw_text(frame, Cell::new(0, 1), TextStyle::new(WHITE), &line);
+
+        line.clear();
+        let delta_mv = (bms.max_cell_voltage - bms.min_cell_voltage) * 1000.0;
+        write!(&mut line, "DELTA{:4.0}mV T{:5.1}C", delta_mv, bms.max_temperature).ok();
+        draw_text(frame, Cell::new(0, 2), TextStyle::new(WHITE), &line);
+
+        line.clear();
+        write!(&mut line, "{:6.1}A", bms.current).ok();
+        draw_text(frame, Cell::new(0, 3), TextStyle::new(MID), &line);
+
+        // Stale data is called out before any flags it might still show
+        let status = Cell::new(9, 3);
+        let flags = BmsFlags::from_bits(bms.flags);
+        if ctx.data.steering.failsafe.bms_lost {
+            draw_text(frame, status, TextStyle::new(WHITE).inverted(), "NO DATA");
+        } else if ctx.data.steering.bms_alert.tripped() {
+            draw_text(frame, status, TextStyle::new(WHITE).inverted(), flags.reason());
+        } else if !flags.is_empty() {
+            draw_text(frame, status, TextStyle::new(WHITE), flags.reason());
+        } else {
+            draw_text(frame, status, TextStyle::new(MID), "FLAGS OK");
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Pedal debug screen
+
+use core::fmt::Write;
+use heapless::String;
+
+use super::{Screen, ScreenContext, BMS_TIMEOUT_MS, VC_TIMEOUT_MS};
+use crate::drivers::display::layout::debug as layout;
+use crate::drivers::display::widget::*;
+use crate::drivers::display::framebuffer::Framebuffer;
+
+
+/// Normalized and raw pedal readings plus link timeouts
+#[derive(Default)]
+pub struct DebugScreen;
+
+impl Screen for DebugScreen {
+    fn render(&mut self, frame: &mut Framebuffer, ctx: &ScreenContext<'_>) {
+        let steering = &ctx.data.steering;
+        let style = TextStyle::new(WHITE);
+
+        Label::new("DEBUG", style).draw(frame, layout::TITLE).ok();
+        TimeoutBox::new("VC", ctx.time_since_vc, VC_TIMEOUT_MS)
+            .draw(frame, layout::VC_TIMEOUT)
+            .ok();
+        TimeoutBox::new("BMS", ctx.time_since_bms, BMS_TIMEOUT_MS)
+            .draw(frame, layout::BMS_TIMEOUT)
+            .ok();
+
+        let mut line: String<24> = String::new();
+        write!(&mut line, "THR {:.3} {:5}", steering.throttle, steering.raw_throttle).ok();
+        Label::new(&line, style).draw(frame, layout::THROTTLE).ok();
+        BarGauge::new(steering.throttle).draw(frame, layout::THROTTLE_BAR).ok();
+
+        line.clear();
+        write!(&mut line, "REG {:.3} {:5}", steering.brake, steering.raw_brake).ok();
+        Label::new(&line, style).draw(frame, layout::REGEN).ok();
+        BarGauge::new(steering.brake).draw(frame, layout::REGEN_BAR).ok();
+
+        // Net pedal command in percent: throttle minus regen
+        line.clear();
+        let pedal = (steering.throttle - steering.brake) * 100.0;
+        write!(&mut line, "PED {:6.1}%", pedal).ok();
+        Label::new(&line, style).draw(frame, layout::PEDAL).ok();
+    }
+}
@@ -0,0 +1,140 @@
+//! Driving screen
+
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+use super::{Screen, ScreenContext, BMS_TIMEOUT_MS, VC_TIMEOUT_MS};
+use crate::control::{FailsafeState, FailsafeStatus};
+use crate::drivers::display::layout::main as layout;
+use crate::drivers::display::widget::*;
+use crate::drivers::display::framebuffer::Framebuffer;
+
+/// Link alerts flash inverted at 2Hz
+const ALERT_PERIOD_MS: u32 = 500;
+
+
+/// Pedal position above which the R/T indicators show the pedal as pressed
+const PEDAL_PRESSED: f32 = 0.2;
+
+/// Speed, drive mode, cruise, pack readings and alerts
+#[derive(Default)]
+pub struct MainScreen;
+
+impl Screen for MainScreen {
+    fn render(&mut self, frame: &mut Framebuffer, ctx: &ScreenContext<'_>) {
+        let data = ctx.data;
+        let steering = &data.steering;
+
+        // Same blink phase as the button LEDs
+        let (left_on, right_on) = steering.turn.lamps(ctx.now);
+        // Static decorations are dimmed after a while without input, except
+        // while they are showing something the driver has to notice
+        let dim = ctx.dim_static;
+        let arrow = |image, on| Dimmed::new(Icon::new(image, if on { WHITE } else { LOW }), dim && !on);
+        arrow(&LEFT_ARROW_ICON, left_on).draw(frame, layout::LEFT_TURN).ok();
+        arrow(&RIGHT_ARROW_ICON, right_on).draw(frame, layout::RIGHT_TURN).ok();
+
+        let speed = data.vc.left_motor_velocity.max(data.vc.right_motor_velocity).abs();
+        Readout::new(speed, "", TextStyle::new(WHITE).large())
+            .digits(2, 0)
+            .draw(frame, layout::SPEED)
+            .ok();
+
+        let throttle_pressed = steering.throttle > PEDAL_PRESSED;
+        let regen = pedal_indicator("R", data.vc.regen_enabled, data.vc.brake_pressed, throttle_pressed);
+        Dimmed::new(regen, dim).draw(frame, layout::REGEN).ok();
+        let throttle = pedal_indicator("T", data.vc.throttle_enabled, throttle_pressed, throttle_pressed);
+        Dimmed::new(throttle, dim).draw(frame, layout::THROTTLE).ok();
+
+        if !steering.pedal_faults.is_empty() {
+            Label::new(steering.pedal_faults.label(), TextStyle::new(WHITE).inverted())
+                .draw(frame, layout::PEDAL_FAULT)
+                .ok();
+        }
+
+        let lock = if steering.buttons.lock_on { WHITE } else { VLOW };
+        Dimmed::new(Icon::new(&LOCK_ICON, lock), dim).draw(frame, layout::LOCK).ok();
+
+        let value = TextStyle::new(WHITE).align(Align::Right);
+        Readout::new(data.bms.current.abs(), "A", value).draw(frame, layout::CURRENT).ok();
+        Readout::new(data.bms.voltage, "v", value).draw(frame, layout::HIGH_VOLTAGE).ok();
+        Readout::new(data.vc.low_voltage, "v", value).draw(frame, layout::LOW_VOLTAGE).ok();
+
+        let cruise = if steering.cruise_engaged() { WHITE } else { LOW };
+        Readout::new(steering.cruise.target().abs(), "", TextStyle::new(cruise))
+            .digits(2, 0)
+            .draw(frame, layout::CRUISE)
+            .ok();
+        Dimmed::new(Selector::new("DRCN", steering.drive.mode() as usize), dim)
+            .draw(frame, layout::DRIVE_STATE)
+            .ok();
+
+        // An acknowledged BPS trip outranks the link alerts in the same spot
+        if steering.bms_alert.tripped() {
+            Label::new(steering.bms_alert.reason(), TextStyle::new(WHITE).inverted())
+                .draw(frame, layout::ALERT)
+                .ok();
+        } else if let Some(alert) = failsafe_alert(steering.failsafe, ctx.current_time) {
+            let label = Label::new(alert, TextStyle::new(WHITE).inverted());
+            BlinkIndicator::new(label, ALERT_PERIOD_MS, BlinkMode::Invert, ctx.current_time)
+                .draw(frame, layout::ALERT)
+                .ok();
+        }
+
+        let vc = TimeoutBox::new("VC", ctx.time_since_vc, VC_TIMEOUT_MS);
+        Dimmed::new(vc, dim && !vc.timed_out()).draw(frame, layout::VC_TIMEOUT).ok();
+        let bms = TimeoutBox::new("BMS", ctx.time_since_bms, BMS_TIMEOUT_MS);
+        Dimmed::new(bms, dim && !bms.timed_out()).draw(frame, layout::BMS_TIMEOUT).ok();
+    }
+}
+
+/// R/T letter: barely visible when disabled, white while in use, with a
+/// dot above it while the throttle is pressed
+fn pedal_indicator(letter: &str, enabled: bool, engaged: bool, pressed: bool) -> PedalIndicator<'_> {
+    let shade = if !enabled {
+        VLOW
+    } else if engaged {
+        WHITE
+    } else {
+        MID
+    };
+    PedalIndicator {
+        label: Label::new(letter, TextStyle::new(shade)),
+        pressed,
+    }
+}
+
+/// Pedal letter plus its pressed dot
+struct PedalIndicator<'a> {
+    label: Label<'a>,
+    pressed: bool,
+}
+
+impl Widget for PedalIndicator<'_> {
+    fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        self.label.draw(target, area)?;
+        let dot = area.top_left + Point::new(1, 0);
+        target.draw_iter([Pixel(dot, if self.pressed { WHITE } else { BLACK })])
+    }
+}
+
+/// Text of the communication-loss alert, if any
+///
+/// VC loss and re-arm take the slot first; a lost BMS alternates with them
+/// every second so both stay visible.
+fn failsafe_alert(status: FailsafeStatus, current_time: u32) -> Option<&'static str> {
+    let vc_text = match status.state {
+        FailsafeState::VcLost => Some(" VC LOST"),
+        FailsafeState::AwaitingRearm => Some("LIFT THR"),
+        FailsafeState::Armed => None,
+    };
+    let bms_text = status.bms_alert.then_some("BMS LOST");
+    match (vc_text, bms_text) {
+        (Some(vc), Some(bms)) => Some(if (current_time / 1000).is_multiple_of(2) { vc } else { bms }),
+        (text, None) | (None, text) => text,
+    }
+}
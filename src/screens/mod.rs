@@ -0,0 +1,164 @@
+//! Display screens
+//!
+//! Each screen draws one page of the display and may react to button
+//! gestures. `ScreenManager` owns one instance of every screen, looked up by
+//! `ScreenId`. The active screen is `SteeringState::screen`; the screen chord
+//! cycles it and it is reported in SW_State.
+//!
+//! # Module Structure
+//!
+//! - `main` - Driving screen: speed, drive mode, pack and link status
+//! - `debug` - Pedal values and raw ADC counts
+//! - `bms` - Cell voltages, temperature and BMS flags
+//! - `network` - Link state, peer message ages and UDP send counters
+//! - `settings` - Settings adjustable from the wheel while parked
+//! - `screensaver` - Dim wandering lock and voltage, shown parked and locked
+
+mod bms;
+mod debug;
+mod main;
+mod network;
+mod screensaver;
+mod settings;
+
+pub use bms::BmsScreen;
+pub use debug::DebugScreen;
+pub use main::MainScreen;
+pub use network::NetworkScreen;
+pub use screensaver::Screensaver;
+pub use settings::SettingsScreen;
+
+use embassy_time::Instant;
+
+use crate::drivers::buttons::GestureEvent;
+use crate::drivers::display::layout::{Cell, COLUMNS};
+use crate::drivers::display::widget::{Label, TextStyle, Widget};
+use crate::drivers::display::framebuffer::Framebuffer;
+use crate::drivers::network::{Destination, SendStats};
+use crate::state::StateData;
+
+/// VC/BMS timeouts shown in the link boxes (the failsafe defaults)
+const VC_TIMEOUT_MS: u32 = 300;
+const BMS_TIMEOUT_MS: u32 = 1000;
+
+/// Screens in the order the chord cycles through them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+#[repr(u32)]
+pub enum ScreenId {
+    #[default]
+    Main = 0,
+    Debug = 1,
+    Bms = 2,
+    Network = 3,
+    Settings = 4,
+}
+
+impl ScreenId {
+    pub const ALL: [ScreenId; 5] = [
+        ScreenId::Main,
+        ScreenId::Debug,
+        ScreenId::Bms,
+        ScreenId::Network,
+        ScreenId::Settings,
+    ];
+
+    pub fn from_raw(value: u32) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    /// The screen after this one, wrapping around
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ScreenId::Main => "MAIN",
+            ScreenId::Debug => "DEBUG",
+            ScreenId::Bms => "BMS",
+            ScreenId::Network => "NETWORK",
+            ScreenId::Settings => "SETTINGS",
+        }
+    }
+}
+
+/// Link state and send counters for the network screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkStatus {
+    pub link_up: bool,
+    /// Send counters, indexed like `Destination::ALL`
+    pub sent: [SendStats; Destination::ALL.len()],
+}
+
+impl NetworkStatus {
+    pub fn stats(&self, destination: Destination) -> SendStats {
+        self.sent[destination as usize]
+    }
+}
+
+/// Everything a screen draws from, gathered once per frame
+pub struct ScreenContext<'a> {
+    pub data: &'a StateData,
+    pub now: Instant,
+    /// Milliseconds since the display started, for flashing elements
+    pub current_time: u32,
+    /// Milliseconds since the last VC message
+    pub time_since_vc: u32,
+    /// Milliseconds since the last BMS message
+    pub time_since_bms: u32,
+    pub network: NetworkStatus,
+    /// Draw static decorations dimmed (no recent input, see `BurnIn`)
+    pub dim_static: bool,
+}
+
+/// A page of the display
+pub trait Screen {
+    /// Draw into the (already cleared) framebuffer
+    fn render(&mut self, frame: &mut Framebuffer, ctx: &ScreenContext<'_>);
+
+    /// React to a button gesture while this screen is shown
+    ///
+    /// Gestures are also handled by the button task, so screens should only
+    /// use buttons that do nothing in the current drive mode. Returns true if
+    /// the gesture was used.
+    fn handle_input(&mut self, _event: GestureEvent, _data: &mut StateData) -> bool {
+        false
+    }
+}
+
+/// One instance of every screen
+#[derive(Default)]
+pub struct ScreenManager {
+    main: MainScreen,
+    debug: DebugScreen,
+    bms: BmsScreen,
+    network: NetworkScreen,
+    settings: SettingsScreen,
+    screensaver: Screensaver,
+}
+
+impl ScreenManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&mut self, id: ScreenId) -> &mut dyn Screen {
+        match id {
+            ScreenId::Main => &mut self.main,
+            ScreenId::Debug => &mut self.debug,
+            ScreenId::Bms => &mut self.bms,
+            ScreenId::Network => &mut self.network,
+            ScreenId::Settings => &mut self.settings,
+        }
+    }
+
+    /// Shown instead of the selected screen while the screensaver is on
+    pub fn screensaver(&mut self) -> &mut dyn Screen {
+        &mut self.screensaver
+    }
+}
+
+/// Draw `text` from `cell` to the end of its row
+fn draw_text(frame: &mut Framebuffer, cell: Cell, style: TextStyle, text: &str) {
+    Label::new(text, style).draw(frame, cell.span(COLUMNS - cell.col)).ok();
+}
@@ -0,0 +1,56 @@
+//! Network diagnostics screen
+
+use core::fmt::Write;
+use heapless::String;
+
+use super::{draw_text, Screen, ScreenContext};
+use crate::drivers::display::layout::Cell;
+use crate::drivers::display::widget::{TextStyle, MID, WHITE};
+use crate::drivers::display::framebuffer::Framebuffer;
+use crate::drivers::network::{Destination, IP_ADDRESS};
+
+/// Ages above this are shown as lost
+const MAX_AGE_MS: u32 = 9999;
+
+/// Link state, time since each peer was heard from and UDP send counters
+#[derive(Default)]
+pub struct NetworkScreen;
+
+impl Screen for NetworkScreen {
+    fn render(&mut self, frame: &mut Framebuffer, ctx: &ScreenContext<'_>) {
+        let network = &ctx.network;
+        let mut line: String<32> = String::new();
+
+        let (link, shade) = if network.link_up {
+            ("LINK UP", WHITE)
+        } else {
+            ("LINK DN", MID)
+        };
+        draw_text(frame, Cell::new(0, 0), TextStyle::new(shade), link);
+        write!(&mut line, "{}", IP_ADDRESS).ok();
+        draw_text(frame, Cell::new(9, 0), TextStyle::new(MID), &line);
+
+        // One row per destination: time since last heard, then sent/failed
+        let rows = [
+            ("VC", Some(ctx.time_since_vc), Destination::Vc),
+            ("BMS", Some(ctx.time_since_bms), Destination::Bms),
+            ("TLM", None, Destination::Telemetry),
+        ];
+        for (row, (label, age, destination)) in rows.into_iter().enumerate() {
+            let stats = network.stats(destination);
+            let lost = age.is_some_and(|age| age > MAX_AGE_MS);
+
+            line.clear();
+            write!(&mut line, "{:<4}", label).ok();
+            match age {
+                Some(age) if age <= MAX_AGE_MS => write!(&mut line, "{:>4}ms", age).ok(),
+                Some(_) => write!(&mut line, "  LOST").ok(),
+                None => write!(&mut line, "      ").ok(),
+            };
+            write!(&mut line, " {:>5}/{}", stats.sent, stats.failed()).ok();
+
+            let shade = if lost { MID } else { WHITE };
+            draw_text(frame, Cell::new(0, row + 1), TextStyle::new(shade), &line);
+        }
+    }
+}
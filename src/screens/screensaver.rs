@@ -0,0 +1,50 @@
+//! Parked screensaver
+//!
+//! Shown instead of the selected screen while parked with the lock on and no
+//! recent input (see `BurnIn`). Only the lock icon and pack voltage are lit,
+//! in a dim shade, and they jump to a new spot every few seconds so no pixel
+//! stays on for long. Any button press brings the screen back.
+
+use embassy_time::Instant;
+
+use super::{Screen, ScreenContext};
+use crate::drivers::display::framebuffer::Framebuffer;
+use crate::drivers::display::font16::{FONT_HEIGHT, FONT_WIDTH};
+use crate::drivers::display::layout::pixels;
+use crate::drivers::display::geometry::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::drivers::display::widget::{Icon, Readout, TextStyle, Widget, LOCK_ICON, LOW};
+
+/// Time the block stays in one spot
+const HOP_PERIOD_MS: u64 = 10_000;
+
+/// Lock icon cell plus a five character voltage readout and its unit
+const BLOCK_WIDTH: usize = 7 * FONT_WIDTH;
+const BLOCK_HEIGHT: usize = FONT_HEIGHT;
+
+/// Lock icon and pack voltage wandering over a black screen
+#[derive(Default)]
+pub struct Screensaver;
+
+impl Screen for Screensaver {
+    fn render(&mut self, frame: &mut Framebuffer, ctx: &ScreenContext<'_>) {
+        let (x, y) = position(ctx.now);
+        Icon::new(&LOCK_ICON, LOW)
+            .draw(frame, pixels(x, y, FONT_WIDTH as u32, BLOCK_HEIGHT as u32))
+            .ok();
+        let voltage = pixels(x + FONT_WIDTH as i32, y, (BLOCK_WIDTH - FONT_WIDTH) as u32, BLOCK_HEIGHT as u32);
+        Readout::new(ctx.data.bms.voltage, "v", TextStyle::new(LOW))
+            .draw(frame, voltage)
+            .ok();
+    }
+}
+
+/// Top left of the block at `now`
+///
+/// Strides coprime to the free area's width and height, so the block keeps
+/// landing somewhere new rather than cycling between a few spots.
+fn position(now: Instant) -> (i32, i32) {
+    let hop = now.as_millis() / HOP_PERIOD_MS;
+    let x = hop * 37 % (DISPLAY_WIDTH - BLOCK_WIDTH + 1) as u64;
+    let y = hop * 11 % (DISPLAY_HEIGHT - BLOCK_HEIGHT + 1) as u64;
+    (x as i32, y as i32)
+}
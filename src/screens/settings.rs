@@ -0,0 +1,194 @@
+//! Settings screen
+//!
+//! Only accepts input in neutral, where the cruise buttons do nothing else.
+//! Cruise up/down move the cursor, holding them raises/lowers the selected
+//! value. The list scrolls when the cursor moves past the last row. Changes
+//! last until the next reset.
+
+use core::fmt::Write;
+use embassy_time::Duration;
+use heapless::String;
+
+use super::{draw_text, Screen, ScreenContext};
+use crate::control::{BrightnessPreset, DriveState};
+use crate::drivers::buttons::{ButtonId, GestureEvent};
+use crate::drivers::display::layout::{Cell, COLUMNS, ROWS};
+use crate::drivers::display::widget::{TextStyle, MID, WHITE};
+use crate::drivers::display::framebuffer::Framebuffer;
+use crate::state::StateData;
+
+/// Turn signal auto-cancel choices, in seconds (0 = off)
+const TURN_TIMEOUTS: [u64; 5] = [0, 10, 20, 30, 60];
+
+/// Display auto-dim choices, in seconds (0 = off)
+const DIM_TIMEOUTS: [u64; 5] = [0, 30, 60, 120, 300];
+
+/// Pixel shift step choices, in seconds (0 = off)
+const SHIFT_PERIODS: [u64; 5] = [0, 30, 60, 120, 300];
+
+/// Screensaver delay choices, in seconds (0 = off)
+const SCREENSAVER_TIMEOUTS: [u64; 5] = [0, 30, 60, 120, 300];
+
+/// Settings shown below the title row
+const VISIBLE: usize = ROWS - 1;
+
+/// A value adjustable from the settings screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    CruiseStep,
+    CruiseRamp,
+    TurnCancel,
+    Brightness,
+    AutoDim,
+    PixelShift,
+    Screensaver,
+}
+
+impl Setting {
+    const ALL: [Setting; 7] = [
+        Setting::CruiseStep,
+        Setting::CruiseRamp,
+        Setting::TurnCancel,
+        Setting::Brightness,
+        Setting::AutoDim,
+        Setting::PixelShift,
+        Setting::Screensaver,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Setting::CruiseStep => "Cruise step",
+            Setting::CruiseRamp => "Cruise ramp",
+            Setting::TurnCancel => "Turn cancel",
+            Setting::Brightness => "Brightness",
+            Setting::AutoDim => "Auto dim",
+            Setting::PixelShift => "Pixel shift",
+            Setting::Screensaver => "Screensaver",
+        }
+    }
+
+    /// Raise (`up`) or lower the value one notch, clamped to its range
+    fn adjust(self, data: &mut StateData, up: bool) {
+        let cruise = &mut data.steering.cruise.config;
+        match self {
+            Setting::CruiseStep => cruise.step = nudge(cruise.step, 0.5, 0.5, 5.0, up),
+            Setting::CruiseRamp => cruise.ramp_step = nudge(cruise.ramp_step, 0.25, 0.25, 2.0, up),
+            Setting::TurnCancel => {
+                let turn = &mut data.steering.turn.config;
+                turn.timeout = step_timeout(turn.timeout, &TURN_TIMEOUTS, up);
+            }
+            Setting::Brightness => {
+                let dimming = &mut data.steering.dimming;
+                dimming.preset = if up {
+                    BrightnessPreset::Day
+                } else {
+                    BrightnessPreset::Night
+                };
+            }
+            Setting::AutoDim => {
+                let dimming = &mut data.steering.dimming.config;
+                dimming.idle_timeout = step_timeout(dimming.idle_timeout, &DIM_TIMEOUTS, up);
+            }
+            Setting::PixelShift => {
+                let burn_in = &mut data.steering.burn_in;
+                burn_in.shift_period = step_timeout(burn_in.shift_period, &SHIFT_PERIODS, up);
+            }
+            Setting::Screensaver => {
+                let burn_in = &mut data.steering.burn_in;
+                burn_in.screensaver_after = step_timeout(burn_in.screensaver_after, &SCREENSAVER_TIMEOUTS, up);
+            }
+        }
+    }
+
+    fn write_value(self, data: &StateData, out: &mut String<8>) {
+        match self {
+            Setting::CruiseStep => write!(out, "{:.1}", data.steering.cruise.config.step),
+            Setting::CruiseRamp => write!(out, "{:.2}", data.steering.cruise.config.ramp_step),
+            Setting::TurnCancel => write_timeout(data.steering.turn.config.timeout, out),
+            Setting::Brightness => write!(out, "{}", data.steering.dimming.preset.label()),
+            Setting::AutoDim => write_timeout(data.steering.dimming.config.idle_timeout, out),
+            Setting::PixelShift => write_timeout(data.steering.burn_in.shift_period, out),
+            Setting::Screensaver => write_timeout(data.steering.burn_in.screensaver_after, out),
+        }
+        .ok();
+    }
+}
+
+/// Move `current` one choice along `choices` (seconds, 0 = off)
+fn step_timeout(current: Option<Duration>, choices: &[u64], up: bool) -> Option<Duration> {
+    let current = current.map_or(0, |timeout| timeout.as_secs());
+    let index = choices.iter().position(|t| *t >= current).unwrap_or(0);
+    let index = if up {
+        (index + 1).min(choices.len() - 1)
+    } else {
+        index.saturating_sub(1)
+    };
+    match choices[index] {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+fn write_timeout(timeout: Option<Duration>, out: &mut String<8>) -> core::fmt::Result {
+    match timeout {
+        Some(timeout) => write!(out, "{}s", timeout.as_secs()),
+        None => write!(out, "OFF"),
+    }
+}
+
+fn nudge(value: f32, step: f32, min: f32, max: f32, up: bool) -> f32 {
+    let value = if up { value + step } else { value - step };
+    value.clamp(min, max)
+}
+
+/// Cursor over the adjustable settings
+#[derive(Default)]
+pub struct SettingsScreen {
+    selected: usize,
+}
+
+impl Screen for SettingsScreen {
+    fn render(&mut self, frame: &mut Framebuffer, ctx: &ScreenContext<'_>) {
+        let parked = ctx.data.steering.drive.mode() == DriveState::Neutral;
+
+        draw_text(frame, Cell::new(0, 0), TextStyle::new(WHITE), "SETTINGS");
+        let (hint, shade) = if parked {
+            ("HOLD=SET", MID)
+        } else {
+            ("NEUTRAL", WHITE)
+        };
+        draw_text(frame, Cell::new(COLUMNS - hint.len(), 0), TextStyle::new(shade), hint);
+
+        // Scroll just far enough to keep the cursor on screen
+        let first = self.selected.saturating_sub(VISIBLE - 1);
+        let mut value: String<8> = String::new();
+        for (row, setting) in Setting::ALL.into_iter().enumerate().skip(first).take(VISIBLE) {
+            let cell = Cell::new(0, row - first + 1);
+            let selected = row == self.selected;
+            let style = TextStyle::new(if parked { WHITE } else { MID });
+
+            draw_text(frame, cell, style, if selected { ">" } else { " " });
+            draw_text(frame, cell.right(1), style, setting.label());
+
+            value.clear();
+            setting.write_value(ctx.data, &mut value);
+            draw_text(frame, Cell::new(COLUMNS - value.len(), cell.row), style, &value);
+        }
+    }
+
+    fn handle_input(&mut self, event: GestureEvent, data: &mut StateData) -> bool {
+        if data.steering.drive.mode() != DriveState::Neutral {
+            return false;
+        }
+
+        let count = Setting::ALL.len();
+        match event {
+            GestureEvent::ShortPress(ButtonId::CruiseUp) => self.selected = (self.selected + count - 1) % count,
+            GestureEvent::ShortPress(ButtonId::CruiseDown) => self.selected = (self.selected + 1) % count,
+            GestureEvent::LongPress(ButtonId::CruiseUp) => Setting::ALL[self.selected].adjust(data, true),
+            GestureEvent::LongPress(ButtonId::CruiseDown) => Setting::ALL[self.selected].adjust(data, false),
+            _ => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,252 @@
+//! Shared vehicle state
+//!
+//! Steering wheel inputs plus the state received from the other nodes on the
+//! car network. The authoritative copy lives behind a mutex; every change is
+//! also published through a `Watch` so consumers can wait for updates instead
+//! of polling. The VC/BMS message timestamps move with every packet, so they
+//! are kept up to date in the `Watch` but don't wake subscribers by
+//! themselves.
+//!
+//! Producers: button task, pedal sampler, network receive task.
+//! Consumers: steering update (on change), display, telemetry, LEDs.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! let state = state::init();
+//! spawner.spawn(tasks::network_receive_task(stack, state)).unwrap();
+//!
+//! // Producer:
+//! state.update_steering(|steering| steering.set_button(ButtonId::Horn, true)).await;
+//!
+//! // Consumer that only wakes up on changes:
+//! let mut receiver = state.subscribe().unwrap();
+//! loop {
+//!     let snapshot = receiver.changed().await;
+//! }
+//! ```
+
+mod steering;
+
+pub use steering::SteeringState;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_sync::watch::{Receiver, Watch};
+use embassy_time::Instant;
+use static_cell::StaticCell;
+
+use crate::control::{BmsFlags, DriveInputs, DriveRejection, DriveState};
+use crate::protocol::{BmsState, SwState, VcState};
+
+/// Maximum number of tasks that can subscribe to state changes
+pub const MAX_SUBSCRIBERS: usize = 4;
+
+/// Receiver handed out by `SharedState::subscribe`
+pub type StateReceiver<'a> = Receiver<'a, CriticalSectionRawMutex, StateData, MAX_SUBSCRIBERS>;
+
+/// Everything the steering wheel knows about itself and the rest of the car
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StateData {
+    /// Inputs read on the steering wheel
+    pub steering: SteeringState,
+    /// Latest state reported by the Vehicle Computer
+    pub vc: VcState,
+    /// Latest state reported by the Battery Management System
+    pub bms: BmsState,
+    /// When the last VC message arrived (None if never)
+    pub last_vc_message: Option<Instant>,
+    /// When the last BMS message arrived (None if never)
+    pub last_bms_message: Option<Instant>,
+}
+
+impl StateData {
+    /// Milliseconds since the last VC message, saturating at `u32::MAX`
+    pub fn time_since_vc(&self, now: Instant) -> u32 {
+        elapsed_millis(self.last_vc_message, now)
+    }
+
+    /// Milliseconds since the last BMS message, saturating at `u32::MAX`
+    pub fn time_since_bms(&self, now: Instant) -> u32 {
+        elapsed_millis(self.last_bms_message, now)
+    }
+
+    /// Inputs the drive mode interlocks look at
+    pub fn drive_inputs(&self) -> DriveInputs {
+        DriveInputs {
+            speed: self.vc.speed,
+            brake: self.steering.brake,
+            brake_input: self.steering.brake_input,
+            regen: self.vc.regen_enabled,
+            force_neutral: self.steering.failsafe.force_neutral,
+        }
+    }
+
+    /// Ask for a new drive mode, subject to the interlocks
+    pub fn request_drive_mode(&mut self, request: DriveState) -> Result<DriveState, DriveRejection> {
+        let inputs = self.drive_inputs();
+        self.steering.drive.request(request, inputs)
+    }
+
+    /// Apply forced drive mode transitions; returns the new mode if it changed
+    ///
+    /// Call after anything the interlocks look at changes.
+    pub fn update_drive_mode(&mut self) -> Option<DriveState> {
+        let inputs = self.drive_inputs();
+        self.steering.drive.update(inputs)
+    }
+
+    /// True if anything besides the VC/BMS message timestamps differs
+    fn content_differs(&self, other: &StateData) -> bool {
+        let without_timestamps = |data: &StateData| StateData {
+            last_vc_message: None,
+            last_bms_message: None,
+            ..*data
+        };
+        without_timestamps(self) != without_timestamps(other)
+    }
+
+    /// Build the SW_State message for the current steering state
+    pub fn sw_state(&self, now: Instant) -> SwState {
+        self.steering
+            .to_sw_state(now, self.time_since_vc(now), self.time_since_bms(now))
+    }
+}
+
+fn elapsed_millis(last: Option<Instant>, now: Instant) -> u32 {
+    match last {
+        Some(last) => now.saturating_duration_since(last).as_millis().min(u32::MAX as u64) as u32,
+        None => u32::MAX,
+    }
+}
+
+/// Mutex-protected state shared between tasks, with change notification
+pub struct SharedState {
+    inner: Mutex<CriticalSectionRawMutex, StateData>,
+    changes: Watch<CriticalSectionRawMutex, StateData, MAX_SUBSCRIBERS>,
+}
+
+impl SharedState {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(StateData::default()),
+            changes: Watch::new_with(StateData::default()),
+        }
+    }
+
+    /// Take a copy of the current state
+    pub async fn snapshot(&self) -> StateData {
+        *self.inner.lock().await
+    }
+
+    /// Subscribe to state changes
+    ///
+    /// Returns None if all `MAX_SUBSCRIBERS` receivers are already taken.
+    pub fn subscribe(&self) -> Option<StateReceiver<'_>> {
+        self.changes.receiver()
+    }
+
+    /// Modify the state and notify subscribers if anything changed
+    ///
+    /// A change to the message timestamps alone is stored without waking
+    /// anyone.
+    pub async fn update<R>(&self, f: impl FnOnce(&mut StateData) -> R) -> R {
+        let mut data = self.inner.lock().await;
+        let before = *data;
+        let result = f(&mut data);
+        if *data != before {
+            let after = *data;
+            let notify = after.content_differs(&before);
+            self.changes.sender().send_if_modified(|value| {
+                *value = Some(after);
+                notify
+            });
+        }
+        result
+    }
+
+    /// Modify the steering wheel inputs
+    pub async fn update_steering<R>(&self, f: impl FnOnce(&mut SteeringState) -> R) -> R {
+        self.update(|data| f(&mut data.steering)).await
+    }
+
+    /// Record a new VC state received at `received_at`
+    ///
+    /// Also feeds the VC's lamps and steering angle to the turn signals;
+    /// returns true if the steering cancelled the turn signal.
+    pub async fn update_vc(&self, vc: VcState, received_at: Instant) -> bool {
+        self.update(|data| {
+            data.vc = vc;
+            data.last_vc_message = Some(received_at);
+
+            let lights = &vc.vc_lights;
+            let lamps_on = lights.left_turn || lights.right_turn || lights.hazards;
+            data.steering.turn.vc_update(lamps_on, vc.steering_angle, received_at)
+        })
+        .await
+    }
+
+    /// Record a new BMS state received at `received_at`
+    ///
+    /// Returns true if the BMS flags raised the BPS trip alert.
+    pub async fn update_bms(&self, bms: BmsState, received_at: Instant) -> bool {
+        self.update(|data| {
+            data.bms = bms;
+            data.last_bms_message = Some(received_at);
+            data.steering.bms_alert.update(BmsFlags::from_bits(bms.flags))
+        })
+        .await
+    }
+}
+
+impl Default for SharedState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Static storage for the shared state
+static STATE: StaticCell<SharedState> = StaticCell::new();
+
+/// Create the shared state - call once from main and hand the reference to tasks
+pub fn init() -> &'static SharedState {
+    STATE.init(SharedState::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::buttons::ButtonId;
+
+    #[test]
+    fn timestamps_alone_are_not_a_change() {
+        let before = StateData::default();
+        let mut after = before;
+        after.last_vc_message = Some(Instant::from_millis(40));
+        after.last_bms_message = Some(Instant::from_millis(50));
+        assert!(!after.content_differs(&before));
+
+        after.vc.speed = 12.0;
+        assert!(after.content_differs(&before));
+    }
+
+    #[test]
+    fn reverse_is_only_reported_in_reverse() {
+        let mut data = StateData::default();
+        data.steering.failsafe.force_neutral = false;
+        data.steering.brake = 1.0;
+        data.steering.set_button(ButtonId::Reverse, true);
+        let now = Instant::from_millis(0);
+        assert!(!data.sw_state(now).button_state.reverse_on);
+
+        assert_eq!(data.request_drive_mode(DriveState::Reverse), Ok(DriveState::Reverse));
+        assert!(data.sw_state(now).button_state.reverse_on);
+
+        // The failsafe forcing neutral clears it, even with the button held
+        data.steering.failsafe.force_neutral = true;
+        assert_eq!(data.update_drive_mode(), Some(DriveState::Neutral));
+        let sw_state = data.sw_state(now);
+        assert!(!sw_state.button_state.reverse_on);
+        assert_eq!(sw_state.drive_mode, DriveState::Neutral as u32);
+    }
+}
@@ -0,0 +1,129 @@
+//! Steering wheel input state
+
+use embassy_time::Instant;
+
+use crate::control::{BmsAlert, BurnIn, CruiseControl, Dimming, DriveModeController, DriveState, FailsafeStatus, TurnSignals};
+use crate::drivers::buttons::ButtonId;
+use crate::drivers::pedals::PedalFaults;
+use crate::protocol::{SteerButtonState, SwState};
+use crate::screens::ScreenId;
+
+/// Inputs read on the steering wheel itself
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SteeringState {
+    /// Debounced regular buttons and current toggle states
+    pub buttons: SteerButtonState,
+    /// Normalized throttle pedal position (0.0 - 1.0)
+    pub throttle: f32,
+    /// Normalized brake/regen pedal position (0.0 - 1.0)
+    pub brake: f32,
+    /// Normalized throttle position as read, before pedal faults or a
+    /// calibration capture force it to zero
+    pub throttle_input: f32,
+    /// Normalized brake position as read, likewise
+    pub brake_input: f32,
+    /// Filtered throttle ADC counts
+    pub raw_throttle: u16,
+    /// Filtered brake ADC counts
+    pub raw_brake: u16,
+    /// Latched pedal plausibility faults
+    pub pedal_faults: PedalFaults,
+    /// Currently displayed screen
+    pub screen: ScreenId,
+    /// What the communication-loss failsafe allows
+    pub failsafe: FailsafeStatus,
+    /// Drive mode selected on the wheel
+    pub drive: DriveModeController,
+    /// Cruise target speed
+    pub cruise: CruiseControl,
+    /// Turn signals and hazards
+    pub turn: TurnSignals,
+    /// BPS trip alert shown to the driver
+    pub bms_alert: BmsAlert,
+    /// Display brightness preset, power save and auto-dim
+    pub dimming: Dimming,
+    /// OLED pixel shift, static dimming and screensaver settings
+    pub burn_in: BurnIn,
+}
+
+impl SteeringState {
+    /// Set the reported state of a button
+    pub fn set_button(&mut self, id: ButtonId, on: bool) {
+        *self.button_mut(id) = on;
+    }
+
+    /// Get the reported state of a button
+    pub fn button(&self, id: ButtonId) -> bool {
+        let b = &self.buttons;
+        match id {
+            ButtonId::CruiseDown => b.cruise_down_on,
+            ButtonId::CruiseUp => b.cruise_up_on,
+            ButtonId::Reverse => b.reverse_on,
+            ButtonId::PushToTalk => b.ptt_on,
+            ButtonId::Horn => b.horn_on,
+            ButtonId::PowerSave => b.power_save_on,
+            ButtonId::Rearview => b.rearview_on,
+            ButtonId::LeftTurn => b.left_turn_on,
+            ButtonId::RightTurn => b.right_turn_on,
+            ButtonId::Lock => b.lock_on,
+        }
+    }
+
+    fn button_mut(&mut self, id: ButtonId) -> &mut bool {
+        let b = &mut self.buttons;
+        match id {
+            ButtonId::CruiseDown => &mut b.cruise_down_on,
+            ButtonId::CruiseUp => &mut b.cruise_up_on,
+            ButtonId::Reverse => &mut b.reverse_on,
+            ButtonId::PushToTalk => &mut b.ptt_on,
+            ButtonId::Horn => &mut b.horn_on,
+            ButtonId::PowerSave => &mut b.power_save_on,
+            ButtonId::Rearview => &mut b.rearview_on,
+            ButtonId::LeftTurn => &mut b.left_turn_on,
+            ButtonId::RightTurn => &mut b.right_turn_on,
+            ButtonId::Lock => &mut b.lock_on,
+        }
+    }
+
+    /// Advance to the next display screen, wrapping around
+    pub fn next_screen(&mut self) {
+        self.screen = self.screen.next();
+    }
+
+    /// True while cruise is engaged and allowed
+    pub fn cruise_engaged(&self) -> bool {
+        self.drive.mode() == DriveState::Cruise && !self.failsafe.inhibit_cruise
+    }
+
+    /// Build the SW_State message sent to the VC and BMS
+    ///
+    /// The failsafe inhibits are applied here, so nothing it blocks ever
+    /// reaches the VC. Neutral is forced by the drive mode controller.
+    ///
+    /// The turn buttons report the turn signal state (both for hazards),
+    /// not the buttons themselves. Reverse reports the drive mode, so it
+    /// clears whenever the failsafe forces neutral.
+    pub fn to_sw_state(&self, now: Instant, time_since_vc: u32, time_since_bms: u32) -> SwState {
+        let mut buttons = self.buttons;
+        (buttons.left_turn_on, buttons.right_turn_on) = self.turn.signal(now).sides();
+        buttons.reverse_on = self.drive.mode() == DriveState::Reverse;
+        if self.failsafe.inhibit_cruise {
+            buttons.cruise_up_on = false;
+            buttons.cruise_down_on = false;
+        }
+
+        SwState {
+            button_state: buttons,
+            throttle: self.failsafe.limit_throttle(self.throttle),
+            brake: self.brake,
+            screen: self.screen as u32,
+            time_tracker_vc: time_since_vc,
+            time_tracker_bms: time_since_bms,
+            pedal_faults: self.pedal_faults.bits() as u32,
+            dtcs: self.failsafe.dtcs.bits() as u32,
+            drive_mode: self.drive.mode() as u32,
+            cruise_enabled: self.cruise_engaged(),
+            cruise_speed: self.cruise.target(),
+        }
+    }
+}
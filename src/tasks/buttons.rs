@@ -1,21 +1,95 @@
 use defmt::*;
-use embassy_time::Timer;
-use crate::drivers::buttons::{ButtonInputs, ButtonState, ButtonEvent, ButtonId};
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Timer};
+use crate::drivers::buttons::{
+    ButtonEvent, ButtonId, ButtonInputs, ButtonSet, ButtonState, GestureDetector, GestureEvent,
+    InputMode, SETTLE_PERIOD,
+};
+use crate::control::{CruiseButton, DriveState, TurnSignal};
+use crate::drivers::pedals::calibration::CALIBRATION_COMBO;
+use crate::state::SharedState;
+use crate::tasks::display::SCREEN_INPUT;
+use crate::tasks::pedals::CALIBRATION_REQUEST;
+use crate::tasks::watchdog::{self, Heartbeat};
+
+/// Chord that cycles the display screen
+const SCREEN_CHORD: ButtonSet =
+    ButtonSet::of(&[ButtonId::Lock, ButtonId::LeftTurn, ButtonId::PushToTalk]);
+
+/// Held to acknowledge the BPS trip alert (a short press still toggles
+/// power save)
+const BMS_ACK_BUTTON: ButtonId = ButtonId::PowerSave;
+
+/// Both turn buttons together toggle the hazards
+const HAZARD_CHORD: ButtonSet = ButtonSet::of(&[ButtonId::LeftTurn, ButtonId::RightTurn]);
+
+/// Polling period in polled mode, and while buttons are held in interrupt
+/// mode (settling there samples every `SETTLE_PERIOD` instead)
+const POLL_PERIOD: Duration = Duration::from_millis(10);
+
+/// Idle wakeup in interrupt mode, catching edges that raced the EXTI arming
+const IDLE_POLL: Duration = Duration::from_millis(500);
+
+/// Idle wakeup in interrupt mode while a button without an EXTI line matters
+const FALLBACK_POLL: Duration = Duration::from_millis(50);
+
+/// Watchdog deadline, in both input modes
+const HEARTBEAT_DEADLINE: Duration = Duration::from_millis(50);
+
+/// Check-in interval while asleep in interrupt mode, independent of input
+const HEARTBEAT_PERIOD: Duration = Duration::from_millis(25);
+
+/// Buttons that do nothing outside drive and cruise, so interrupt mode may
+/// leave them unpolled while parked
+const DRIVE_ONLY: ButtonSet = ButtonSet::of(&[ButtonId::CruiseUp, ButtonId::CruiseDown]);
+
+/// Chord that starts a pedal calibration, or finishes the one in progress
+const CALIBRATION_CHORD: ButtonSet = ButtonSet::of(&CALIBRATION_COMBO);
+
+/// Calibration is only offered while the car is (near) stationary
+const CALIBRATION_MAX_SPEED: f32 = 0.5;
+
+/// ...as reported by a VC message at most this old (ms), since the speed
+/// also reads 0 once the VC has gone silent
+const CALIBRATION_VC_MAX_AGE: u32 = 300;
 
 #[embassy_executor::task]
-pub async fn button_task(inputs: ButtonInputs) {
-    info!("Button task started!");
+pub async fn button_task(
+    mut inputs: ButtonInputs,
+    mode: InputMode,
+    shared_state: &'static SharedState,
+) {
+    info!("Button task started in {:?} mode", mode);
     log::info!("USB Logger: Button monitoring task started");
 
     let mut button_state = ButtonState::new(&inputs);
+    let mut gestures = GestureDetector::default();
+    let polled = inputs.polled();
+
+    let heartbeat = watchdog::register("buttons", HEARTBEAT_DEADLINE);
 
-    // Main button polling loop
+    // Main button loop
     loop {
-        // Poll buttons every 10ms for responsive debouncing
+        heartbeat.beat();
+
         let events = button_state.update(&inputs);
 
         // Process any button events
         for event in events {
+            // Publish the new button state for the rest of the firmware
+            let now = Instant::now();
+            shared_state
+                .update_steering(|steering| {
+                    // Any button use keeps the display from dimming
+                    steering.dimming.input(now);
+                    match event {
+                        ButtonEvent::Pressed(button) => steering.set_button(button, true),
+                        ButtonEvent::Released(button) => steering.set_button(button, false),
+                        ButtonEvent::Toggled(button, state) => steering.set_button(button, state),
+                    }
+                })
+                .await;
+
             match event {
                 ButtonEvent::Pressed(button) => {
                     let button_name = button_name(button);
@@ -36,8 +110,226 @@ pub async fn button_task(inputs: ButtonInputs) {
             }
         }
 
-        // Wait before next poll
-        Timer::after_millis(10).await;
+        // Long presses, double presses and chords
+        for gesture in gestures.update(button_state.pressed(), Instant::now()) {
+            handle_gesture(gesture, shared_state).await;
+            // The active screen sees every gesture too; drop them if it falls behind
+            SCREEN_INPUT.try_send(gesture).ok();
+        }
+
+        // Wait before next read
+        match mode {
+            InputMode::Polled => Timer::after(POLL_PERIOD).await,
+            InputMode::Interrupt => {
+                if !button_state.is_settled() {
+                    // Settle fast after an edge so a press registers in a few ms
+                    Timer::after(SETTLE_PERIOD).await;
+                } else if !button_state.pressed().is_empty() {
+                    // Keep sampling while held so long presses and chords are timed
+                    Timer::after(POLL_PERIOD).await;
+                } else {
+                    // Nothing settling or held: sleep until an edge, still
+                    // polling for buttons without an EXTI line that matter now
+                    let driving = matches!(
+                        shared_state.snapshot().await.steering.drive.mode(),
+                        DriveState::Drive | DriveState::Cruise
+                    );
+                    let idle = if driving || !DRIVE_ONLY.contains_all(polled) {
+                        FALLBACK_POLL
+                    } else {
+                        IDLE_POLL
+                    };
+                    sleep_until_edge(&mut inputs, &heartbeat, idle).await;
+                }
+            }
+        }
+    }
+}
+
+/// Wait for an edge on any EXTI line, or `timeout`, checking in with the
+/// watchdog every `HEARTBEAT_PERIOD` meanwhile
+async fn sleep_until_edge(inputs: &mut ButtonInputs, heartbeat: &Heartbeat, timeout: Duration) {
+    let wake = Instant::now() + timeout;
+    // Kept across heartbeats so the EXTI lines stay armed
+    let mut edge = core::pin::pin!(inputs.wait_for_edge());
+    loop {
+        let beat_at = (Instant::now() + HEARTBEAT_PERIOD).min(wake);
+        match select(edge.as_mut(), Timer::at(beat_at)).await {
+            Either::Second(()) if beat_at < wake => heartbeat.beat(),
+            _ => return,
+        }
+    }
+}
+
+async fn handle_gesture(gesture: GestureEvent, shared_state: &'static SharedState) {
+    match gesture {
+        GestureEvent::Chord(set) if set == SCREEN_CHORD => {
+            let screen = shared_state
+                .update_steering(|steering| {
+                    steering.next_screen();
+                    steering.screen
+                })
+                .await;
+            info!("Screen chord: switched to screen {}", screen);
+            log::info!("SCREEN: {}", screen.label());
+        }
+        // Reverse tap: neutral <-> reverse
+        GestureEvent::ShortPress(ButtonId::Reverse) => {
+            request_drive_mode(shared_state, |mode| match mode {
+                DriveState::Reverse => DriveState::Neutral,
+                _ => DriveState::Reverse,
+            })
+            .await;
+        }
+        // Reverse hold: neutral <-> drive
+        GestureEvent::LongPress(ButtonId::Reverse) => {
+            request_drive_mode(shared_state, |mode| match mode {
+                DriveState::Drive | DriveState::Cruise => DriveState::Neutral,
+                _ => DriveState::Drive,
+            })
+            .await;
+        }
+        // Cruise buttons engage cruise from drive, then step the target
+        GestureEvent::ShortPress(button @ (ButtonId::CruiseUp | ButtonId::CruiseDown)) => {
+            press_cruise(shared_state, cruise_button(button)).await;
+        }
+        // Holding a cruise button ramps the target
+        GestureEvent::Held(button @ (ButtonId::CruiseUp | ButtonId::CruiseDown), _) => {
+            let target = shared_state
+                .update(|data| {
+                    let steering = &mut data.steering;
+                    steering
+                        .cruise_engaged()
+                        .then(|| steering.cruise.ramp(cruise_button(button)))
+                })
+                .await;
+            if let Some(target) = target {
+                debug!("Cruise target ramped to {}", target);
+            }
+        }
+        // Turn buttons act on release, however long they were held, so a
+        // hazard chord never lights one side
+        GestureEvent::ShortPress(ButtonId::LeftTurn) | GestureEvent::LongRelease(ButtonId::LeftTurn) => {
+            press_turn(shared_state, TurnSignal::Left).await;
+        }
+        GestureEvent::ShortPress(ButtonId::RightTurn) | GestureEvent::LongRelease(ButtonId::RightTurn) => {
+            press_turn(shared_state, TurnSignal::Right).await;
+        }
+        GestureEvent::Chord(set) if set == HAZARD_CHORD => {
+            press_turn(shared_state, TurnSignal::Hazard).await;
+        }
+        GestureEvent::LongPress(BMS_ACK_BUTTON) => {
+            let acknowledged = shared_state
+                .update_steering(|steering| {
+                    let alerting = steering.bms_alert.alerting();
+                    steering.bms_alert.acknowledge();
+                    alerting
+                })
+                .await;
+            if acknowledged {
+                info!("BPS alert acknowledged");
+                log::info!("BPS ALERT ACKNOWLEDGED");
+            }
+        }
+        GestureEvent::ShortPress(ButtonId::PowerSave) => {
+            let power_save = shared_state
+                .update_steering(|steering| steering.dimming.toggle_power_save())
+                .await;
+            let state_text = if power_save { "ON" } else { "OFF" };
+            info!("Display power save {}", state_text);
+            log::info!("POWER SAVE: {}", state_text);
+        }
+        GestureEvent::Chord(set) if set == CALIBRATION_CHORD => {
+            let snapshot = shared_state.snapshot().await;
+            if snapshot.time_since_vc(Instant::now()) > CALIBRATION_VC_MAX_AGE {
+                warn!("Ignoring pedal calibration request without a recent VC speed");
+            } else if snapshot.vc.speed.abs() >= CALIBRATION_MAX_SPEED {
+                warn!("Ignoring pedal calibration request while moving");
+            } else {
+                CALIBRATION_REQUEST.signal(());
+            }
+        }
+        GestureEvent::LongPress(button) => {
+            info!("Button {} long press", button_name(button));
+        }
+        GestureEvent::DoublePress(button) => {
+            info!("Button {} double press", button_name(button));
+        }
+        GestureEvent::Chord(set) => {
+            debug!("Chord {:?}", set);
+        }
+        GestureEvent::ShortPress(_) | GestureEvent::LongRelease(_) | GestureEvent::Held(..) => {}
+    }
+}
+
+/// Hand a turn button press to the turn signal controller
+async fn press_turn(shared_state: &'static SharedState, button: TurnSignal) {
+    let now = Instant::now();
+    let signal = shared_state
+        .update_steering(|steering| steering.turn.press(button, now))
+        .await;
+    info!("Turn signal: {}", signal);
+    log::info!("TURN SIGNAL: {:?}", signal);
+}
+
+fn cruise_button(button: ButtonId) -> CruiseButton {
+    match button {
+        ButtonId::CruiseUp => CruiseButton::Up,
+        _ => CruiseButton::Down,
+    }
+}
+
+/// Short press of a cruise button: engage from drive, or step the target
+async fn press_cruise(shared_state: &'static SharedState, button: CruiseButton) {
+    let result = shared_state
+        .update(|data| {
+            if data.steering.cruise_engaged() {
+                return Some(Ok(data.steering.cruise.step(button)));
+            }
+            if data.steering.drive.mode() != DriveState::Drive {
+                return None;
+            }
+            let engaged = data.request_drive_mode(DriveState::Cruise);
+            let speed = data.vc.speed;
+            Some(engaged.map(|_| data.steering.cruise.engage(button, speed)))
+        })
+        .await;
+
+    match result {
+        Some(Ok(target)) => {
+            info!("Cruise target {}", target);
+            log::info!("CRUISE: {}", target);
+        }
+        Some(Err(reason)) => {
+            warn!("Cruise rejected: {}", reason);
+            log::warn!("CRUISE REJECTED: {:?}", reason);
+        }
+        // Not in drive, nothing to do
+        None => {}
+    }
+}
+
+/// Ask the drive mode controller for the mode `choose` picks from the current one
+async fn request_drive_mode(
+    shared_state: &'static SharedState,
+    choose: impl FnOnce(DriveState) -> DriveState,
+) {
+    let (request, result) = shared_state
+        .update(|data| {
+            let request = choose(data.steering.drive.mode());
+            (request, data.request_drive_mode(request))
+        })
+        .await;
+
+    match result {
+        Ok(mode) => {
+            info!("Drive mode: {}", mode);
+            log::info!("DRIVE MODE: {}", mode.label());
+        }
+        Err(reason) => {
+            warn!("Drive mode {} rejected: {}", request, reason);
+            log::warn!("DRIVE MODE {} REJECTED: {:?}", request.label(), reason);
+        }
     }
 }
 
@@ -54,4 +346,4 @@ fn button_name(button: ButtonId) -> &'static str {
         ButtonId::RightTurn => "Right Turn",
         ButtonId::Lock => "Lock",
     }
-}
\ No newline at end of file
+}
@@ -1,186 +1,249 @@
 use defmt::*;
+use embassy_net::Stack;
 use embassy_stm32::gpio::Output;
 use embassy_stm32::mode::Async;
 use embassy_stm32::spi::Spi;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
 use embassy_time::{Duration, Instant, Timer};
-use crate::drivers::display::Ssd1322Display;
-use crate::drivers::display::DriveState;
-use crate::drivers::display::ssd1322::DISPLAY_BLACK;
+use core::sync::atomic::{AtomicU32, Ordering};
+use static_cell::ConstStaticCell;
+use crate::drivers::buttons::GestureEvent;
+use crate::drivers::crash;
+use crate::control::{BrightnessPreset, DriveState};
+use crate::drivers::display::{Brightness, Ssd1322Display};
+use crate::drivers::display::framebuffer::Framebuffer;
+use crate::drivers::display::governor::FrameGovernor;
+use crate::drivers::display::geometry::DISPLAY_BLACK;
+use crate::drivers::network::{Destination, UdpSender};
+use crate::screens::{NetworkStatus, ScreenContext, ScreenManager};
+use crate::state::SharedState;
+use crate::tasks::watchdog;
+
+/// How long the crash screen is shown after a reset caused by a crash
+const CRASH_SCREEN_TIME: Duration = Duration::from_secs(10);
+
+/// Frame rate the display task paces itself to
+const TARGET_FPS: u32 = 30;
+
+/// How often the achieved frame rate is logged
+const FPS_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Button gestures forwarded by the button task to the active screen
+pub static SCREEN_INPUT: Channel<CriticalSectionRawMutex, GestureEvent, 8> = Channel::new();
+
+/// Front and back buffer. One is drawn into while the other is streamed to the panel.
+static FRAMES: ConstStaticCell<[Framebuffer; 2]> = ConstStaticCell::new([Framebuffer::new(), Framebuffer::new()]);
+
+/// Buffers free to be drawn into
+static FREE_FRAMES: Channel<CriticalSectionRawMutex, &'static mut Framebuffer, 2> = Channel::new();
+
+/// Finished frames waiting for the flush task
+static READY_FRAMES: Channel<CriticalSectionRawMutex, &'static mut Framebuffer, 1> = Channel::new();
+
+/// Panel brightness wanted by the display task, applied by the flush task
+static BRIGHTNESS: Signal<CriticalSectionRawMutex, Brightness> = Signal::new();
+
+/// Rows the panel should move the picture down by, applied by the flush task
+static VERTICAL_SHIFT: Signal<CriticalSectionRawMutex, i32> = Signal::new();
+
+/// Pixel bytes sent to the panel, reported alongside the frame rate
+static FLUSHED_BYTES: AtomicU32 = AtomicU32::new(0);
 
 // Display state structure
 struct DisplayState {
-    current_screen: u8,
-    left_blink: bool,
-    right_blink: bool,
-    last_blink: u32,
     bms_flash: bool,
     last_flash: u32,
+    brightness: BrightnessPreset,
+    screensaver: bool,
+    shift_y: i32,
 }
 
 impl DisplayState {
     fn new() -> Self {
         Self {
-            current_screen: 0,
-            left_blink: false,
-            right_blink: false,
-            last_blink: 0,
             bms_flash: false,
             last_flash: 0,
+            brightness: BrightnessPreset::Day,
+            screensaver: false,
+            shift_y: 0,
         }
     }
 }
 
-// Placeholder vehicle state - in production this would come from CAN/network messages
-struct VehicleState {
-    drive_mode: DriveState,
-    left_motor_velocity: f32,
-    right_motor_velocity: f32,
-    cruise_enabled: bool,
-    cruise_speed: f32,
-    regen_enabled: bool,
-    brake_pressed: bool,
-    throttle_enabled: bool,
-    throttle_pressed: bool,
-    battery_current: f32,
-    high_voltage: f32,
-    low_voltage: f32,
-    lock_on: bool,
-    bps_strobe: bool,
-    throttle_value: f32,
-    raw_throttle: u16,
-    regen_value: f32,
-    raw_regen: u32,
-    pedal_value: f32,
-    raw_pedal: u32,
-}
-
-impl Default for VehicleState {
-    fn default() -> Self {
-        Self {
-            drive_mode: DriveState::Neutral,
-            left_motor_velocity: 0.0,
-            right_motor_velocity: 0.0,
-            cruise_enabled: false,
-            cruise_speed: 0.0,
-            regen_enabled: true,
-            brake_pressed: false,
-            throttle_enabled: true,
-            throttle_pressed: false,
-            battery_current: 0.0,
-            high_voltage: 120.0,
-            low_voltage: 12.5,
-            lock_on: false,
-            bps_strobe: false,
-            throttle_value: 0.0,
-            raw_throttle: 0,
-            regen_value: 0.0,
-            raw_regen: 0,
-            pedal_value: 0.0,
-            raw_pedal: 0,
-        }
-    }
-}
-
+/// Streams finished frames to the panel
+///
+/// SPI1 TX runs on DMA2_CH3, so while a frame is on the bus this task sleeps
+/// and the display task renders the next frame into the other buffer.
 #[embassy_executor::task]
-pub async fn display_task(
+pub async fn display_flush_task(
     spi: Spi<'static, Async>,
     dc: Output<'static>,
     cs: Output<'static>,
     rst: Output<'static>,
 ) {
-    info!("Display task started!");
-
     // Initialize display
     let mut display = Ssd1322Display::new(spi, dc, cs, rst).await;
     Timer::after_millis(100).await;
     info!("Display initialized");
 
+    // A stuck SPI DMA transfer shows up here first
+    let heartbeat = watchdog::register("display_flush", Duration::from_millis(200));
+
+    loop {
+        heartbeat.beat();
+
+        let frame = READY_FRAMES.receive().await;
+        if let Some(brightness) = BRIGHTNESS.try_take() {
+            display.set_brightness(brightness).await;
+        }
+        if let Some(dy) = VERTICAL_SHIFT.try_take() {
+            display.set_vertical_shift(dy).await;
+        }
+        let sent = display.flush(frame).await;
+        FLUSHED_BYTES.fetch_add(sent as u32, Ordering::Relaxed);
+        FREE_FRAMES.send(frame).await;
+    }
+}
+
+#[embassy_executor::task]
+pub async fn display_task(
+    stack: &'static Stack<'static>,
+    sender: &'static UdpSender,
+    shared_state: &'static SharedState,
+) {
+    info!("Display task started!");
+
+    let [front, back] = FRAMES.take();
+    FREE_FRAMES.send(front).await;
+    FREE_FRAMES.send(back).await;
+
     let mut state = DisplayState::new();
-    let vehicle_state = VehicleState::default();
+    let mut screens = ScreenManager::new();
     
     // Timing variables
     let start_time = Instant::now();
-    let mut time_since_vc = 0u32;
-    let mut time_since_bms = 0u32;
+    let heartbeat = watchdog::register("display", Duration::from_millis(200));
+    let mut governor = FrameGovernor::new(TARGET_FPS, FPS_REPORT_INTERVAL, start_time);
 
     loop {
+        heartbeat.beat();
+
+        // Wait for a buffer the flush task is done with
+        let frame = FREE_FRAMES.receive().await;
         let current_time = start_time.elapsed().as_millis() as u32;
         
-        // TODO: Update vehicle_state from actual CAN messages or network data
-        // For now using placeholder values
-        
-        // Update time since last message
-        time_since_vc += 50; // Placeholder - would be updated when actual message received
-        time_since_bms += 50; // Placeholder - would be updated when actual message received
-        
-        // Clear display
-        display.fill(DISPLAY_BLACK);
-
-        match state.current_screen {
-            0 => {
-                // Main screen
-                display.write_drive_state(vehicle_state.drive_mode);
-                
-                let max_velocity = vehicle_state.left_motor_velocity.max(vehicle_state.right_motor_velocity);
-                display.write_speed(max_velocity);
-                
-                display.write_turn_signal_state(
-                    &mut state.left_blink,
-                    &mut state.right_blink,
-                    &mut state.last_blink,
-                    current_time,
-                );
-                
-                display.write_cruise_speed(vehicle_state.cruise_enabled, vehicle_state.cruise_speed);
-                
-                display.write_regen(
-                    vehicle_state.regen_enabled,
-                    vehicle_state.brake_pressed,
-                    vehicle_state.throttle_value > 0.2,
-                );
-                
-                display.write_throttle(
-                    vehicle_state.throttle_enabled,
-                    vehicle_state.throttle_pressed,
-                    vehicle_state.throttle_value > 0.2,
-                );
-                
-                display.write_current(vehicle_state.battery_current);
-                display.write_high_voltage(vehicle_state.high_voltage);
-                display.write_low_voltage(vehicle_state.low_voltage);
-                display.write_lock(vehicle_state.lock_on);
-                
-                display.write_bms_flash(
-                    vehicle_state.bps_strobe,
-                    &mut state.bms_flash,
-                    &mut state.last_flash,
-                    current_time,
-                );
-                
-                display.write_timeout(time_since_vc);
-                display.write_bms_timeout(time_since_bms);
-            }
-            1 => {
-                // Debug screen
-                display.write_timeout(time_since_vc);
-                display.write_bms_timeout(time_since_bms);
-                
-                display.write_throttle_debug(vehicle_state.throttle_value, vehicle_state.raw_throttle);
-                display.write_regen_debug(vehicle_state.regen_value, vehicle_state.raw_regen);
-                display.write_pedal_value(vehicle_state.pedal_value, vehicle_state.raw_pedal);
-                
-                display.write_debug();
+        // Let the active screen act on button gestures
+        while let Ok(event) = SCREEN_INPUT.try_receive() {
+            let used_by = shared_state
+                .update(|data| {
+                    let id = data.steering.screen;
+                    screens.get(id).handle_input(event, data).then_some(id)
+                })
+                .await;
+            if let Some(id) = used_by {
+                debug!("{} screen used a button gesture", id.label());
             }
-            _ => {
-                // Unknown screen, default to main
-                state.current_screen = 0;
+        }
+
+        // Pull in the latest steering inputs and VC/BMS data
+        let snapshot = shared_state.snapshot().await;
+        let now = Instant::now();
+        let mut network = NetworkStatus {
+            link_up: stack.is_link_up(),
+            ..NetworkStatus::default()
+        };
+        for destination in Destination::ALL {
+            network.sent[destination as usize] = sender.stats(destination);
+        }
+        // Burn-in mitigation runs off the same idle clock as the auto-dim
+        let steering = &snapshot.steering;
+        let parked = steering.drive.mode() == DriveState::Neutral;
+        let idle = steering.dimming.idle_for(now);
+        let screensaver = steering.burn_in.screensaver(idle, parked, steering.buttons.lock_on);
+        if screensaver != state.screensaver {
+            state.screensaver = screensaver;
+            info!("Display screensaver: {}", screensaver);
+        }
+        let ctx = ScreenContext {
+            data: &snapshot,
+            now,
+            current_time,
+            time_since_vc: snapshot.time_since_vc(now),
+            time_since_bms: snapshot.time_since_bms(now),
+            network,
+            dim_static: steering.burn_in.dim_static(idle),
+        };
+
+        // Clear display
+        frame.fill(DISPLAY_BLACK);
+
+        let alert = steering.bms_alert;
+        let crash = crash::last_crash().filter(|_| start_time.elapsed() < CRASH_SCREEN_TIME);
+
+        // Dim as the driver asked, but never hide a takeover
+        let brightness = if crash.is_some() || alert.alerting() {
+            BrightnessPreset::Day
+        } else {
+            steering.dimming.level(now, parked)
+        };
+        if brightness != state.brightness {
+            state.brightness = brightness;
+            BRIGHTNESS.signal(Brightness::preset(brightness));
+            info!("Display brightness: {}", brightness);
+        }
+
+        if let Some(record) = crash {
+            // A crash in the previous run takes over the screen for a while
+            frame.write_crash(record);
+        } else {
+            // A BPS trip takes over every screen until the driver acknowledges it
+            frame.write_bms_flash(
+                alert.alerting(),
+                alert.reason(),
+                &mut state.bms_flash,
+                &mut state.last_flash,
+                current_time,
+            );
+            if screensaver && !alert.alerting() {
+                screens.screensaver().render(frame, &ctx);
+            } else if !alert.alerting() {
+                // The screen is chosen with the button chord and reported in SW_State
+                screens.get(steering.screen).render(frame, &ctx);
             }
         }
 
-        // Flush display
-        display.flush().await;
+        // Walk the whole frame around a small orbit to spread wear on the panel:
+        // across in the frame, up and down with the panel's start line
+        let (dx, dy) = steering.burn_in.offset(now);
+        frame.shift_x(dx);
+        if dy != state.shift_y {
+            state.shift_y = dy;
+            VERTICAL_SHIFT.signal(dy);
+        }
 
-        // Run at ~20Hz (50ms period) to match the C implementation
-        Timer::after(Duration::from_millis(10)).await;
+        // Hand the frame to the flush task and pace to the target frame rate
+        READY_FRAMES.send(frame).await;
+        let finished = Instant::now();
+        let next_frame = governor.frame_done(finished);
+        if let Some(stats) = governor.report(finished) {
+            let flushed = FLUSHED_BYTES.swap(0, Ordering::Relaxed);
+            info!(
+                "Display: {}.{:02} fps, {} dropped, {} bytes flushed",
+                stats.fps_centi / 100,
+                stats.fps_centi % 100,
+                stats.dropped,
+                flushed
+            );
+            log::info!(
+                "Display: {}.{:02} fps, {} dropped, {} bytes flushed",
+                stats.fps_centi / 100,
+                stats.fps_centi % 100,
+                stats.dropped,
+                flushed
+            );
+        }
+        Timer::at(next_frame).await;
     }
-}
\ No newline at end of file
+}
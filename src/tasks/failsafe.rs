@@ -0,0 +1,79 @@
+/// Communication-loss failsafe task
+///
+/// Runs the failsafe state machine against the VC/BMS link timestamps and
+/// publishes what it allows into the steering state, where `to_sw_state`
+/// applies it to every outgoing message.
+use defmt::*;
+use embassy_time::{Duration, Instant, Ticker};
+
+use crate::control::{Failsafe, FailsafeConfig, FailsafeState, FailsafeStatus};
+use crate::state::SharedState;
+use crate::tasks::watchdog;
+
+/// How often the link timeouts are checked
+const CHECK_PERIOD: Duration = Duration::from_millis(20);
+
+#[embassy_executor::task]
+pub async fn failsafe_task(config: FailsafeConfig, shared_state: &'static SharedState) {
+    info!("Failsafe task started!");
+
+    let mut failsafe = Failsafe::new(config);
+    // What the shared state starts out with
+    let mut reported = FailsafeStatus::default();
+    let mut ticker = Ticker::every(CHECK_PERIOD);
+    let heartbeat = watchdog::register("failsafe", Duration::from_millis(100));
+
+    loop {
+        heartbeat.beat();
+
+        let snapshot = shared_state.snapshot().await;
+        let now = Instant::now();
+        let since_vc = Duration::from_millis(snapshot.time_since_vc(now) as u64);
+        let since_bms = Duration::from_millis(snapshot.time_since_bms(now) as u64);
+
+        // Re-arm on the pedal itself: the limited throttle reads zero while a
+        // pedal fault is latched or a calibration is running
+        let status = failsafe.update(since_vc, since_bms, snapshot.steering.throttle_input);
+
+        if status.state != reported.state {
+            match status.state {
+                FailsafeState::VcLost => {
+                    error!("Failsafe: VC lost, throttle and cruise inhibited");
+                    log::error!("FAILSAFE: VC LOST");
+                }
+                FailsafeState::AwaitingRearm => {
+                    warn!("Failsafe: VC back, release throttle to re-arm");
+                    log::warn!("FAILSAFE: RELEASE THROTTLE TO RE-ARM");
+                }
+                FailsafeState::Armed => {
+                    info!("Failsafe: armed");
+                    log::info!("FAILSAFE: ARMED");
+                }
+            }
+        }
+        if status.bms_lost != reported.bms_lost {
+            if status.bms_lost {
+                error!("Failsafe: BMS lost");
+                log::error!("FAILSAFE: BMS LOST");
+            } else {
+                info!("Failsafe: BMS back");
+            }
+        }
+
+        if status != reported {
+            let forced = shared_state
+                .update(|data| {
+                    data.steering.failsafe = status;
+                    data.update_drive_mode()
+                })
+                .await;
+            if let Some(mode) = forced {
+                warn!("Failsafe: drive mode forced to {}", mode);
+                log::warn!("DRIVE MODE: {}", mode.label());
+            }
+            reported = status;
+        }
+
+        ticker.next().await;
+    }
+}
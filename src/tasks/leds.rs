@@ -0,0 +1,139 @@
+/// LED task - drives button and status LEDs from the shared state
+use defmt::*;
+use embassy_net::Stack;
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::drivers::buttons::ButtonId;
+use crate::drivers::display::display_write::BPS_FLASH_PERIOD_MS;
+use crate::drivers::leds::{LedPattern, Leds, StatusLed, PWM_STEPS};
+use crate::state::{SharedState, StateData, SteeringState};
+use crate::tasks::watchdog;
+
+/// Software PWM step (16 steps -> 125 Hz PWM, no visible flicker), only
+/// while an LED is breathing
+const PWM_TICK: Duration = Duration::from_micros(500);
+
+/// Time between pattern updates from the shared state
+const UPDATE_PERIOD: Duration = Duration::from_millis(50);
+
+/// Same link timeouts as the display's VC/BMS boxes
+const VC_TIMEOUT_MS: u32 = 300;
+const BMS_TIMEOUT_MS: u32 = 1000;
+
+/// A blink rather than a breathe, so the LEDs need no PWM while idle
+const HEARTBEAT: LedPattern = LedPattern::Blink { period_ms: 2000 };
+const FAULT_BLINK: LedPattern = LedPattern::Blink { period_ms: 250 };
+/// Same rate as the display's BPS alert
+const BPS_STROBE: LedPattern = LedPattern::Blink { period_ms: BPS_FLASH_PERIOD_MS };
+
+/// Acknowledgement flash when a regular button is pressed
+const PRESS_FLASH: Duration = Duration::from_millis(150);
+
+/// Buttons that only latch while held
+const REGULAR_BUTTONS: [ButtonId; 7] = [
+    ButtonId::CruiseDown,
+    ButtonId::CruiseUp,
+    ButtonId::Reverse,
+    ButtonId::PushToTalk,
+    ButtonId::Horn,
+    ButtonId::PowerSave,
+    ButtonId::Rearview,
+];
+
+#[embassy_executor::task]
+pub async fn led_task(
+    mut leds: Leds,
+    stack: &'static Stack<'static>,
+    shared_state: &'static SharedState,
+) {
+    info!("LED task started!");
+
+    let mut previous = SteeringState::default();
+    let mut phase = 0u8;
+    let mut next_update = Instant::now();
+    let heartbeat = watchdog::register("leds", Duration::from_millis(100));
+
+    loop {
+        let now = Instant::now();
+
+        if now >= next_update {
+            next_update = now + UPDATE_PERIOD;
+            heartbeat.beat();
+            let snapshot = shared_state.snapshot().await;
+            update_button_leds(&mut leds, &snapshot.steering, &previous, now);
+            update_status_leds(&mut leds, &snapshot, stack.is_link_up(), now);
+            previous = snapshot.steering;
+
+            // Report which button LEDs are lit in SW_State
+            let mask = leds.buttons.lit_mask();
+            shared_state
+                .update_steering(|steering| steering.buttons.led_state = mask)
+                .await;
+        }
+
+        if leds.needs_pwm(now) {
+            leds.refresh(now, phase);
+            phase = (phase + 1) % PWM_STEPS;
+            Timer::after(PWM_TICK).await;
+        } else {
+            // Every LED is plain on or off: sleep until one switches
+            leds.refresh(now, 0);
+            let wake = leds.next_change(now).map_or(next_update, |at| at.min(next_update));
+            Timer::at(wake).await;
+        }
+    }
+}
+
+/// Button LEDs mirror the button states: toggles stay lit, turn signals
+/// blink in step with the display arrows
+fn update_button_leds(
+    leds: &mut Leds,
+    steering: &SteeringState,
+    previous: &SteeringState,
+    now: Instant,
+) {
+    let lit = |on: bool, pattern: LedPattern| if on { pattern } else { LedPattern::Off };
+
+    let (left, right) = steering.turn.lamps(now);
+    leds.buttons.set(ButtonId::LeftTurn, lit(left, LedPattern::Solid), now);
+    leds.buttons.set(ButtonId::RightTurn, lit(right, LedPattern::Solid), now);
+    leds.buttons.set(ButtonId::Lock, lit(steering.button(ButtonId::Lock), LedPattern::Solid), now);
+
+    for id in REGULAR_BUTTONS {
+        let pressed = steering.button(id);
+        leds.buttons.set(id, lit(pressed, LedPattern::Solid), now);
+
+        // A tap shorter than the update period would otherwise never show
+        if pressed && !previous.button(id) {
+            leds.buttons.flash(id, PRESS_FLASH, now);
+        }
+    }
+}
+
+/// Status LEDs: heartbeat, Ethernet link, VC/BMS connectivity, faults/BPS
+fn update_status_leds(leds: &mut Leds, data: &StateData, link_up: bool, now: Instant) {
+    leds.status.set(StatusLed::Heartbeat, HEARTBEAT, now);
+
+    let network = if link_up { LedPattern::Solid } else { LedPattern::Off };
+    leds.status.set(StatusLed::Network, network, now);
+
+    // Solid with both nodes talking, blinking with one, off with none
+    let vc_alive = data.time_since_vc(now) < VC_TIMEOUT_MS;
+    let bms_alive = data.time_since_bms(now) < BMS_TIMEOUT_MS;
+    let nodes = match (vc_alive, bms_alive) {
+        (true, true) => LedPattern::Solid,
+        (false, false) => LedPattern::Off,
+        _ => LedPattern::Blink { period_ms: 1000 },
+    };
+    leds.status.set(StatusLed::Nodes, nodes, now);
+
+    // A BPS trip outranks pedal faults and stays on until it clears
+    let fault = if data.steering.bms_alert.tripped() {
+        BPS_STROBE
+    } else if !data.steering.pedal_faults.is_empty() {
+        FAULT_BLINK
+    } else {
+        LedPattern::Off
+    };
+    leds.status.set(StatusLed::Fault, fault, now);
+}
@@ -1,9 +1,17 @@
-pub mod blinky;
 pub mod buttons;
 pub mod display;
+pub mod failsafe;
+pub mod leds;
+pub mod network_recv;
+pub mod pedals;
 pub mod telemetry;
+pub mod watchdog;
 
-pub use blinky::blinky_task;
 pub use buttons::button_task;
-pub use display::display_task;
-pub use telemetry::{telemetry_task, steering_update_task};
\ No newline at end of file
+pub use display::{display_task, display_flush_task};
+pub use failsafe::failsafe_task;
+pub use leds::led_task;
+pub use network_recv::network_receive_task;
+pub use pedals::{calibration_store_task, pedal_task, CalibrationStore};
+pub use telemetry::{telemetry_task, steering_update_task};
+pub use watchdog::watchdog_task;
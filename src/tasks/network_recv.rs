@@ -0,0 +1,117 @@
+/// Network receive task - decodes VC and BMS packets into the shared state
+use defmt::*;
+use embassy_net::udp::PacketMetadata;
+use embassy_net::{IpAddress, Stack};
+use embassy_time::{with_timeout, Duration, Instant};
+
+use crate::control::BmsFlags;
+use crate::drivers::network::{self, BMS_ADDRESS, MAX_PACKET_SIZE, VC_ADDRESS};
+use crate::protocol::{DataMessage, Message};
+use crate::state::SharedState;
+use crate::tasks::watchdog;
+
+/// Longest wait for a packet before checking in with the watchdog
+const RECV_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Nodes we accept state from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+enum Peer {
+    Vc,
+    Bms,
+}
+
+/// Identify the sender of a packet by its source address
+fn identify_sender(addr: IpAddress) -> Option<Peer> {
+    if addr == VC_ADDRESS.into() {
+        Some(Peer::Vc)
+    } else if addr == BMS_ADDRESS.into() {
+        Some(Peer::Bms)
+    } else {
+        None
+    }
+}
+
+/// Network receive task
+///
+/// Listens on RECEIVE_PORT (4001) and stores the latest VC/BMS state along
+/// with the time it arrived. Packets from unknown senders are dropped.
+#[embassy_executor::task]
+pub async fn network_receive_task(stack: &'static Stack<'static>, state: &'static SharedState) {
+    info!("Starting network receive task");
+
+    // Wait for network to be ready
+    network::wait_for_link_up(stack).await;
+
+    let mut rx_buffer = [0; MAX_PACKET_SIZE];
+    let mut tx_buffer = [0; MAX_PACKET_SIZE];
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+
+    let socket = match network::create_receive_socket(
+        stack,
+        &mut rx_buffer,
+        &mut tx_buffer,
+        &mut rx_meta,
+        &mut tx_meta,
+    )
+    .await
+    {
+        Ok(socket) => socket,
+        Err(e) => {
+            error!("Failed to bind receive socket: {:?}", e);
+            return;
+        }
+    };
+
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    let heartbeat = watchdog::register("net_recv", Duration::from_secs(2));
+
+    loop {
+        heartbeat.beat();
+
+        // Time out regularly so the watchdog hears from us when nobody is talking
+        let (len, meta) = match with_timeout(RECV_TIMEOUT, socket.recv_from(&mut buf)).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => {
+                warn!("Failed to receive packet: {:?}", e);
+                continue;
+            }
+            Err(_) => continue,
+        };
+        let received_at = Instant::now();
+
+        let Some(peer) = identify_sender(meta.endpoint.addr) else {
+            debug!("Ignoring {} bytes from unknown sender {}", len, meta.endpoint);
+            continue;
+        };
+
+        let message = match DataMessage::decode(&buf[..len]) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Failed to decode packet from {:?}: {:?}", peer, e);
+                continue;
+            }
+        };
+
+        match (peer, message.vc_state, message.bms_state) {
+            (Peer::Vc, Some(vc_state), _) => {
+                if state.update_vc(vc_state, received_at).await {
+                    info!("Turn signal cancelled by steering");
+                }
+                // The VC enabling regen can cancel cruise
+                if let Some(mode) = state.update(|data| data.update_drive_mode()).await {
+                    info!("Drive mode forced to {}", mode);
+                    log::info!("DRIVE MODE: {}", mode.label());
+                }
+            }
+            (Peer::Bms, _, Some(bms_state)) => {
+                if state.update_bms(bms_state, received_at).await {
+                    let flags = BmsFlags::from_bits(bms_state.flags);
+                    error!("BPS tripped: {} (flags {:#x})", flags.reason(), flags.bits());
+                    log::error!("BPS TRIPPED: {}", flags.reason());
+                }
+            }
+            _ => debug!("Packet from {:?} did not contain its own state", peer),
+        }
+    }
+}
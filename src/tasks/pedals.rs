@@ -0,0 +1,187 @@
+use defmt::*;
+use embassy_stm32::bind_interrupts;
+use embassy_stm32::flash::{self, Async, Flash};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Ticker};
+use crate::drivers::pedals::calibration::CALIBRATION_SIZE;
+use crate::drivers::pedals::{
+    Calibrator, PedalCalibration, PedalCounts, PedalFaults, PedalInputs, PedalProcessor,
+    PlausibilityChecker,
+};
+use crate::drivers::storage::{ConfigStore, StorageError};
+use crate::state::SharedState;
+use crate::tasks::watchdog;
+
+/// Pedal sampling period (50 Hz, well above the 20 Hz steering update)
+const SAMPLE_PERIOD: Duration = Duration::from_millis(20);
+
+// Flash end-of-operation interrupt, for the async flash driver
+bind_interrupts!(pub struct FlashIrqs {
+    FLASH => flash::InterruptHandler;
+});
+
+/// Flash-backed store holding the pedal calibration
+pub type CalibrationStore = ConfigStore<Flash<'static, Async>>;
+
+/// Raised by the button task: starts a calibration, or finishes the one in progress
+pub static CALIBRATION_REQUEST: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// A finished calibration waiting to be written to flash
+static CALIBRATION_SAVE: Signal<CriticalSectionRawMutex, PedalCalibration> = Signal::new();
+
+#[embassy_executor::task]
+pub async fn pedal_task(
+    mut pedals: PedalInputs,
+    calibration: PedalCalibration,
+    shared_state: &'static SharedState,
+) {
+    info!("Pedal task started!");
+
+    let mut processor = PedalProcessor::new();
+    processor.calibration = calibration;
+
+    let mut calibrator = Calibrator::new();
+    let mut checker = PlausibilityChecker::default();
+    let mut reported_faults = PedalFaults::NONE;
+    let mut ticker = Ticker::every(SAMPLE_PERIOD);
+    let heartbeat = watchdog::register("pedals", Duration::from_millis(100));
+
+    loop {
+        heartbeat.beat();
+
+        match pedals.read().await {
+            Some(counts) => {
+                let reading = processor.process(counts);
+                let faults = checker.check(counts, reading.throttle, reading.brake);
+                if faults != reported_faults {
+                    if faults.is_empty() {
+                        info!("Pedal faults cleared");
+                    } else {
+                        warn!("Pedal fault latched: {:?}", faults);
+                        log::warn!("PEDAL FAULT: {}", faults.label());
+                    }
+                    reported_faults = faults;
+                }
+                let filtered = PedalCounts {
+                    throttle: reading.raw_throttle,
+                    brake: reading.raw_brake,
+                };
+
+                // The first request starts a capture, the next one finishes it
+                if CALIBRATION_REQUEST.try_take().is_some() {
+                    if calibrator.is_active() {
+                        finish_calibration(&mut calibrator, &mut processor);
+                    } else {
+                        info!("Pedal calibration started - sweep both pedals fully");
+                        log::info!("PEDAL CALIBRATION: started");
+                        calibrator.start(filtered);
+                    }
+                }
+                calibrator.sample(filtered);
+
+                // Pedals read as released while the driver sweeps them for calibration
+                let (throttle, brake) = if calibrator.is_active() {
+                    (0.0, 0.0)
+                } else {
+                    faults.limit(reading.throttle, reading.brake)
+                };
+
+                let forced = shared_state
+                    .update(|data| {
+                        let steering = &mut data.steering;
+                        steering.throttle = throttle;
+                        steering.brake = brake;
+                        steering.throttle_input = reading.throttle;
+                        steering.brake_input = reading.brake;
+                        steering.raw_throttle = reading.raw_throttle;
+                        steering.raw_brake = reading.raw_brake;
+                        steering.pedal_faults = faults;
+                        // A brake press cancels cruise, even with a brake fault
+                        data.update_drive_mode()
+                    })
+                    .await;
+                if let Some(mode) = forced {
+                    info!("Drive mode forced to {}", mode);
+                    log::info!("DRIVE MODE: {}", mode.label());
+                }
+            }
+            None => {
+                warn!("Pedal ADC overrun, restarting conversion");
+            }
+        }
+
+        ticker.next().await;
+    }
+}
+
+/// Writes finished calibrations to flash
+///
+/// The sector erase takes a second or two, so it runs here rather than in the
+/// pedal task, which has to keep sampling (and checking in with the watchdog)
+/// meanwhile.
+#[embassy_executor::task]
+pub async fn calibration_store_task(mut store: CalibrationStore) {
+    loop {
+        let calibration = CALIBRATION_SAVE.wait().await;
+        match store.save(&calibration.to_bytes()).await {
+            Ok(()) => info!("Pedal calibration saved"),
+            Err(e) => error!("Failed to save pedal calibration: {:?}", e),
+        }
+    }
+}
+
+/// Read the stored calibration, falling back to the full ADC range
+pub async fn load_calibration(store: &mut CalibrationStore) -> PedalCalibration {
+    let mut buf = [0u8; CALIBRATION_SIZE];
+    match store.load(&mut buf).await {
+        Ok(len) => match PedalCalibration::from_bytes(&buf[..len]) {
+            Some(calibration) => {
+                info!(
+                    "Loaded pedal calibration: throttle {}-{}, brake {}-{}",
+                    calibration.throttle.min,
+                    calibration.throttle.max,
+                    calibration.brake.min,
+                    calibration.brake.max
+                );
+                calibration
+            }
+            None => {
+                warn!("Stored pedal calibration is invalid, using full ADC range");
+                PedalCalibration::default()
+            }
+        },
+        Err(StorageError::NotFound) => {
+            info!("No stored pedal calibration, using full ADC range");
+            PedalCalibration::default()
+        }
+        Err(e) => {
+            warn!("Failed to load pedal calibration: {:?}", e);
+            PedalCalibration::default()
+        }
+    }
+}
+
+/// Apply the captured calibration if it is usable, and queue it to be saved
+fn finish_calibration(calibrator: &mut Calibrator, processor: &mut PedalProcessor) {
+    let calibration = match calibrator.finish() {
+        Ok(calibration) => calibration,
+        Err(e) => {
+            warn!("Pedal calibration rejected: {:?}", e);
+            log::warn!("PEDAL CALIBRATION: rejected, keeping previous values");
+            return;
+        }
+    };
+
+    processor.calibration = calibration;
+    info!(
+        "Pedal calibration complete: throttle {}-{}, brake {}-{}",
+        calibration.throttle.min,
+        calibration.throttle.max,
+        calibration.brake.min,
+        calibration.brake.max
+    );
+    log::info!("PEDAL CALIBRATION: complete");
+
+    CALIBRATION_SAVE.signal(calibration);
+}
@@ -1,77 +1,67 @@
 /// Telemetry broadcast task - sends steering wheel data over UDP
 use defmt::*;
 use embassy_net::Stack;
-use embassy_time::{Duration, Timer};
-
-use crate::drivers::network;
-
-/// Simple test message structure
-/// In the future, this will be replaced with protobuf messages
-#[repr(C)]
-#[derive(Debug, Clone, Copy)]
-pub struct TelemetryMessage {
-    pub sequence: u32,
-    pub timestamp: u32,
-    pub button_state: u16,
-    pub throttle: u16,
-    pub brake: u16,
-}
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Timer};
 
-impl TelemetryMessage {
-    pub fn new() -> Self {
-        Self {
-            sequence: 0,
-            timestamp: 0,
-            button_state: 0,
-            throttle: 0,
-            brake: 0,
-        }
-    }
+use crate::drivers::crash::{self, CrashRecord};
+use crate::drivers::network::{self, UdpSender};
+use crate::protocol::{DataMessage, Message, MAX_MESSAGE_SIZE};
+use crate::state::SharedState;
+use crate::tasks::watchdog;
 
-    /// Convert to bytes for transmission
-    pub fn to_bytes(&self) -> [u8; 16] {
-        let mut bytes = [0u8; 16];
-        bytes[0..4].copy_from_slice(&self.sequence.to_le_bytes());
-        bytes[4..8].copy_from_slice(&self.timestamp.to_le_bytes());
-        bytes[8..10].copy_from_slice(&self.button_state.to_le_bytes());
-        bytes[10..12].copy_from_slice(&self.throttle.to_le_bytes());
-        bytes[12..14].copy_from_slice(&self.brake.to_le_bytes());
-        // Last 2 bytes are padding
-        bytes
-    }
-}
+/// Longest gap between steering updates while nothing changes
+const STEERING_UPDATE_PERIOD: Duration = Duration::from_millis(50);
+
+/// Shortest gap between steering updates, so a burst of changes goes out as one
+const STEERING_MIN_GAP: Duration = Duration::from_millis(10);
 
 /// Telemetry broadcast task
 ///
 /// Sends telemetry data every second to:
 /// - Broadcast address (192.168.0.255:6000)
 /// - AWS telemetry server (if configured)
+///
+/// A crash recorded by the previous run is included in every broadcast.
 #[embassy_executor::task]
-pub async fn telemetry_task(stack: &'static Stack<'static>) {
+pub async fn telemetry_task(
+    stack: &'static Stack<'static>,
+    sender: &'static UdpSender,
+    shared_state: &'static SharedState,
+) {
     info!("Starting telemetry broadcast task");
 
     // Wait for network to be ready
     network::wait_for_link_up(stack).await;
 
     let mut sequence = 0u32;
-    let mut message = TelemetryMessage::new();
+    let mut buf = [0u8; MAX_MESSAGE_SIZE];
+    let heartbeat = watchdog::register("telemetry", Duration::from_secs(2));
 
     loop {
-        // Update message with current data
-        message.sequence = sequence;
-        message.timestamp = embassy_time::Instant::now().as_millis() as u32;
-
-        // TODO: Get actual button and pedal states from shared state
-        // For now, use test values
-        message.button_state = (sequence & 0xFF) as u16; // Test pattern
-        message.throttle = ((sequence * 100) % 4096) as u16; // Simulate ADC value
-        message.brake = ((sequence * 50) % 4096) as u16;
-
-        // Convert to bytes
-        let data = message.to_bytes();
+        heartbeat.beat();
+
+        // Broadcast the full picture: our inputs plus the latest VC/BMS state
+        let snapshot = shared_state.snapshot().await;
+        let message = DataMessage {
+            sw_state: Some(snapshot.sw_state(Instant::now())),
+            vc_state: Some(snapshot.vc),
+            bms_state: Some(snapshot.bms),
+            // Repeated every broadcast so a late listener still sees it
+            crash_report: crash::last_crash().map(CrashRecord::to_report),
+        };
+
+        let len = match message.encode(&mut buf) {
+            Ok(len) => len,
+            Err(e) => {
+                error!("Failed to encode telemetry: {:?}", e);
+                Timer::after(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
 
         // Broadcast telemetry
-        match network::broadcast_telemetry(stack, &data).await {
+        match sender.broadcast_telemetry(&buf[..len]).await {
             Ok(()) => {
                 info!("Telemetry broadcast #{} sent successfully", sequence);
             }
@@ -80,6 +70,11 @@ pub async fn telemetry_task(stack: &'static Stack<'static>) {
             }
         }
 
+        // Report per-destination send counters every 10 broadcasts
+        if sequence.is_multiple_of(10) {
+            sender.log_stats();
+        }
+
         sequence = sequence.wrapping_add(1);
 
         // Wait 1 second before next broadcast
@@ -87,36 +82,50 @@ pub async fn telemetry_task(stack: &'static Stack<'static>) {
     }
 }
 
-/// Steering wheel update task (50ms cycle)
+/// Steering wheel update task
 ///
-/// This will eventually:
-/// - Read button states
-/// - Read ADC values for pedals
-/// - Send updates to VC and BMS
+/// Sends the current button and pedal state from the shared state to the VC
+/// and BMS as soon as it changes, and at least every 50ms so their link
+/// timeouts never trip.
 #[embassy_executor::task]
-pub async fn steering_update_task(stack: &'static Stack<'static>) {
+pub async fn steering_update_task(
+    stack: &'static Stack<'static>,
+    sender: &'static UdpSender,
+    shared_state: &'static SharedState,
+) {
     info!("Starting steering wheel update task");
 
     // Wait for network to be ready
     network::wait_for_link_up(stack).await;
 
     let mut sequence = 0u32;
+    let mut buf = [0u8; MAX_MESSAGE_SIZE];
+    let heartbeat = watchdog::register("steering", Duration::from_millis(100));
+    let mut changes = unwrap!(shared_state.subscribe());
+    let mut next_update = Instant::now();
 
     loop {
-        // Create test message
-        let mut message = TelemetryMessage::new();
-        message.sequence = sequence;
-        message.timestamp = embassy_time::Instant::now().as_millis() as u32;
-
-        // TODO: Get actual states
-        message.button_state = 0x0001; // Test: first button pressed
-        message.throttle = 2048; // Test: 50% throttle
-        message.brake = 0;
-
-        let data = message.to_bytes();
+        heartbeat.beat();
+
+        let snapshot = match select(changes.changed(), Timer::at(next_update)).await {
+            Either::First(snapshot) => snapshot,
+            Either::Second(()) => shared_state.snapshot().await,
+        };
+        let now = Instant::now();
+        next_update = now + STEERING_UPDATE_PERIOD;
+        let sw_state = snapshot.sw_state(now);
+
+        let len = match DataMessage::from_sw_state(sw_state).encode(&mut buf) {
+            Ok(len) => len,
+            Err(e) => {
+                error!("Failed to encode steering update: {:?}", e);
+                continue;
+            }
+        };
+        let data = &buf[..len];
 
         // Send to Vehicle Computer
-        match network::send_to_vc(stack, &data).await {
+        match sender.send_to_vc(data).await {
             Ok(()) => {
                 debug!("Update #{} sent to VC", sequence);
             }
@@ -126,7 +135,7 @@ pub async fn steering_update_task(stack: &'static Stack<'static>) {
         }
 
         // Send to BMS
-        match network::send_to_bms(stack, &data).await {
+        match sender.send_to_bms(data).await {
             Ok(()) => {
                 debug!("Update #{} sent to BMS", sequence);
             }
@@ -137,7 +146,6 @@ pub async fn steering_update_task(stack: &'static Stack<'static>) {
 
         sequence = sequence.wrapping_add(1);
 
-        // Wait 50ms for next update
-        Timer::after(Duration::from_millis(50)).await;
+        Timer::after(STEERING_MIN_GAP).await;
     }
 }
\ No newline at end of file
@@ -0,0 +1,210 @@
+/// Watchdog supervisor - feeds the IWDG only while every task checks in
+///
+/// Tasks register with their own deadline and call `Heartbeat::beat` once per
+/// loop. The supervisor checks all deadlines every `CHECK_PERIOD` and stops
+/// feeding the IWDG as soon as one task is overdue, so a hung SPI transfer or
+/// stuck network task resets the wheel instead of freezing it.
+///
+/// The name of the starved task is written to a `.uninit` RAM record, which
+/// survives the reset and is reported on the next boot.
+///
+/// # Usage
+///
+/// ```no_run
+/// let heartbeat = watchdog::register("display", Duration::from_millis(200));
+/// loop {
+///     heartbeat.beat();
+///     // ...
+/// }
+/// ```
+use core::cell::RefCell;
+use core::mem::MaybeUninit;
+use defmt::*;
+use embassy_stm32::pac;
+use embassy_stm32::peripherals::IWDG;
+use embassy_stm32::wdg::IndependentWatchdog;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant, Ticker};
+
+/// Maximum number of supervised tasks
+///
+/// Nine tasks register today; keep spare slots, since one too many
+/// panics at boot.
+pub const MAX_TASKS: usize = 16;
+
+/// IWDG timeout
+///
+/// Generous, since task deadlines catch a single hung task much sooner; this
+/// only has to catch the supervisor itself (or the whole executor) stalling.
+pub const WATCHDOG_TIMEOUT_US: u32 = 4_000_000;
+
+/// How often the supervisor checks deadlines
+const CHECK_PERIOD: Duration = Duration::from_millis(20);
+
+/// A supervisor tick this late means the whole executor was blocked
+const STALL_THRESHOLD: Duration = Duration::from_millis(200);
+
+const NAME_LEN: usize = 16;
+const RECORD_MAGIC: u32 = 0x5744_4f47; // "WDOG"
+
+struct Slot {
+    name: &'static str,
+    deadline: Duration,
+    last_beat: Instant,
+}
+
+static SLOTS: Mutex<CriticalSectionRawMutex, RefCell<heapless::Vec<Slot, MAX_TASKS>>> =
+    Mutex::new(RefCell::new(heapless::Vec::new()));
+
+/// Handle a supervised task uses to check in
+pub struct Heartbeat {
+    index: usize,
+}
+
+impl Heartbeat {
+    /// Check in - call at least once per deadline
+    pub fn beat(&self) {
+        SLOTS.lock(|slots| {
+            slots.borrow_mut()[self.index].last_beat = Instant::now();
+        });
+    }
+}
+
+/// Register a task that must check in at least every `deadline`
+///
+/// Panics if more than `MAX_TASKS` tasks register.
+pub fn register(name: &'static str, deadline: Duration) -> Heartbeat {
+    let index = SLOTS.lock(|slots| {
+        let mut slots = slots.borrow_mut();
+        let slot = Slot {
+            name,
+            deadline,
+            last_beat: Instant::now(),
+        };
+        if slots.push(slot).is_err() {
+            core::panic!("Too many watchdog tasks");
+        }
+        slots.len() - 1
+    });
+
+    info!("Watchdog: supervising {} (deadline {} ms)", name, deadline.as_millis());
+    Heartbeat { index }
+}
+
+/// First task past its deadline, with how far past it is
+fn find_starved(now: Instant) -> Option<(&'static str, Duration)> {
+    SLOTS.lock(|slots| {
+        slots.borrow().iter().find_map(|slot| {
+            let since = now.saturating_duration_since(slot.last_beat);
+            (since > slot.deadline).then(|| (slot.name, since - slot.deadline))
+        })
+    })
+}
+
+/// Restart every deadline, used after the executor was blocked
+fn restart_deadlines(now: Instant) {
+    SLOTS.lock(|slots| {
+        for slot in slots.borrow_mut().iter_mut() {
+            slot.last_beat = now;
+        }
+    });
+}
+
+/// Starved task record, kept in RAM that is not zeroed on reset
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ResetRecord {
+    magic: u32,
+    overdue_ms: u32,
+    name: [u8; NAME_LEN],
+}
+
+#[link_section = ".uninit.watchdog"]
+static mut RESET_RECORD: MaybeUninit<ResetRecord> = MaybeUninit::uninit();
+
+fn write_record(name: &str, overdue: Duration) {
+    let mut record = ResetRecord {
+        magic: RECORD_MAGIC,
+        overdue_ms: overdue.as_millis().min(u32::MAX as u64) as u32,
+        name: [0; NAME_LEN],
+    };
+    let len = name.len().min(NAME_LEN);
+    record.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+
+    // SAFETY: only the supervisor task touches the record
+    unsafe { core::ptr::addr_of_mut!(RESET_RECORD).write_volatile(MaybeUninit::new(record)) };
+}
+
+/// Read and clear the record left by the previous run
+fn take_record() -> Option<ResetRecord> {
+    // SAFETY: only the supervisor task touches the record; every bit pattern
+    // is a valid `ResetRecord`, garbage is rejected by the magic check
+    unsafe {
+        let ptr = core::ptr::addr_of_mut!(RESET_RECORD);
+        let record = ptr.read_volatile().assume_init();
+        ptr.write_volatile(MaybeUninit::new(ResetRecord {
+            magic: 0,
+            overdue_ms: 0,
+            name: [0; NAME_LEN],
+        }));
+        (record.magic == RECORD_MAGIC).then_some(record)
+    }
+}
+
+/// Report why the previous run ended, if it was the watchdog
+fn report_last_reset() {
+    let csr = pac::RCC.csr().read();
+    let record = take_record();
+    pac::RCC.csr().modify(|w| w.set_rmvf(true));
+
+    if !csr.wdgrstf() {
+        return;
+    }
+
+    match record {
+        Some(record) => {
+            let len = record.name.iter().position(|b| *b == 0).unwrap_or(NAME_LEN);
+            let name = core::str::from_utf8(&record.name[..len]).unwrap_or("?");
+            error!("Watchdog reset: task {} starved ({} ms overdue)", name, record.overdue_ms);
+            log::error!("WATCHDOG RESET: task {} starved ({} ms overdue)", name, record.overdue_ms);
+        }
+        None => {
+            error!("Watchdog reset: executor blocked, no task recorded");
+            log::error!("WATCHDOG RESET: executor blocked, no task recorded");
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn watchdog_task(mut wdg: IndependentWatchdog<'static, IWDG>) {
+    info!("Watchdog task started!");
+    report_last_reset();
+
+    wdg.unleash();
+
+    let mut ticker = Ticker::every(CHECK_PERIOD);
+    let mut last_check = Instant::now();
+
+    loop {
+        let now = Instant::now();
+
+        // Nobody could check in while the executor was blocked
+        if now.saturating_duration_since(last_check) > STALL_THRESHOLD {
+            warn!("Watchdog: executor was blocked, restarting deadlines");
+            restart_deadlines(now);
+        }
+        last_check = now;
+
+        if let Some((name, overdue)) = find_starved(now) {
+            error!("Watchdog: task {} starved ({} ms overdue), resetting", name, overdue.as_millis());
+            write_record(name, overdue);
+
+            // Stop feeding and let the IWDG reset the MCU
+            core::future::pending::<()>().await;
+        }
+
+        wdg.pet();
+        ticker.next().await;
+    }
+}